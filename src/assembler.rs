@@ -0,0 +1,286 @@
+// Hack assembler
+//
+// @value       A-instruction, a constant up to 32767 or a symbol
+// dest=comp;jump
+// (LABEL)      the address of the next instruction
+//
+// Labels are resolved in a first pass, every other unknown symbol is a
+// variable allocated from address 16 in order of first use.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+pub const VARIABLE_BASE: u16 = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Address(AddressValue),
+    Compute { dest: u16, comp: u16, jump: u16 },
+    Label(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressValue {
+    Constant(u16),
+    Symbol(String)
+}
+
+pub fn predefined_symbols() -> BTreeMap<String, u16> {
+    let mut out = BTreeMap::new();
+    for (i, name) in ["SP", "LCL", "ARG", "THIS", "THAT"].iter().enumerate() {
+        out.insert(name.to_string(), i as u16);
+    }
+    for i in 0..16 {
+        out.insert(format!("R{}", i), i);
+    }
+    out.insert("SCREEN".to_string(), 16384);
+    out.insert("KBD".to_string(), 24576);
+    out
+}
+
+// a bit followed by the six c bits
+fn comp_bits(comp: &str) -> Option<u16> {
+    let bits = match comp {
+        "0" => 0b0_101010,
+        "1" => 0b0_111111,
+        "-1" => 0b0_111010,
+        "D" => 0b0_001100,
+        "A" => 0b0_110000,
+        "!D" => 0b0_001101,
+        "!A" => 0b0_110001,
+        "-D" => 0b0_001111,
+        "-A" => 0b0_110011,
+        "D+1" | "1+D" => 0b0_011111,
+        "A+1" | "1+A" => 0b0_110111,
+        "D-1" => 0b0_001110,
+        "A-1" => 0b0_110010,
+        "D+A" | "A+D" => 0b0_000010,
+        "D-A" => 0b0_010011,
+        "A-D" => 0b0_000111,
+        "D&A" | "A&D" => 0b0_000000,
+        "D|A" | "A|D" => 0b0_010101,
+        "M" => 0b1_110000,
+        "!M" => 0b1_110001,
+        "-M" => 0b1_110011,
+        "M+1" | "1+M" => 0b1_110111,
+        "M-1" => 0b1_110010,
+        "D+M" | "M+D" => 0b1_000010,
+        "D-M" => 0b1_010011,
+        "M-D" => 0b1_000111,
+        "D&M" | "M&D" => 0b1_000000,
+        "D|M" | "M|D" => 0b1_010101,
+        _ => return None
+    };
+    Some(bits)
+}
+
+fn dest_bits(dest: &str) -> Option<u16> {
+    let mut out = 0;
+    for c in dest.chars() {
+        let bit = match c {
+            'A' => 0b100,
+            'D' => 0b010,
+            'M' => 0b001,
+            _ => return None
+        };
+        if out & bit != 0 {
+            return None;
+        }
+        out |= bit;
+    }
+    Some(out)
+}
+
+fn jump_bits(jump: &str) -> Option<u16> {
+    let bits = match jump {
+        "JGT" => 0b001,
+        "JEQ" => 0b010,
+        "JGE" => 0b011,
+        "JLT" => 0b100,
+        "JNE" => 0b101,
+        "JLE" => 0b110,
+        "JMP" => 0b111,
+        _ => return None
+    };
+    Some(bits)
+}
+
+pub fn is_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if !c.is_ascii_digit() => {},
+        _ => return false
+    }
+    name.chars().all(|c| c.is_ascii_alphanumeric() || "_.$:".contains(c))
+}
+
+pub fn encode(instruction: &Instruction, symbols: &BTreeMap<String, u16>) -> Option<u16> {
+    match instruction {
+        Instruction::Address(AddressValue::Constant(x)) => Some(*x),
+        Instruction::Address(AddressValue::Symbol(x)) => symbols.get(x).cloned(),
+        Instruction::Compute { dest, comp, jump } => Some(0b111 << 13 | comp << 6 | dest << 3 | jump),
+        Instruction::Label(_) => None
+    }
+}
+
+// Parses one line, None for lines without an instruction
+pub fn parse_line(file: &str, line: usize, text: &str) -> Result<Option<Instruction>, AsmError> {
+    let error = |message: String| AsmError { file: file.to_string(), line, message };
+    let text = match text.find("//") {
+        Some(i) => &text[..i],
+        None => text
+    };
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    if let Some(value) = text.strip_prefix('@') {
+        if value.starts_with(|c: char| c.is_ascii_digit()) {
+            return match value.parse::<u16>() {
+                Ok(x) if x <= 0x7fff => Ok(Some(Instruction::Address(AddressValue::Constant(x)))),
+                _ => Err(error(format!("invalid constant '{}'", value)))
+            };
+        }
+        if !is_symbol(value) {
+            return Err(error(format!("invalid symbol '{}'", value)));
+        }
+        return Ok(Some(Instruction::Address(AddressValue::Symbol(value.to_string()))));
+    }
+    if let Some(label) = text.strip_prefix('(') {
+        let label = match label.strip_suffix(')') {
+            Some(x) => x,
+            None => return Err(error(format!("expected ')' after label '{}'", label)))
+        };
+        if !is_symbol(label) {
+            return Err(error(format!("invalid label '{}'", label)));
+        }
+        return Ok(Some(Instruction::Label(label.to_string())));
+    }
+
+    let (dest, rest) = match text.find('=') {
+        Some(i) => (Some(&text[..i]), &text[i + 1..]),
+        None => (None, &text[..])
+    };
+    let (comp, jump) = match rest.find(';') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None)
+    };
+    let dest = match dest {
+        Some(x) => match dest_bits(x) {
+            Some(bits) if bits != 0 => bits,
+            _ => return Err(error(format!("unknown destination '{}'", x)))
+        },
+        None => 0
+    };
+    let comp = match comp_bits(comp) {
+        Some(x) => x,
+        None => return Err(error(format!("unknown computation '{}'", comp)))
+    };
+    let jump = match jump {
+        Some(x) => match jump_bits(x) {
+            Some(x) => x,
+            None => return Err(error(format!("unknown jump '{}'", x)))
+        },
+        None => 0
+    };
+    Ok(Some(Instruction::Compute { dest, comp, jump }))
+}
+
+// Parses a whole program keeping the line of every instruction
+pub fn parse_asm(file: &str, source: &str) -> Result<Vec<(usize, Instruction)>, AsmError> {
+    let mut out = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        if let Some(x) = parse_line(file, i + 1, text)? {
+            out.push((i + 1, x));
+        }
+    }
+    Ok(out)
+}
+
+pub fn assemble(file: &str, source: &str) -> Result<Vec<u16>, AsmError> {
+    let program = parse_asm(file, source)?;
+
+    let mut symbols = predefined_symbols();
+    let mut address = 0;
+    for (line, instruction) in &program {
+        match instruction {
+            Instruction::Label(name) => {
+                if symbols.contains_key(name) {
+                    return Err(AsmError { file: file.to_string(), line: *line, message: format!("symbol '{}' is already defined", name) });
+                }
+                symbols.insert(name.clone(), address);
+            },
+            _ => address += 1
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut next_variable = VARIABLE_BASE;
+    for (_, instruction) in &program {
+        if let Instruction::Address(AddressValue::Symbol(name)) = instruction {
+            if !symbols.contains_key(name) {
+                symbols.insert(name.clone(), next_variable);
+                next_variable += 1;
+            }
+        }
+        if let Some(x) = encode(instruction, &symbols) {
+            out.push(x);
+        }
+    }
+    Ok(out)
+}
+
+// .hack text, one 16 digit binary word per line
+pub fn to_hack(words: &[u16]) -> String {
+    let mut out = String::new();
+    for word in words {
+        out.push_str(&format!("{:016b}\n", word));
+    }
+    out
+}
+
+pub fn parse_hack(file: &str, source: &str) -> Result<Vec<u16>, AsmError> {
+    let mut out = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if text.len() != 16 || !text.chars().all(|c| c == '0' || c == '1') {
+            return Err(AsmError { file: file.to_string(), line: i + 1, message: format!("invalid machine word '{}'", text) });
+        }
+        out.push(u16::from_str_radix(text, 2).unwrap());
+    }
+    Ok(out)
+}
+
+pub fn assemble_file<P: AsRef<Path>>(path: P) -> Result<Vec<u16>, AsmError> {
+    let path = path.as_ref();
+    let file = path.display().to_string();
+    let source = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => return Err(AsmError { file, line: 0, message: e.to_string() })
+    };
+    assemble(&file, &source)
+}
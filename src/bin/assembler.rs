@@ -0,0 +1,32 @@
+use std::env;
+use std::fs;
+use std::process;
+use sunho_computer::assembler::{assemble_file, to_hack};
+
+// usage: assembler <file.asm>... writes file.hack next to every file
+fn main() {
+    let files: Vec<String> = env::args().skip(1).collect();
+    if files.is_empty() {
+        eprintln!("usage: assembler <file.asm>...");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for file in &files {
+        let words = match assemble_file(file) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+                continue;
+            }
+        };
+        let out = std::path::Path::new(file).with_extension("hack");
+        if let Err(e) = fs::write(&out, to_hack(&words)) {
+            eprintln!("{}: {}", out.display(), e);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
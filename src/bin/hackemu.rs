@@ -0,0 +1,124 @@
+use std::env;
+use std::process;
+use std::time::Instant;
+use sunho_computer::hack::{load_program, HackEmulator, Lockstep};
+use sunho_computer::keyboard::KeyScript;
+use sunho_computer::screen::{Frame, FrameDumper};
+
+const USAGE: &str = "usage: hackemu <file.hack | file.asm> [cycles] [--lockstep] [--screen <file>] [--frames <directory> <every>] [--format <png | pbm>] [--keys <file>]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+// Runs the program and prints the registers and the first 16 words of RAM.
+// With --lockstep the gate level computer runs along and the first cycle
+// where they differ is reported. --screen saves the last frame, as PNG or PBM
+// by extension, and --frames saves one every <every> cycles in the --format
+// given, PNG by default. --keys drives the keyboard with a script, see
+// keyboard.rs.
+fn main() {
+    let mut lockstep = false;
+    let mut screen = None;
+    let mut frames = None;
+    let mut format = "png".to_string();
+    let mut keys = None;
+    let mut positional = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lockstep" => lockstep = true,
+            "--keys" => keys = Some(args.next().unwrap_or_else(|| usage())),
+            "--screen" => screen = Some(args.next().unwrap_or_else(|| usage())),
+            "--frames" => {
+                let directory = args.next().unwrap_or_else(|| usage());
+                let every = args.next().and_then(|x| x.parse::<u64>().ok()).unwrap_or_else(|| usage());
+                frames = Some((directory, every));
+            },
+            "--format" => {
+                format = args.next().unwrap_or_else(|| usage());
+                if format != "png" && format != "pbm" {
+                    usage();
+                }
+            },
+            _ => positional.push(arg)
+        }
+    }
+    if positional.is_empty() || positional.len() > 2 {
+        usage();
+    }
+    let cycles = match positional.get(1).map(|x| x.parse::<u64>()) {
+        None => 1_000_000,
+        Some(Ok(x)) => x,
+        Some(Err(_)) => usage()
+    };
+    let program = match load_program(&positional[0]) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let mut script = match keys.map(KeyScript::load) {
+        Some(Ok(x)) => x,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        },
+        None => KeyScript::new(Vec::new())
+    };
+    let mut dumper = match frames {
+        Some((directory, every)) => match FrameDumper::new(&directory, &format, every) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                eprintln!("{}: {}", directory, e);
+                process::exit(1);
+            }
+        },
+        None => None
+    };
+    let mut dump = |emulator: &HackEmulator| {
+        if let Some(dumper) = dumper.as_mut() {
+            if let Err(e) = dumper.cycle(emulator.cycles(), emulator) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    };
+
+    let start = Instant::now();
+    let emulator = if lockstep {
+        let mut both = Lockstep::new(&program);
+        for _ in 0..cycles {
+            script.drive(both.emulator.cycles(), &mut both);
+            if let Err(e) = both.step() {
+                println!("{}", e);
+                process::exit(1);
+            }
+            dump(&both.emulator);
+        }
+        both.emulator
+    } else {
+        let mut emulator = HackEmulator::new(&program);
+        for _ in 0..cycles {
+            script.drive(emulator.cycles(), &mut emulator);
+            emulator.step();
+            dump(&emulator);
+        }
+        emulator
+    };
+    let seconds = start.elapsed().as_secs_f64();
+
+    if let Some(path) = screen {
+        if let Err(e) = Frame::new(emulator.screen()).save(&path) {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    }
+    println!("{} cycles in {:.3}s ({:.0} per second)", cycles, seconds, cycles as f64 / seconds.max(1e-9));
+    println!("PC={} A={} D={}", emulator.pc(), emulator.a(), emulator.d());
+    for (i, word) in emulator.ram()[..16].iter().enumerate() {
+        println!("RAM[{}]={}", i, *word as i16);
+    }
+}
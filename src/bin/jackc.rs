@@ -0,0 +1,33 @@
+use std::env;
+use std::fs;
+use std::process;
+use sunho_computer::jack::compile_path;
+
+// usage: jackc <file.jack | directory>... writes a .vm file next to every class
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: jackc <file.jack | directory>...");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in &paths {
+        let files = match compile_path(path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+                continue;
+            }
+        };
+        for (out, vm) in files {
+            if let Err(e) = fs::write(&out, vm) {
+                eprintln!("{}: {}", out.display(), e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
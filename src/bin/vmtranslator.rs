@@ -0,0 +1,43 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use sunho_computer::vm::translate_path;
+
+// usage: vmtranslator <file.vm | directory>...
+// File.vm becomes File.asm, a directory Dir becomes Dir/Dir.asm
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: vmtranslator <file.vm | directory>...");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in &paths {
+        let path = Path::new(path);
+        let asm = match translate_path(path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+                continue;
+            }
+        };
+        let out: PathBuf = if path.is_dir() {
+            let name = path.canonicalize()
+                .ok()
+                .and_then(|x| x.file_name().map(|x| x.to_string_lossy().to_string()))
+                .unwrap_or_else(|| "out".to_string());
+            path.join(name).with_extension("asm")
+        } else {
+            path.with_extension("asm")
+        };
+        if let Err(e) = fs::write(&out, asm) {
+            eprintln!("{}: {}", out.display(), e);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,80 @@
+use super::GateFactoryFunction;
+use crate::build_gate_function;
+use crate::connect;
+
+pub fn gate_halfadder() -> GateFactoryFunction {
+    build_gate_function! {
+        halfadder(a, b => sum, carry):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, xor { a=a, b=b } => { out=sum });
+                connect!(g, f, and { a=a, b=b } => { out=carry });
+            }
+    }
+}
+
+pub fn gate_fulladder() -> GateFactoryFunction {
+    build_gate_function! {
+        fulladder(a, b, c => sum, carry):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, halfadder { a=a, b=b } => { sum=sumab, carry=carryab });
+                connect!(g, f, halfadder { a=sumab, b=c } => { sum=sum, carry=carryc });
+                connect!(g, f, or { a=carryab, b=carryc } => { out=carry });
+            }
+    }
+}
+
+pub fn gate_add16() -> GateFactoryFunction {
+    build_gate_function! {
+        add16(a[16], b[16] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                // carry[i] is the carry out of bit i, the last one overflows
+                for i in 0..16 {
+                    g.insert_pin(PinKind::Internal, "carry", 16, i);
+                }
+                connect!(g, f, halfadder { a=a[0], b=b[0] } => { sum=out[0], carry=carry[0] });
+                for i in 1..16 {
+                    connect!(g, f, fulladder { a=a[i], b=b[i], c=carry[i - 1] } => { sum=out[i], carry=carry[i] });
+                }
+            }
+    }
+}
+
+pub fn gate_inc16() -> GateFactoryFunction {
+    build_gate_function! {
+        inc16(in[16] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, add16 { a=in, b[0]=true, b[1, 16]=false } => { out=out });
+            }
+    }
+}
+
+// zx, zy: zero the x, y input
+// nx, ny: negate the x, y input
+// f: compute x + y when set, x & y otherwise
+// no: negate the output
+// zr: out == 0, ng: out < 0
+pub fn gate_alu() -> GateFactoryFunction {
+    build_gate_function! {
+        alu(x[16], y[16], zx, nx, zy, ny, f, no => out[16], zr, ng):
+            | g: &mut Gate, factory: &GateFactory | {
+                connect!(g, factory, mux16 { a=x, b=false, sel=zx } => { out=zerox });
+                connect!(g, factory, not16 { in=zerox } => { out=notzerox });
+                connect!(g, factory, mux16 { a=zerox, b=notzerox, sel=nx } => { out=finalx });
+
+                connect!(g, factory, mux16 { a=y, b=false, sel=zy } => { out=zeroy });
+                connect!(g, factory, not16 { in=zeroy } => { out=notzeroy });
+                connect!(g, factory, mux16 { a=zeroy, b=notzeroy, sel=ny } => { out=finaly });
+
+                connect!(g, factory, and16 { a=finalx, b=finaly } => { out=andxy });
+                connect!(g, factory, add16 { a=finalx, b=finaly } => { out=addxy });
+                connect!(g, factory, mux16 { a=andxy, b=addxy, sel=f } => { out=result });
+
+                connect!(g, factory, not16 { in=result } => { out=notresult });
+                connect!(g, factory, mux16 { a=result, b=notresult, sel=no } => { out=out, out=finalout, out[15]=ng });
+
+                connect!(g, factory, or8way { in=finalout[0, 8] } => { out=nonzerolow });
+                connect!(g, factory, or8way { in=finalout[8, 16] } => { out=nonzerohigh });
+                connect!(g, factory, nor { a=nonzerolow, b=nonzerohigh } => { out=zr });
+            }
+    }
+}
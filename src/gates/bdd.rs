@@ -0,0 +1,317 @@
+// Reduced ordered binary decision diagrams
+//
+// A node tests a variable and goes to `low` when it is false and to `high`
+// when it is true, variables are tested in increasing order on every path.
+// Nodes are shared through a unique table, so two functions are the same
+// exactly when they are the same node. That makes equivalence checking of
+// chips with too many inputs for a truth table a comparison of nodes.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::gates::gate::{Gate, PinKind};
+use crate::gates::netlist::{CellKind, Netlist, TRUE};
+use crate::gates::truth::{Counterexample, TruthTableError};
+use crate::gates::utils::PinValues;
+
+pub type BddNode = u32;
+
+pub const BDD_FALSE: BddNode = 0;
+pub const BDD_TRUE: BddNode = 1;
+
+// nodes at which building stops, about 300MB
+pub const MAX_BDD_NODES: usize = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Operation {
+    And,
+    Or,
+    Xor
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bdd {
+    // (variable, low, high), the terminals have the variable u32::MAX
+    nodes: Vec<(u32, BddNode, BddNode)>,
+    unique: HashMap<(u32, BddNode, BddNode), BddNode>,
+    cache: HashMap<(Operation, BddNode, BddNode), BddNode>
+}
+
+impl Bdd {
+    pub fn new() -> Bdd {
+        Bdd {
+            nodes: vec![(u32::MAX, BDD_FALSE, BDD_FALSE), (u32::MAX, BDD_TRUE, BDD_TRUE)],
+            unique: HashMap::new(),
+            cache: HashMap::new()
+        }
+    }
+
+    // nodes created so far, the terminals included
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn node(&mut self, variable: u32, low: BddNode, high: BddNode) -> BddNode {
+        if low == high {
+            return low;
+        }
+        let next = self.nodes.len() as BddNode;
+        let out = *self.unique.entry((variable, low, high)).or_insert(next);
+        if out == next {
+            self.nodes.push((variable, low, high));
+        }
+        out
+    }
+
+    pub fn variable(&mut self, variable: u32) -> BddNode {
+        self.node(variable, BDD_FALSE, BDD_TRUE)
+    }
+
+    // variable tested by a node, None for the terminals
+    pub fn top(&self, f: BddNode) -> Option<u32> {
+        let (variable, _, _) = self.nodes[f as usize];
+        if variable == u32::MAX { None } else { Some(variable) }
+    }
+
+    // (low, high) of a node that is not a terminal
+    pub fn children(&self, f: BddNode) -> (BddNode, BddNode) {
+        let (_, low, high) = self.nodes[f as usize];
+        (low, high)
+    }
+
+    fn apply(&mut self, operation: Operation, f: BddNode, g: BddNode) -> BddNode {
+        match operation {
+            Operation::And => {
+                if f == BDD_FALSE || g == BDD_FALSE {
+                    return BDD_FALSE;
+                }
+                if f == BDD_TRUE || f == g {
+                    return g;
+                }
+                if g == BDD_TRUE {
+                    return f;
+                }
+            },
+            Operation::Or => {
+                if f == BDD_TRUE || g == BDD_TRUE {
+                    return BDD_TRUE;
+                }
+                if f == BDD_FALSE || f == g {
+                    return g;
+                }
+                if g == BDD_FALSE {
+                    return f;
+                }
+            },
+            Operation::Xor => {
+                if f == g {
+                    return BDD_FALSE;
+                }
+                if f == BDD_FALSE {
+                    return g;
+                }
+                if g == BDD_FALSE {
+                    return f;
+                }
+                if f == BDD_TRUE && g == BDD_TRUE {
+                    return BDD_FALSE;
+                }
+            }
+        }
+        // every operation is commutative
+        let (f, g) = if f < g { (f, g) } else { (g, f) };
+        if let Some(x) = self.cache.get(&(operation, f, g)) {
+            return *x;
+        }
+        let (fv, f0, f1) = self.nodes[f as usize];
+        let (gv, g0, g1) = self.nodes[g as usize];
+        let variable = fv.min(gv);
+        let (f0, f1) = if fv == variable { (f0, f1) } else { (f, f) };
+        let (g0, g1) = if gv == variable { (g0, g1) } else { (g, g) };
+        let low = self.apply(operation, f0, g0);
+        let high = self.apply(operation, f1, g1);
+        let out = self.node(variable, low, high);
+        self.cache.insert((operation, f, g), out);
+        out
+    }
+
+    pub fn and(&mut self, f: BddNode, g: BddNode) -> BddNode {
+        self.apply(Operation::And, f, g)
+    }
+
+    pub fn or(&mut self, f: BddNode, g: BddNode) -> BddNode {
+        self.apply(Operation::Or, f, g)
+    }
+
+    pub fn xor(&mut self, f: BddNode, g: BddNode) -> BddNode {
+        self.apply(Operation::Xor, f, g)
+    }
+
+    pub fn not(&mut self, f: BddNode) -> BddNode {
+        self.apply(Operation::Xor, f, BDD_TRUE)
+    }
+
+    pub fn nand(&mut self, f: BddNode, g: BddNode) -> BddNode {
+        let x = self.and(f, g);
+        self.not(x)
+    }
+
+    // value of a function where variable i is assignment[i], missing
+    // variables are false
+    pub fn eval(&self, mut f: BddNode, assignment: &[bool]) -> bool {
+        while f > BDD_TRUE {
+            let (variable, low, high) = self.nodes[f as usize];
+            f = if assignment.get(variable as usize).copied().unwrap_or(false) { high } else { low };
+        }
+        f == BDD_TRUE
+    }
+
+    // An assignment that makes the function true, preferring false for every
+    // variable. Variables the function does not test are left out.
+    pub fn satisfy(&self, mut f: BddNode) -> Option<BTreeMap<u32, bool>> {
+        if f == BDD_FALSE {
+            return None;
+        }
+        let mut out = BTreeMap::new();
+        while f > BDD_TRUE {
+            let (variable, low, high) = self.nodes[f as usize];
+            // every node other than false has a path to true
+            if low != BDD_FALSE {
+                out.insert(variable, false);
+                f = low;
+            } else {
+                out.insert(variable, true);
+                f = high;
+            }
+        }
+        Some(out)
+    }
+
+    // nodes reachable from a function, the terminals included
+    pub fn size(&self, f: BddNode) -> usize {
+        let mut seen = HashSet::new();
+        let mut stack = vec![f];
+        while let Some(x) = stack.pop() {
+            if !seen.insert(x) {
+                continue;
+            }
+            if x > BDD_TRUE {
+                let (_, low, high) = self.nodes[x as usize];
+                stack.push(low);
+                stack.push(high);
+            }
+        }
+        seen.len()
+    }
+}
+
+// Input bits in the order of the variables. One bit pins like the control
+// bits of the alu come first, then the bits of the buses interleaved by
+// index: a[0] b[0] a[1] b[1] ... which keeps adders and comparators linear.
+pub fn variable_order(gate: &Gate) -> Vec<(String, i64)> {
+    let mut pins: Vec<(String, i64)> = Vec::new();
+    for name in gate.pin_names() {
+        if let Some(pin) = gate.get_pin(name, 0) {
+            if pin.kind == PinKind::Input && !pins.iter().any(|(x, _)| x == name) {
+                pins.push((name.clone(), pin.size));
+            }
+        }
+    }
+    let mut out: Vec<(String, i64)> = pins.iter().filter(|(_, size)| *size == 1).map(|(name, _)| (name.clone(), 0)).collect();
+    let width = pins.iter().map(|(_, size)| *size).max().unwrap_or(0);
+    for index in 0..width {
+        for (name, size) in pins.iter().filter(|(_, size)| *size > 1) {
+            if index < *size {
+                out.push((name.clone(), index));
+            }
+        }
+    }
+    out
+}
+
+// Functions of every output bit of a combinational netlist, `variable` gives
+// the variable of an input bit
+pub fn symbolic_outputs(bdd: &mut Bdd, netlist: &Netlist, variable: &dyn Fn(&str, i64) -> Option<u32>) -> Result<Vec<Vec<BddNode>>, String> {
+    let mut values = vec![BDD_FALSE; netlist.net_count()];
+    values[TRUE] = BDD_TRUE;
+    for port in &netlist.inputs {
+        for (i, net) in port.nets.iter().enumerate() {
+            values[*net] = match variable(&port.name, i as i64) {
+                Some(x) => bdd.variable(x),
+                None => return Err(format!("no variable for {}[{}]", port.name, i))
+            };
+        }
+    }
+    let order = netlist.order().map_err(|_| "has a combinational loop".to_string())?;
+    for i in order {
+        let cell = &netlist.cells[i];
+        match &cell.kind {
+            CellKind::Nand => values[cell.outputs[0]] = bdd.nand(values[cell.inputs[0]], values[cell.inputs[1]]),
+            CellKind::Dff => return Err("is not combinational".to_string()),
+            CellKind::Builtin { chip, .. } => return Err(format!("uses the builtin chip {}", chip))
+        }
+        if bdd.node_count() > MAX_BDD_NODES {
+            return Err(format!("needs more than {} nodes", MAX_BDD_NODES));
+        }
+    }
+    Ok(netlist.outputs.iter().map(|port| port.nets.iter().map(|x| values[*x]).collect()).collect())
+}
+
+fn signature(gate: &Gate, kind: PinKind) -> Vec<(String, i64)> {
+    let mut out: Vec<(String, i64)> = gate.pins()
+        .filter(|x| x.kind == kind && x.index == 0)
+        .map(|x| (x.name.clone(), x.size))
+        .collect();
+    out.sort();
+    out
+}
+
+// Proves two combinational gates equivalent without enumerating their
+// inputs, None if they are, a counterexample otherwise
+pub fn prove_equivalence(a: &Gate, b: &Gate) -> Result<Option<Counterexample>, TruthTableError> {
+    let error = |chip: &Gate, message: String| TruthTableError { chip: chip.name.clone(), message };
+    if signature(a, PinKind::Input) != signature(b, PinKind::Input) {
+        return Err(error(a, format!("inputs differ from those of {}", b.name)));
+    }
+    if signature(a, PinKind::Output) != signature(b, PinKind::Output) {
+        return Err(error(a, format!("outputs differ from those of {}", b.name)));
+    }
+    let order = variable_order(a);
+    let variable = |name: &str, index: i64| order.iter().position(|(x, i)| x == name && *i == index).map(|x| x as u32);
+
+    let mut bdd = Bdd::new();
+    let netlist_a = Netlist::flatten(a);
+    let netlist_b = Netlist::flatten(b);
+    let outputs_a = symbolic_outputs(&mut bdd, &netlist_a, &variable).map_err(|x| error(a, x))?;
+    let outputs_b = symbolic_outputs(&mut bdd, &netlist_b, &variable).map_err(|x| error(b, x))?;
+
+    // the first output bit that differs in declaration order of a
+    for (port, functions) in netlist_a.outputs.iter().zip(&outputs_a) {
+        let j = netlist_b.outputs.iter().position(|x| x.name == port.name).unwrap();
+        for (index, f) in functions.iter().enumerate() {
+            let g = outputs_b[j][index];
+            if *f == g {
+                continue;
+            }
+            let difference = bdd.xor(*f, g);
+            let assignment = bdd.satisfy(difference).unwrap();
+            let bits: Vec<bool> = (0..order.len() as u32).map(|x| assignment.get(&x).copied().unwrap_or(false)).collect();
+            let mut inputs = PinValues::new();
+            for ((name, index), value) in order.iter().zip(&bits) {
+                inputs.set(name, *index, *value);
+            }
+            let values = |netlist: &Netlist, outputs: &[Vec<BddNode>]| {
+                let mut out = PinValues::new();
+                for (port, functions) in netlist.outputs.iter().zip(outputs) {
+                    for (i, f) in functions.iter().enumerate() {
+                        out.set(&port.name, i as i64, bdd.eval(*f, &bits));
+                    }
+                }
+                out
+            };
+            let expected = values(&netlist_a, &outputs_a);
+            let actual = values(&netlist_b, &outputs_b);
+            return Ok(Some(Counterexample::new(a, b, inputs, expected, actual)));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,223 @@
+// Chips that are implemented natively instead of with gates, like the builtin
+// chips of the nand2tetris simulator. ROM32K, Screen and Keyboard only exist
+// here, and the RAM4K/RAM16K built from registers need gigabytes, so
+// GateFactory::with_builtin_memory replaces them with these.
+
+use crate::gates::utils::PinValues;
+use crate::gates::gate::PrimitiveGateImplementor;
+use super::factory::GateFactoryFunction;
+
+use crate::build_gate_function;
+
+fn read_number(values: &[bool], start: usize, size: usize) -> usize {
+    let mut out = 0;
+    for i in 0..size {
+        if values[start + i] {
+            out |= 1 << i;
+        }
+    }
+    out
+}
+
+fn write_number(values: &mut [bool], start: usize, size: usize, value: u16) {
+    for i in 0..size {
+        values[start + i] = (value >> i) & 1 == 1;
+    }
+}
+
+// (in[16], load, address[n] => out[16])
+// out is the word at address, writes happen on the clock like a register
+#[derive(Debug)]
+struct RamImplementor {
+    words: Vec<u16>,
+    address_size: usize,
+    pending: Option<(usize, u16)>,
+    next: Option<(usize, u16)>
+}
+
+impl RamImplementor {
+    fn new(address_size: usize) -> RamImplementor {
+        RamImplementor {
+            words: vec![0; 1 << address_size],
+            address_size,
+            pending: None,
+            next: None
+        }
+    }
+}
+
+impl PrimitiveGateImplementor for RamImplementor {
+    fn run(&mut self, inputs: PinValues) -> PinValues {
+        let address = inputs.get_number("address", self.address_size as i64) as usize;
+        self.pending = if inputs.get("load", 0) {
+            Some((address, inputs.get_number("in", 16) as u16))
+        } else {
+            None
+        };
+        let mut out = PinValues::new();
+        out.set_number("out", 16, self.words[address] as u64);
+        out
+    }
+
+    // in, load, address, out
+    fn run_values(&mut self, values: &mut [bool]) -> bool {
+        let address = read_number(values, 17, self.address_size);
+        self.pending = if values[16] {
+            Some((address, read_number(values, 0, 16) as u16))
+        } else {
+            None
+        };
+        write_number(values, 17 + self.address_size, 16, self.words[address]);
+        true
+    }
+
+    fn dependencies(&self, _output: &str) -> Option<Vec<String>> {
+        Some(vec!["address".to_string()])
+    }
+
+    fn stateful(&self) -> bool {
+        true
+    }
+
+    fn tick(&mut self) {
+        self.next = self.pending;
+    }
+
+    fn tock(&mut self) {
+        if let Some((address, value)) = self.next.take() {
+            self.words[address] = value;
+        }
+    }
+
+    fn memory(&self) -> Option<&[u16]> {
+        Some(&self.words)
+    }
+
+    fn memory_mut(&mut self) -> Option<&mut [u16]> {
+        Some(&mut self.words)
+    }
+}
+
+// (address[15] => out[16]), the program is loaded through memory_mut. Like
+// the keyboard it counts as stateful because its words can change between
+// clock cycles.
+#[derive(Debug)]
+struct RomImplementor {
+    words: Vec<u16>
+}
+
+impl PrimitiveGateImplementor for RomImplementor {
+    fn run(&mut self, inputs: PinValues) -> PinValues {
+        let address = inputs.get_number("address", 15) as usize;
+        let mut out = PinValues::new();
+        out.set_number("out", 16, self.words[address] as u64);
+        out
+    }
+
+    // address, out
+    fn run_values(&mut self, values: &mut [bool]) -> bool {
+        let address = read_number(values, 0, 15);
+        write_number(values, 15, 16, self.words[address]);
+        true
+    }
+
+    fn stateful(&self) -> bool {
+        true
+    }
+
+    fn memory(&self) -> Option<&[u16]> {
+        Some(&self.words)
+    }
+
+    fn memory_mut(&mut self) -> Option<&mut [u16]> {
+        Some(&mut self.words)
+    }
+}
+
+// ( => out[16]), the scan code of the pressed key is set through memory_mut
+#[derive(Debug, Default)]
+struct KeyboardImplementor {
+    key: [u16; 1]
+}
+
+impl PrimitiveGateImplementor for KeyboardImplementor {
+    fn run(&mut self, _inputs: PinValues) -> PinValues {
+        let mut out = PinValues::new();
+        out.set_number("out", 16, self.key[0] as u64);
+        out
+    }
+
+    fn run_values(&mut self, values: &mut [bool]) -> bool {
+        write_number(values, 0, 16, self.key[0]);
+        true
+    }
+
+    fn stateful(&self) -> bool {
+        true
+    }
+
+    fn state(&self) -> PinValues {
+        let mut out = PinValues::new();
+        out.set_number("out", 16, self.key[0] as u64);
+        out
+    }
+
+    fn state_values(&self, values: &mut [bool]) -> bool {
+        write_number(values, 0, 16, self.key[0]);
+        true
+    }
+
+    fn memory(&self) -> Option<&[u16]> {
+        Some(&self.key)
+    }
+
+    fn memory_mut(&mut self) -> Option<&mut [u16]> {
+        Some(&mut self.key)
+    }
+}
+
+pub fn gate_rom32k() -> GateFactoryFunction {
+    build_gate_function! {
+        rom32k(address[15] => out[16]):
+            | g: &mut Gate, _f: &GateFactory | {
+               g.primitive_implementor = Some(Box::new(RomImplementor { words: vec![0; 1 << 15] }))
+            }
+    }
+}
+
+// 256 rows of 512 pixels, 32 words per row, bit 0 is the leftmost pixel
+pub fn gate_screen() -> GateFactoryFunction {
+    build_gate_function! {
+        screen(in[16], load, address[13] => out[16]):
+            | g: &mut Gate, _f: &GateFactory | {
+               g.primitive_implementor = Some(Box::new(RamImplementor::new(13)))
+            }
+    }
+}
+
+pub fn gate_keyboard() -> GateFactoryFunction {
+    build_gate_function! {
+        keyboard( => out[16]):
+            | g: &mut Gate, _f: &GateFactory | {
+               g.primitive_implementor = Some(Box::new(KeyboardImplementor::default()))
+            }
+    }
+}
+
+pub fn gate_builtin_ram4k() -> GateFactoryFunction {
+    build_gate_function! {
+        ram4k(in[16], load, address[12] => out[16]):
+            | g: &mut Gate, _f: &GateFactory | {
+               g.primitive_implementor = Some(Box::new(RamImplementor::new(12)))
+            }
+    }
+}
+
+pub fn gate_builtin_ram16k() -> GateFactoryFunction {
+    build_gate_function! {
+        ram16k(in[16], load, address[14] => out[16]):
+            | g: &mut Gate, _f: &GateFactory | {
+               g.primitive_implementor = Some(Box::new(RamImplementor::new(14)))
+            }
+    }
+}
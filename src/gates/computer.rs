@@ -0,0 +1,72 @@
+use super::GateFactoryFunction;
+use super::builtins::gate_builtin_ram16k;
+use crate::build_gate_function;
+use crate::connect;
+
+// instruction bits, 0 is the least significant:
+// 15: C instruction, 12: a, 11..6: zx nx zy ny f no, 5..3: dest A D M,
+// 2..0: jump if negative, zero, positive
+pub fn gate_cpu() -> GateFactoryFunction {
+    build_gate_function! {
+        cpu(inM[16], instruction[16], reset => outM[16], writeM, addressM[15], pc[15]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, not { in=instruction[15] } => { out=ainstruction });
+                connect!(g, f, mux16 { a=instruction, b=aluout, sel=instruction[15] } => { out=ain });
+                connect!(g, f, and { a=instruction[15], b=instruction[5] } => { out=destA });
+                connect!(g, f, or { a=ainstruction, b=destA } => { out=loadA });
+                connect!(g, f, register { in=ain, load=loadA } => { out=areg, out[0, 15]=addressM });
+
+                connect!(g, f, and { a=instruction[15], b=instruction[4] } => { out=loadD });
+                connect!(g, f, register { in=aluout, load=loadD } => { out=dreg });
+
+                connect!(g, f, mux16 { a=areg, b=inM, sel=instruction[12] } => { out=aorm });
+                connect!(g, f, alu {
+                    x=dreg, y=aorm,
+                    zx=instruction[11], nx=instruction[10], zy=instruction[9],
+                    ny=instruction[8], f=instruction[7], no=instruction[6]
+                } => { out=aluout, out=outM, zr=zr, ng=ng });
+                connect!(g, f, and { a=instruction[15], b=instruction[3] } => { out=writeM });
+
+                connect!(g, f, or { a=zr, b=ng } => { out=notpositive });
+                connect!(g, f, not { in=notpositive } => { out=positive });
+                connect!(g, f, and { a=instruction[2], b=ng } => { out=jlt });
+                connect!(g, f, and { a=instruction[1], b=zr } => { out=jeq });
+                connect!(g, f, and { a=instruction[0], b=positive } => { out=jgt });
+                connect!(g, f, or { a=jlt, b=jeq } => { out=jle });
+                connect!(g, f, or { a=jle, b=jgt } => { out=jump });
+                connect!(g, f, and { a=instruction[15], b=jump } => { out=loadpc });
+                connect!(g, f, pc { in=areg, load=loadpc, inc=true, reset=reset } => { out[0, 15]=pc });
+            }
+    }
+}
+
+// 0x0000-0x3fff: ram16k, 0x4000-0x5fff: screen, 0x6000: keyboard
+// The ram16k is always the builtin one, the one built from registers needs
+// gigabytes.
+pub fn gate_memory() -> GateFactoryFunction {
+    build_gate_function! {
+        memory(in[16], load, address[15] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                let mut builtin = GateFactory::default();
+                builtin.register(gate_builtin_ram16k);
+                connect!(g, f, dmux4way { in=load, sel=address[13, 15] } => { a=loadram0, b=loadram1, c=loadscreen });
+                connect!(g, f, or { a=loadram0, b=loadram1 } => { out=loadram });
+                connect!(g, builtin, ram16k { in=in, load=loadram, address=address[0, 14] } => { out=ramout });
+                connect!(g, f, screen { in=in, load=loadscreen, address=address[0, 13] } => { out=screenout });
+                connect!(g, f, keyboard { } => { out=keyboardout });
+                connect!(g, f, mux4way16 { a=ramout, b=ramout, c=screenout, d=keyboardout, sel=address[13, 15] } => { out=out });
+            }
+    }
+}
+
+// The program is loaded into the rom32k part through Gate::memory_mut.
+pub fn gate_computer() -> GateFactoryFunction {
+    build_gate_function! {
+        computer(reset => ):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, rom32k { address=pc } => { out=instruction });
+                connect!(g, f, cpu { inM=memout, instruction=instruction, reset=reset } => { outM=outM, writeM=writeM, addressM=addressM, pc=pc });
+                connect!(g, f, memory { in=outM, load=writeM, address=addressM } => { out=memout });
+            }
+    }
+}
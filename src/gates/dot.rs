@@ -0,0 +1,210 @@
+// Graphviz export of a gate
+//
+// Every expanded part is a cluster with a node for each of its pins, parts
+// below the depth limit and primitives are boxes. The connections between
+// the bits of two pins become one edge labelled with the bits. With
+// `flatten` the cells of the netlist are drawn, wired to the cells that
+// drive their inputs.
+
+use std::collections::BTreeMap;
+
+use crate::gates::gate::{Connection, Gate, PinKind};
+use crate::gates::netlist::{CellKind, Driver, Net, Netlist};
+use crate::gates::vcd::part_names;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DotOptions {
+    // levels of parts to expand, None for all of them
+    pub depth: Option<usize>,
+    pub flatten: bool
+}
+
+fn quote(x: &str) -> String {
+    format!("\"{}\"", x.replace('"', "\\\""))
+}
+
+// 0-3,5 for the bits 0 1 2 3 5
+fn ranges(bits: &[i64]) -> String {
+    let mut bits = bits.to_vec();
+    bits.sort();
+    bits.dedup();
+    let mut out: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < bits.len() {
+        let mut j = i;
+        while j + 1 < bits.len() && bits[j + 1] == bits[j] + 1 {
+            j += 1;
+        }
+        out.push(if i == j { bits[i].to_string() } else { format!("{}-{}", bits[i], bits[j]) });
+        i = j + 1;
+    }
+    out.join(",")
+}
+
+// names and sizes of the pins of a gate in declaration order
+fn pin_sizes(gate: &Gate) -> Vec<(String, PinKind, i64)> {
+    let mut out: Vec<(String, PinKind, i64)> = Vec::new();
+    for name in gate.pin_names() {
+        if out.iter().any(|(x, _, _)| x == name) {
+            continue;
+        }
+        let kind = match gate.get_pin(name, 0) {
+            Some(pin) => pin.kind,
+            None => continue
+        };
+        let mut size = 0;
+        while gate.get_pin(name, size).is_some() {
+            size += 1;
+        }
+        out.push((name.clone(), kind, size));
+    }
+    out
+}
+
+struct Graph {
+    out: String,
+    clusters: usize,
+    // (from, to, bits of the bus) in the order they are found
+    edges: Vec<(String, String, Vec<i64>)>,
+    edge_index: BTreeMap<(String, String), usize>
+}
+
+impl Graph {
+    fn edge(&mut self, from: String, to: String, bit: Option<i64>) {
+        let next = self.edges.len();
+        let index = *self.edge_index.entry((from.clone(), to.clone())).or_insert(next);
+        if index == next {
+            self.edges.push((from, to, Vec::new()));
+        }
+        if let Some(bit) = bit {
+            self.edges[index].2.push(bit);
+        }
+    }
+
+    fn pin_node(&mut self, id: &str, name: &str, kind: PinKind, size: i64, indent: &str) {
+        let label = if size > 1 { format!("{}[{}]", name, size) } else { name.to_string() };
+        let shape = match kind {
+            PinKind::Input => "invhouse",
+            PinKind::Output => "house",
+            PinKind::Internal => "plaintext"
+        };
+        self.out.push_str(&format!("{}{} [label={}, shape={}];\n", indent, quote(id), quote(&label), shape));
+    }
+
+    fn gate(&mut self, gate: &Gate, scope: &str, depth: Option<usize>, indent: &str) {
+        self.out.push_str(&format!("{}subgraph cluster_{} {{\n", indent, self.clusters));
+        self.clusters += 1;
+        let inner = format!("{}    ", indent);
+        self.out.push_str(&format!("{}label={};\n", inner, quote(&format!("{} ({})", scope, gate.name))));
+        for (name, kind, size) in pin_sizes(gate) {
+            self.pin_node(&format!("{}:{}", scope, name), &name, kind, size, &inner);
+        }
+
+        let names = part_names(gate);
+        let mut constants = [false, false];
+        for (i, part) in gate.gates.iter().enumerate() {
+            let part_scope = format!("{}.{}", scope, names[i]);
+            let expand = !part.gates.is_empty() && depth != Some(0);
+            if expand {
+                self.gate(part, &part_scope, depth.map(|x| x - 1), &inner);
+            } else {
+                self.out.push_str(&format!("{}{} [label={}, shape=box];\n", inner, quote(&part_scope), quote(&part.name)));
+            }
+            for pin in part.pins() {
+                let node = if expand { format!("{}:{}", part_scope, pin.name) } else { part_scope.clone() };
+                // edges are labelled with the bits of the parent pin if it
+                // is a bus, of the part pin otherwise
+                let bit = if expand && pin.size > 1 { Some(pin.index) } else { None };
+                for connection in pin.connections() {
+                    match connection {
+                        Connection::ToParent(name, index) => {
+                            let parent = format!("{}:{}", scope, name);
+                            let size = gate.get_pin(name, *index).map(|x| x.size).unwrap_or(1);
+                            let bit = if size > 1 { Some(*index) } else { bit };
+                            if pin.kind == PinKind::Output {
+                                self.edge(node.clone(), parent, bit);
+                            } else {
+                                self.edge(parent, node.clone(), bit);
+                            }
+                        },
+                        Connection::Constant(value) => {
+                            let id = format!("{}:{}", scope, value);
+                            if !constants[*value as usize] {
+                                constants[*value as usize] = true;
+                                self.out.push_str(&format!("{}{} [label={}, shape=plaintext];\n", inner, quote(&id), quote(&value.to_string())));
+                            }
+                            self.edge(id, node.clone(), bit);
+                        },
+                        Connection::ToChild(..) => {}
+                    }
+                }
+            }
+        }
+        self.out.push_str(&format!("{}}}\n", indent));
+    }
+}
+
+fn flat(root: &Gate, graph: &mut Graph) {
+    let netlist = Netlist::flatten(root);
+    let scope = root.name.clone();
+    for (name, kind, size) in pin_sizes(root) {
+        if kind != PinKind::Internal {
+            graph.pin_node(&format!("{}:{}", scope, name), &name, kind, size, "    ");
+        }
+    }
+    let mut constants = [false, false];
+    let mut node = |graph: &mut Graph, net: Net| -> String {
+        match netlist.driver(net) {
+            Driver::Cell(cell, _) => netlist.cells[cell].path.clone(),
+            Driver::Input(port, _) => format!("{}:{}", scope, netlist.inputs[port].name),
+            Driver::Constant(value) => {
+                let id = format!("{}:{}", scope, value);
+                if !constants[value as usize] {
+                    constants[value as usize] = true;
+                    graph.out.push_str(&format!("    {} [label={}, shape=plaintext];\n", quote(&id), quote(&value.to_string())));
+                }
+                id
+            }
+        }
+    };
+    for cell in &netlist.cells {
+        let label = match &cell.kind {
+            CellKind::Nand => "nand",
+            CellKind::Dff => "dff",
+            CellKind::Builtin { chip, .. } => chip
+        };
+        graph.out.push_str(&format!("    {} [label={}, shape=box];\n", quote(&cell.path), quote(label)));
+        for net in &cell.inputs {
+            let from = node(graph, *net);
+            graph.edge(from, cell.path.clone(), None);
+        }
+    }
+    for port in &netlist.outputs {
+        let to = format!("{}:{}", scope, port.name);
+        for (index, net) in port.nets.iter().enumerate() {
+            let from = node(graph, *net);
+            graph.edge(from, to.clone(), if port.nets.len() > 1 { Some(index as i64) } else { None });
+        }
+    }
+}
+
+pub fn to_dot(gate: &Gate, options: &DotOptions) -> String {
+    let mut graph = Graph { out: String::new(), clusters: 0, edges: Vec::new(), edge_index: BTreeMap::new() };
+    graph.out.push_str(&format!("digraph {} {{\n", quote(&gate.name)));
+    graph.out.push_str("    rankdir=LR;\n");
+    if options.flatten {
+        flat(gate, &mut graph);
+    } else {
+        graph.gate(gate, &gate.name, options.depth, "    ");
+    }
+    let edges = std::mem::take(&mut graph.edges);
+    for (from, to, bits) in edges {
+        if bits.is_empty() {
+            graph.out.push_str(&format!("    {} -> {};\n", quote(&from), quote(&to)));
+        } else {
+            graph.out.push_str(&format!("    {} -> {} [label={}];\n", quote(&from), quote(&to), quote(&ranges(&bits))));
+        }
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
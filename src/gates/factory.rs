@@ -1,129 +1,154 @@
-use crate::gates::gate::Gate;
-use std::{collections::{BTreeMap, HashMap, BTreeSet}, rc::{Rc}, borrow::Cow, fmt};
-use super::graph::Graph;
+use crate::gates::gate::{Gate, PinKind};
+use std::{collections::BTreeMap, rc::Rc};
 
+pub type GateGenerator = Rc<dyn Fn(&GateFactory) -> Result<Gate, String>>;
+
+#[derive(Default)]
 pub struct GateFactory {
-    factory_funcs: BTreeMap<String, fn(&GateFactory) -> Gate>
+    factory_funcs: BTreeMap<String, GateFactoryFunction>
 }
 
 impl GateFactory {
-    pub fn default() -> GateFactory {
-        GateFactory {
-            factory_funcs: BTreeMap::new()
-        }
+    pub fn register(&mut self, func: fn() -> GateFactoryFunction) {
+        self.register_function(func());
     }
 
-    pub fn register(&mut self, func: fn() -> GateFactoryFunction) {
-        let tmp = func();
-        self.factory_funcs.insert(tmp.name.to_string(), tmp.generator);
+    pub fn register_function(&mut self, func: GateFactoryFunction) {
+        self.factory_funcs.insert(func.name.to_string(), func);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factory_funcs.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&GateFactoryFunction> {
+        self.factory_funcs.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.factory_funcs.keys()
     }
 
     pub fn build(&self, name: &str) -> Gate {
-        self.factory_funcs.get(name).unwrap()(self)
+        match self.try_build(name) {
+            Ok(x) => x,
+            Err(x) => panic!("{}", x)
+        }
+    }
+
+    pub fn try_build(&self, name: &str) -> Result<Gate, String> {
+        match self.factory_funcs.get(name) {
+            Some(func) => (func.generator)(self),
+            None => Err(format!("gate {} is not registered", name))
+        }
     }
 }
 
+#[derive(Clone)]
 pub struct GateFactoryFunction {
     pub name: String,
-    pub generator: fn(&GateFactory) -> Gate
+    pub signature: GateSignature,
+    pub generator: GateGenerator
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GateSignature {
+    pub inputs: Vec<(String, i64)>,
+    pub outputs: Vec<(String, i64)>
+}
+
+impl GateSignature {
+    pub fn size(&self, name: &str) -> Option<(PinKind, i64)> {
+        if let Some((_, size)) = self.inputs.iter().find(|(x, _)| x == name) {
+            return Some((PinKind::Input, *size));
+        }
+        if let Some((_, size)) = self.outputs.iter().find(|(x, _)| x == name) {
+            return Some((PinKind::Output, *size));
+        }
+        None
+    }
 }
 
 #[macro_export]
 macro_rules! build_gate_function {
     ($name:ident ($($input:ident $([ $input_size:tt ])?), * => $($output:ident $([ $output_size:tt ])?), *): $next:expr) => (
-        (|| {
-            use crate::gates::factory::{GateFactory, GateFactoryFunction, Gate, PinKind, PinKey, Pin};
+        {
+            use std::rc::Rc;
+            use $crate::gates::factory::{GateFactory, GateFactoryFunction, GateSignature};
+            use $crate::gates::gate::{Gate, PinKind};
+            let signature = GateSignature {
+                inputs: vec![$((stringify!($input).to_string(), $crate::build_gate_function!(@size $($input_size)?))),*],
+                outputs: vec![$((stringify!($output).to_string(), $crate::build_gate_function!(@size $($output_size)?))),*]
+            };
             GateFactoryFunction {
                 name: stringify!($name).to_string(),
-                generator: |f: &GateFactory| -> Gate {
+                signature: signature.clone(),
+                generator: Rc::new(move |f: &GateFactory| -> Result<Gate, String> {
                     let mut gate = Gate::new(stringify!($name));
-                    $(
-                        if (stringify!($($input_size)?).is_empty()) {
-                            gate.insert_pin(PinKind::Input, stringify!($input), 1, 0);
-                        } else {
-                            let mut x = 0;
-                            for i in 0..$($input_size)? {
-                                x += 1;
-                            }
-                            for i in 0..$($input_size)? {
-                                gate.insert_pin(PinKind::Input, stringify!($input), x, i);
-                            }
+                    for (name, size) in &signature.inputs {
+                        for i in 0..*size {
+                            gate.insert_pin(PinKind::Input, name, *size, i);
+                        }
+                    }
+                    for (name, size) in &signature.outputs {
+                        for i in 0..*size {
+                            gate.insert_pin(PinKind::Output, name, *size, i);
                         }
-                        
-                    )*
-
-                    $(
-                        if (stringify!($($output_size)?).is_empty()) {
-                            gate.insert_pin(PinKind::Output, stringify!($output), 1, 0);
-                        } else {
-                            let mut x = 0;
-                            for i in 0..$($output_size)? {
-                                x += 1;
-                            }
-                            for i in 0..$($output_size)? {
-                                gate.insert_pin(PinKind::Output, stringify!($output), x, i);
-                            }
-                        }    
-                    )*
+                    }
                     $next(&mut gate, f);
                     if let Err(x) = gate.compile() {
-                        panic!(x)
+                        return Err(format!("failed to compile gate {}: {}", stringify!($name), x));
                     }
-                    return gate;
-                }
+                    Ok(gate)
+                })
             }
-        })()
-    )
+        }
+    );
+    (@size) => (1);
+    (@size $size:tt) => ($size);
 }
 
 #[macro_export]
 macro_rules! connect {
-    () => ((0,0));
-    ($($name:ident $([($name_index:tt)*])? = $pin:ident $([$($pin_index:tt)*])?),*) => (
-        (|| {
-            let mut out = Vec::new();
-            $( 
-                let range: (usize, usize) =  connect!($($($name_index)*)?);
-                let range2: (usize, usize) =  connect!($($($pin_index)*)?);
-                out.push(((stringify!($name), range), (stringify!($pin), range2)));
-            )*
-            out
-        })()
+    (@pins $($name:ident $([$($name_index:tt)*])? = $pin:ident $([$($pin_index:tt)*])?),*) => (
+        vec![$(
+            ((stringify!($name), $crate::connect!($($($name_index)*)?)), (stringify!($pin), $crate::connect!($($($pin_index)*)?)))
+        ),*]
     );
+    () => ((0,0));
     ($index_start:expr, $index_end:expr) => {{
-        (|| {
-            ($index_start, $index_end)
-        })()
+        ($index_start, $index_end)
     }};
     ($index_start:expr) => {{
-        (|| {
-            ($index_start, $index_start + 1)
-        })()
+        ($index_start, $index_start + 1)
     }};
     ($g:ident, $f:ident, $gate_name:ident { $($input:tt)* } => { $($output:tt)* })=> {{
-        
-        use crate::gates::{GateFactory, GateFactoryFunction, Gate, PinKind, PinKey, Pin};
-        let gate = $f.build(stringify!($gate_name));
-        let gate2 = $f.build(stringify!($gate_name));
-        let gi = $g.add_gate(gate2);
-        let inputs = connect!($($input)*);
-        let outputs = connect!($($output)*);
+        use $crate::gates::gate::PinKind;
+        use $crate::gates::utils::PinKey;
+        let gi = $g.add_gate($f.build(stringify!($gate_name)));
+        let inputs: Vec<((&str, (usize, usize)), (&str, (usize, usize)))> = $crate::connect!(@pins $($input)*);
+        let outputs: Vec<((&str, (usize, usize)), (&str, (usize, usize)))> = $crate::connect!(@pins $($output)*);
         let mut connect = | child: &(&str, (usize, usize)), parent: &(&str, (usize, usize))| {
 
-            let ( pname, mut prange ) = parent;
-            let ( cname, mut crange ) = child;
-    
-            
+            let ( pname, mut prange ) = *parent;
+            let ( cname, mut crange ) = *child;
+
             if crange.0 == crange.1 {
-                let tmp = cname.to_string();
-                let size = gate.get_pin(&cname.to_string(), 0).unwrap().size;
+                let size = $g.gates[gi].get_pin(cname, 0).unwrap().size;
                 crange = (0,size as usize);
             }
 
+            if pname == "true" || pname == "false" {
+                for i in crange.0..crange.1 {
+                    $g.connect_constant(gi, &PinKey::new(cname, i as i64), pname == "true").unwrap();
+                }
+                return;
+            }
+
             if !$g.exists_pin(pname, 0) {
                 for i in 0..(crange.1 - crange.0) {
                     $g.insert_pin(PinKind::Internal, pname, (crange.1 - crange.0) as i64, i as i64);
-                }   
+                }
             }
 
             if prange.0 == prange.1 {
@@ -132,18 +157,16 @@ macro_rules! connect {
             }
 
             for i in 0..(crange.1-crange.0){
-                $g.connect_pins(gi,  &PinKey::new(pname, (prange.0 + i) as i64), &PinKey::new(cname, (crange.0 + i) as i64));
+                $g.connect_pins(gi,  &PinKey::new(pname, (prange.0 + i) as i64), &PinKey::new(cname, (crange.0 + i) as i64)).unwrap();
             }
         };
 
-        for i in &inputs {
-            let (x, y) = i;
+        for (x, y) in &inputs {
             connect(x,y);
         }
 
-        for i in &outputs {
-            let (x, y) = i;
+        for (x, y) in &outputs {
             connect(x,y);
         }
     }};
-}
\ No newline at end of file
+}
@@ -1,56 +1,69 @@
-
-// tick: write out[t]
-// tock: run combintional circuits & update out[t]
-// 
+// tick: latch the inputs of every DFF (out[t+1] = in[t])
+// tock: make the latched values the state & rerun combinational circuits
+//
 // EX
 // out[t+1] -> Bit() -> out[t] -> Comb
-// tick: output all clocked pins (out of DFF)
-// tock: run Comb & run Bit
-// 
-// run -> DFF -> clocked_run
 //
-// DFF -> clocked 
-// 
 // Invariants
-// Comb_t = f(Bit_t) tick 
-// Bit_t+1 = g(Comb_t(.)) tock
-// 
-// Inside:
-// Tick: run every gate that is not connected with Bit_t+1
-// Tock: run every gate that is connected with Bit_t+1
-// 
-// Outside:
-// Tick: run inside tick according to the sequence (but only consider the gates that ticks)
-// New dependency: gates don't need in and load in tick stage
-// 
-// Tock: run inside tock regardless of order (t1 only depends on internal state)
+// Comb_t = f(Bit_t, in_t)
+// Bit_t+1 = g(Comb_t)
+//
+// Outputs of a DFF only depend on its state (clocked pins), so a wire from a
+// clocked pin never makes its reader wait and feedback through a DFF is not
+// a cycle. compile() schedules the children of a gate in three parts:
 //
-// Bit_t+1 connected with 
+// Publish: every stateful child computes its clocked outputs from its state
+// Tick plan: children that only depend on published values (Comb_t = f(Bit_t))
+// Tock plan: children that depend on the inputs of the gate, in order
+//
+// A child runs once for each set of outputs that becomes ready, so a memory
+// whose out only depends on address (which comes from a register) can run
+// in the tick plan and again in the tock plan once its in and load are known.
+// Stateful children always run last with their final inputs before latching.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::collections::btree_map::Entry;
 use crate::gates::utils::PinValues;
 use crate::gates::utils::PinMap;
 use crate::gates::utils::PinKey;
+use crate::gates::graph::Graph;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Connection {
-    None,
     ToParent(String, i64),
-    ToChild(String, i64)
+    ToChild(usize, String, i64),
+    Constant(bool)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PinKind {
     Input,
     Internal,
     Output
 }
 
+#[derive(Debug)]
 pub enum GateValidationError {
     InvalidPinConnection,
-    PinNotExists
+    PinNotExists,
+    CombinationalLoop,
+    // a primitive that a netlist simulator can not run, like a builtin memory
+    UnsupportedPrimitive(String)
 }
 
+impl fmt::Display for GateValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GateValidationError::InvalidPinConnection => write!(f, "invalid pin connection"),
+            GateValidationError::PinNotExists => write!(f, "pin does not exist"),
+            GateValidationError::CombinationalLoop => write!(f, "combinational loop"),
+            GateValidationError::UnsupportedPrimitive(chip) => write!(f, "unsupported primitive {}", chip)
+        }
+    }
+}
+
+impl std::error::Error for GateValidationError {}
 
 #[derive(Debug)]
 pub struct Pin {
@@ -58,17 +71,27 @@ pub struct Pin {
     pub index: i64,
     pub size: i64,
     pub kind: PinKind,
+    // output pin that only depends on the state of the gate, not on its inputs
     pub clocked: bool,
-    connection: Connection
+    // position of the value of the pin in the values of its gate, pins are
+    // numbered in the order they are inserted
+    pub slot: usize,
+    pub(crate) connections: Vec<Connection>
+}
+
+impl Pin {
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
 }
 
 #[derive(Debug, Clone)]
 struct GateRunPlan {
-    gate_index: i64,
-    // parent -> child
-    reads: Vec<(PinKey, PinKey)>,
-    write_internals: Vec<(PinKey, PinKey)>,
-    write_outputs: Vec<(PinKey, PinKey)>
+    gate_index: usize,
+    // parent slot -> child slot
+    reads: Vec<(usize, usize)>,
+    constants: Vec<(usize, bool)>,
+    writes: Vec<(usize, usize)>
 }
 
 #[derive(Debug)]
@@ -78,13 +101,76 @@ pub struct Gate {
     pub gates: Vec<Gate>,
     pub primitive_implementor: Option<Box<dyn PrimitiveGateImplementor>>,
     pins: PinMap,
-    temp_values: PinValues,
+    values: Vec<bool>,
+    // whether values holds the outputs for the current inputs
+    evaluated: bool,
+    stateful: bool,
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+    compiled_publish_plans: Vec<GateRunPlan>,
     compiled_tick_plans: Option<Vec<GateRunPlan>>,
     compiled_tock_plans: Option<Vec<GateRunPlan>>
 }
 
 pub trait PrimitiveGateImplementor: std::fmt::Debug{
+    // Evaluates the outputs for the current state. Must not change the state.
     fn run(&mut self, inputs: PinValues) -> PinValues;
+
+    // Fast path of run. `values` has a slot for every pin bit in the order
+    // the pins are declared, inputs then outputs, and the outputs are written
+    // in place. The gate calls it first and only falls back to run when it
+    // returns false: then the input slots are copied into PinValues and the
+    // outputs run returns are copied back into their slots. Both paths must
+    // give the same outputs, so run stays the reference implementation and
+    // this one returns false if not implemented.
+    fn run_values(&mut self, _values: &mut [bool]) -> bool {
+        false
+    }
+
+    // Inputs that `output` depends on combinationally, None for every input.
+    fn dependencies(&self, _output: &str) -> Option<Vec<String>> {
+        None
+    }
+
+    fn stateful(&self) -> bool {
+        false
+    }
+
+    // Outputs that only depend on the state
+    fn state(&self) -> PinValues {
+        PinValues::new()
+    }
+
+    // Fast path of state with the slots of run_values: only the outputs
+    // that depend on the state are written. When it returns false, which it
+    // does if not implemented, the gate calls state and copies the values
+    // into their slots.
+    fn state_values(&self, _values: &mut [bool]) -> bool {
+        false
+    }
+
+    // Latches the inputs of the last run
+    fn tick(&mut self) {}
+
+    // Makes the latched inputs the new state
+    fn tock(&mut self) {}
+
+    // Words of a builtin memory, None if the gate has none
+    fn memory(&self) -> Option<&[u16]> {
+        None
+    }
+
+    fn memory_mut(&mut self) -> Option<&mut [u16]> {
+        None
+    }
+}
+
+// A step of the compiled schedule: the outputs of `child` connected to the
+// parent and the inputs of the child that must be known before it runs.
+// Settle events make sure a stateful child sees its final inputs before
+// the tick latches them.
+enum Event {
+    Output(usize, String),
+    Settle(usize)
 }
 
 impl Gate {
@@ -93,11 +179,15 @@ impl Gate {
             name: name.to_string(),
             id: "hello".to_string(),
             pins: PinMap::new(),
+            compiled_publish_plans: Vec::new(),
             compiled_tick_plans: None,
             compiled_tock_plans: None,
             gates: Vec::new(),
             primitive_implementor: None,
-            temp_values: PinValues::new()
+            values: Vec::new(),
+            evaluated: false,
+            stateful: false,
+            dependencies: BTreeMap::new()
         }
     }
 
@@ -127,8 +217,28 @@ impl Gate {
             }
         };
 
-        x.connection = Connection::ToChild(y.name.clone(), y.index);
-        y.connection = Connection::ToParent(x.name.clone(), x.index);
+        x.connections.push(Connection::ToChild(gate_index, y.name.clone(), y.index));
+        y.connections.push(Connection::ToParent(x.name.clone(), x.index));
+        Ok(())
+    }
+
+    pub fn connect_constant(&mut self, gate_index: usize, child_pin: &PinKey, value: bool) -> Result<(), GateValidationError> {
+        let gate = match self.gates.get_mut(gate_index) {
+            Some(x) => x,
+            None => {
+                return Err(GateValidationError::PinNotExists);
+            }
+        };
+        let y = match gate.pins.get_mut(&child_pin.name, child_pin.index) {
+            Some(x) => x,
+            None => {
+                return Err(GateValidationError::PinNotExists);
+            }
+        };
+        if y.kind != PinKind::Input {
+            return Err(GateValidationError::InvalidPinConnection);
+        }
+        y.connections.push(Connection::Constant(value));
         Ok(())
     }
 
@@ -136,142 +246,466 @@ impl Gate {
         self.pins.get(name, index)
     }
 
+    pub fn pins(&self) -> impl Iterator<Item = &Pin> {
+        self.pins.internal.values()
+    }
+
+    // value of a pin after the last evaluation
+    pub fn value(&self, name: &str, index: i64) -> Option<bool> {
+        self.pins.get(name, index).map(|pin| self.values[pin.slot])
+    }
+
+    // values of the pins by slot
+    pub(crate) fn values(&self) -> &[bool] {
+        &self.values
+    }
+
+    pub fn pin_names(&self) -> &[String] {
+        self.pins.names()
+    }
+
     pub fn insert_pin(&mut self, kind: PinKind, name: &str, size: i64, index: i64) {
-        self.pins.insert(&self.id, kind, name, size, index);
+        self.pins.insert(kind, name, size, index);
     }
 
     pub fn exists_pin(&self, name: &str, index: i64) -> bool {
-        !self.pins.get(name, index).is_none()
+        self.pins.get(name, index).is_some()
+    }
+
+    // first part named `name` in depth first order, the gate itself included
+    pub fn find(&self, name: &str) -> Option<&Gate> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.gates.iter().find_map(|gate| gate.find(name))
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Gate> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.gates.iter_mut().find_map(|gate| gate.find_mut(name))
+    }
+
+    // words of a builtin memory like rom32k or screen
+    pub fn memory(&self) -> Option<&[u16]> {
+        self.primitive_implementor.as_ref().and_then(|x| x.memory())
+    }
+
+    pub fn memory_mut(&mut self) -> Option<&mut [u16]> {
+        self.primitive_implementor.as_mut().and_then(|x| x.memory_mut())
+    }
+
+    // whether the gate keeps state between clock cycles
+    pub fn is_stateful(&self) -> bool {
+        self.stateful
+    }
+
+    // input pins that `output` depends on combinationally
+    pub fn dependencies(&self, output: &str) -> Option<&BTreeSet<String>> {
+        self.dependencies.get(output)
+    }
+
+    fn input_names(&self) -> BTreeSet<String> {
+        self.pins.internal.values()
+            .filter(|pin| pin.kind == PinKind::Input)
+            .map(|pin| pin.name.clone())
+            .collect()
+    }
+
+    fn output_names(&self) -> BTreeSet<String> {
+        self.pins.internal.values()
+            .filter(|pin| pin.kind == PinKind::Output)
+            .map(|pin| pin.name.clone())
+            .collect()
+    }
+
+    fn child_dependencies(&self, gate_index: usize, output: &str) -> BTreeSet<String> {
+        match self.gates[gate_index].dependencies.get(output) {
+            Some(x) => x.clone(),
+            None => BTreeSet::new()
+        }
     }
 
     pub fn compile(&mut self) -> Result<(), GateValidationError> {
-        let mut graph = Graph::new();
-        for i in  0..self.gates.len() {
-            graph.add_node(i as i64);
+        self.values = vec![false; self.pins.len()];
+        self.evaluated = false;
+
+        if let Some(x) = &self.primitive_implementor {
+            let inputs = self.input_names();
+            let mut dependencies = BTreeMap::new();
+            for output in self.output_names() {
+                let deps = match x.dependencies(&output) {
+                    Some(deps) => deps.into_iter().collect(),
+                    None => inputs.clone()
+                };
+                dependencies.insert(output, deps);
+            }
+            self.stateful = x.stateful();
+            self.dependencies = dependencies;
+            self.mark_clocked_pins();
+            self.compiled_publish_plans = Vec::new();
+            self.compiled_tick_plans = Some(Vec::new());
+            self.compiled_tock_plans = Some(Vec::new());
+            return Ok(());
         }
+
         let mut runs = Vec::new();
-        let mut read_from_internal: BTreeMap<PinKey, Vec<i64>> = BTreeMap::new();
-        let mut write_to_internal: BTreeMap<PinKey, i64> = BTreeMap::new();
-        for i in  0..self.gates.len() {
-            let gate = self.gates.get(i).unwrap();
+        // child input name -> parent pins feeding it
+        let mut child_sources: Vec<BTreeMap<String, Vec<PinKey>>> = Vec::new();
+        // parent internal or output pin -> child output writing it
+        let mut writers: BTreeMap<PinKey, (usize, String)> = BTreeMap::new();
+        // parent slot -> child slot for the clocked outputs of each child
+        let mut clocked_writes: Vec<Vec<(usize, usize)>> = Vec::new();
+        for (i, gate) in self.gates.iter().enumerate() {
             let mut reads = Vec::new();
-            let mut write_internals = Vec::new();
-            let mut write_outputs = Vec::new();
-            for (_, pin) in &gate.pins.internal {
-                if let Connection::ToParent(name, index) = &pin.connection {
-                    let parent_key = PinKey::new(&name, *index);
-                    let child_key = PinKey::new(&pin.name, pin.index);
+            let mut constants = Vec::new();
+            let mut writes = Vec::new();
+            let mut clocked = Vec::new();
+            let mut sources: BTreeMap<String, Vec<PinKey>> = BTreeMap::new();
+            for pin in gate.pins.internal.values() {
+                if pin.kind == PinKind::Input && pin.connections.is_empty() {
+                    constants.push((pin.slot, false));
+                }
+                for connection in &pin.connections {
+                    let (name, index) = match connection {
+                        Connection::ToParent(name, index) => (name, index),
+                        Connection::Constant(value) => {
+                            constants.push((pin.slot, *value));
+                            continue;
+                        },
+                        Connection::ToChild(..) => continue
+                    };
+                    let parent_key = PinKey::new(name, *index);
                     let parent_pin = match self.pins.get(name, *index) {
                         Some(x) => x,
                         None => { return Err(GateValidationError::InvalidPinConnection) }
                     };
                     if let PinKind::Input = pin.kind {
-                        match parent_pin.kind {
-                            PinKind::Internal => {
-                                read_from_internal.entry(parent_key.clone()).or_insert(Vec::new());
-                                read_from_internal.get_mut(&parent_key).unwrap().push(i as i64);
-                            },
-                            PinKind::Input => { },
-                            PinKind::Output => {
-                                 return Err(GateValidationError::InvalidPinConnection);
-                            }
-                        };
-                        reads.push((parent_key, child_key));
+                        if let PinKind::Output = parent_pin.kind {
+                            return Err(GateValidationError::InvalidPinConnection);
+                        }
+                        sources.entry(pin.name.clone()).or_default().push(parent_key.clone());
+                        reads.push((parent_pin.slot, pin.slot));
                     } else if let PinKind::Output = pin.kind {
-                       match parent_pin.kind {
-                           PinKind::Internal => {
-                                write_internals.push((parent_key.clone(), child_key.clone()));
-                                write_to_internal.insert(parent_key, i as i64);
-                           },
-                           PinKind::Output => {
-                                write_outputs.push((parent_key, child_key));
-                           },
-                           PinKind::Input => {
-                                return Err(GateValidationError::InvalidPinConnection);
-                           }
-                       };
+                        if writers.insert(parent_key.clone(), (i, pin.name.clone())).is_some() {
+                            return Err(GateValidationError::InvalidPinConnection);
+                        }
+                        if let PinKind::Input = parent_pin.kind {
+                            return Err(GateValidationError::InvalidPinConnection);
+                        }
+                        writes.push((parent_pin.slot, pin.slot));
+                        if pin.clocked {
+                            clocked.push((parent_pin.slot, pin.slot));
+                        }
                     }
                 }
             }
             runs.push(GateRunPlan {
-                gate_index: i as i64,
-                reads: reads,
-                write_internals: write_internals,
-                write_outputs: write_outputs
+                gate_index: i,
+                reads,
+                constants,
+                writes
             });
+            clocked_writes.push(clocked);
+            child_sources.push(sources);
         }
 
+        // One event per child output that drives something and one settle
+        // event per stateful child.
+        let mut events = Vec::new();
+        let mut event_ids: BTreeMap<(usize, String), i64> = BTreeMap::new();
+        for (i, output) in writers.values() {
+            if let Entry::Vacant(entry) = event_ids.entry((*i, output.clone())) {
+                entry.insert(events.len() as i64);
+                events.push(Event::Output(*i, output.clone()));
+            }
+        }
+        for (i, gate) in self.gates.iter().enumerate() {
+            if gate.stateful {
+                events.push(Event::Settle(i));
+            }
+        }
 
-        for (key, reads) in &read_from_internal {
-            let node = match write_to_internal.get(key) {
-                Some(x) => *x,
-                None => { return Err(GateValidationError::InvalidPinConnection) }
+        let mut graph = Graph::new();
+        for id in 0..events.len() {
+            graph.add_node(id as i64);
+        }
+        // inputs of the parent each event depends on directly
+        let mut direct_inputs: Vec<BTreeSet<String>> = Vec::new();
+        let mut event_deps: Vec<Vec<i64>> = Vec::new();
+        for (id, event) in events.iter().enumerate() {
+            let (i, inputs) = match event {
+                Event::Output(i, output) => (*i, self.child_dependencies(*i, output)),
+                Event::Settle(i) => (*i, self.gates[*i].input_names())
             };
-            for r in reads {
-                graph.add_edge(*r, node);
+            let mut names = BTreeSet::new();
+            let mut deps = Vec::new();
+            for input in &inputs {
+                let sources = match child_sources[i].get(input) {
+                    Some(x) => x,
+                    None => continue
+                };
+                for source in sources {
+                    match self.pins.get(&source.name, source.index).unwrap().kind {
+                        PinKind::Input => {
+                            names.insert(source.name.clone());
+                        },
+                        _ => {
+                            let writer = match writers.get(source) {
+                                Some(x) => x,
+                                None => { return Err(GateValidationError::InvalidPinConnection) }
+                            };
+                            let dep = event_ids[writer];
+                            graph.add_edge(id as i64, dep);
+                            deps.push(dep);
+                        }
+                    }
+                }
             }
+            direct_inputs.push(names);
+            event_deps.push(deps);
         }
-        let results = match graph.topological_sort_with_cycle_detection() {
+        let order = match graph.topological_sort_with_cycle_detection() {
             Ok(x) => x,
-            Err(_) => { return Err(GateValidationError::InvalidPinConnection) }
+            Err(_) => { return Err(GateValidationError::CombinationalLoop) }
         };
 
-        let mut out: Vec<GateRunPlan> = Vec::new();
-        for i in results {
-            out.push(runs[i as usize].clone());
+        // Events that do not depend on the inputs of the parent run in the
+        // tick plan, the rest in the tock plan. Outputs of a stateful child
+        // that only depend on its state are published before both.
+        let mut event_inputs: Vec<BTreeSet<String>> = vec![BTreeSet::new(); events.len()];
+        let mut position: Vec<(bool, i64)> = vec![(false, -1); events.len()];
+        let mut tick: Vec<usize> = Vec::new();
+        let mut tock: Vec<usize> = Vec::new();
+        let mut last_tick: BTreeMap<usize, i64> = BTreeMap::new();
+        let mut last_tock: BTreeMap<usize, i64> = BTreeMap::new();
+        let mut published: BTreeSet<usize> = BTreeSet::new();
+        for id in order {
+            let id = id as usize;
+            let mut names = direct_inputs[id].clone();
+            for dep in &event_deps[id] {
+                names.extend(event_inputs[*dep as usize].iter().cloned());
+            }
+            let (i, depends_on_nothing) = match &events[id] {
+                Event::Output(i, output) => (*i, self.child_dependencies(*i, output).is_empty()),
+                Event::Settle(i) => (*i, false)
+            };
+            if depends_on_nothing && self.gates[i].stateful {
+                published.insert(i);
+                event_inputs[id] = names;
+                continue;
+            }
+            let is_tock = !names.is_empty();
+            let need = event_deps[id].iter()
+                .map(|dep| position[*dep as usize])
+                .filter(|(dep_tock, _)| *dep_tock == is_tock)
+                .map(|(_, pos)| pos)
+                .max()
+                .unwrap_or(-1);
+            let (plans, last) = if is_tock { (&mut tock, &mut last_tock) } else { (&mut tick, &mut last_tick) };
+            let pos = match last.get(&i) {
+                Some(pos) if *pos > need => *pos,
+                _ => {
+                    plans.push(i);
+                    last.insert(i, plans.len() as i64 - 1);
+                    plans.len() as i64 - 1
+                }
+            };
+            position[id] = (is_tock, pos);
+            event_inputs[id] = names;
+        }
+
+        let mut dependencies: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for output in self.output_names() {
+            dependencies.insert(output, BTreeSet::new());
+        }
+        for (parent, writer) in &writers {
+            if let PinKind::Output = self.pins.get(&parent.name, parent.index).unwrap().kind {
+                let names = &event_inputs[event_ids[writer] as usize];
+                dependencies.get_mut(&parent.name).unwrap().extend(names.iter().cloned());
+            }
+        }
+
+        for (i, gate) in self.gates.iter().enumerate() {
+            if gate.stateful {
+                published.insert(i);
+            }
         }
-        self.compiled_tock_plans = Some(out);
+        self.compiled_publish_plans = published.iter().map(|i| {
+            GateRunPlan {
+                gate_index: *i,
+                reads: Vec::new(),
+                constants: Vec::new(),
+                writes: clocked_writes[*i].clone()
+            }
+        }).collect();
+        self.stateful = self.gates.iter().any(|gate| gate.stateful);
+        self.dependencies = dependencies;
+        self.mark_clocked_pins();
+        self.compiled_tick_plans = Some(tick.iter().map(|i| runs[*i].clone()).collect());
+        self.compiled_tock_plans = Some(tock.iter().map(|i| runs[*i].clone()).collect());
         Ok(())
     }
 
-    pub fn tick(&mut self, inputs: PinValues) {
-        self.temp_values = inputs;
-        match &self.compiled_tick_plans {
-            Some(runs) => {
-                self.run(runs)
-            },
-            None => PinValues::new()
-        };
+    fn mark_clocked_pins(&mut self) {
+        let dependencies = &self.dependencies;
+        for pin in self.pins.internal.values_mut() {
+            if pin.kind == PinKind::Output {
+                pin.clocked = dependencies.get(&pin.name).map(|x| x.is_empty()).unwrap_or(true);
+            }
+        }
+    }
+
+    // Evaluates the gate for the current state.
+    pub fn eval(&mut self, inputs: PinValues) -> PinValues {
+        self.set_inputs(&inputs);
+        self.prepare();
+        self.evaluate();
+        self.outputs()
+    }
+
+    // Runs a whole clock cycle. Combinational gates are just evaluated.
+    pub fn run(&mut self, inputs: PinValues) -> PinValues {
+        if !self.stateful {
+            return self.eval(inputs);
+        }
+        self.tick(inputs);
+        self.tock()
     }
 
+    // Rising edge: every stateful part latches its inputs. The returned
+    // outputs are still those of the current state.
+    pub fn tick(&mut self, inputs: PinValues) -> PinValues {
+        self.set_inputs(&inputs);
+        self.prepare();
+        self.evaluate();
+        self.latch();
+        self.outputs()
+    }
+
+    // Falling edge: the latched values become the state and the outputs are
+    // evaluated again with the inputs of the last tick.
     pub fn tock(&mut self) -> PinValues {
-        match &self.compiled_tock_plans {
-            Some(runs) => {
-                self.run(runs)
-            },
-            None => PinValues::new()
+        self.commit();
+        self.prepare();
+        self.evaluate();
+        self.outputs()
+    }
+
+    fn set_inputs(&mut self, inputs: &PinValues) {
+        for (key, value) in inputs.iter() {
+            if let Some(pin) = self.pins.get(&key.name, key.index) {
+                if pin.kind == PinKind::Input {
+                    self.values[pin.slot] = *value;
+                }
+            }
         }
     }
 
-    fn run(&mut self, runs: &Vec<GateRunPlan>, ) -> PinValues {
-        match self.primitive_implementor.as_mut() {
-            Some(x) => x.run(self.temp_values),
-            None => {
-                let mut output_values = PinValues::new();
-                for run in runs {
-                    let gate = self.gates.get_mut(run.gate_index as usize).unwrap();
-                    let mut xx = PinValues::new();
-                    run.reads.iter()
-                        .for_each(| (parent, child) | { 
-                            let x = self.temp_values.get(&parent.name, parent.index);
-                            xx.set(&child.name, child.index, x);
-                        });
-
-                    let res = gate.tock();
-                    run.write_internals.iter()
-                        .for_each(| (parent, child) | {
-                            let x = res.get(&child.name, child.index);
-                            self.temp_values.set(&parent.name, parent.index, x);
-                        });
-                    
-                    run.write_outputs.iter()
-                        .for_each(| (parent, child) | {
-                            let x = res.get(&child.name, child.index);
-                            output_values.set(&parent.name, parent.index, x);
-                        });
+    fn outputs(&self) -> PinValues {
+        let mut out = PinValues::new();
+        for pin in self.pins.internal.values().filter(|pin| pin.kind == PinKind::Output) {
+            out.set(&pin.name, pin.index, self.values[pin.slot]);
+        }
+        out
+    }
+
+    fn latch(&mut self) {
+        if let Some(x) = self.primitive_implementor.as_mut() {
+            x.tick();
+            return;
+        }
+        for gate in self.gates.iter_mut().filter(|gate| gate.stateful) {
+            gate.latch();
+        }
+    }
+
+    fn commit(&mut self) {
+        if let Some(x) = self.primitive_implementor.as_mut() {
+            x.tock();
+            return;
+        }
+        for gate in self.gates.iter_mut().filter(|gate| gate.stateful) {
+            gate.commit();
+        }
+    }
+
+    // Computes every value that only depends on the state: the outputs of
+    // stateful parts first, then the tick plan. Every stateful part has a
+    // publish plan, possibly without writes. A combinational gate has no
+    // publish plans but its tick plan still holds the parts only fed by
+    // constants.
+    fn prepare(&mut self) {
+        if let Some(x) = self.primitive_implementor.as_ref() {
+            if !x.state_values(&mut self.values) {
+                let state = x.state();
+                for (key, value) in state.iter() {
+                    let slot = self.pins.get(&key.name, key.index).unwrap().slot;
+                    self.values[slot] = *value;
                 }
-                output_values
             }
+            return;
+        }
+        for run in &self.compiled_publish_plans {
+            let gate = &mut self.gates[run.gate_index];
+            gate.prepare();
+            for (parent, child) in &run.writes {
+                self.values[*parent] = gate.values[*child];
+            }
+        }
+        if let Some(runs) = self.compiled_tick_plans.take() {
+            self.execute(&runs);
+            self.compiled_tick_plans = Some(runs);
+        }
+    }
+
+    // Evaluates the tock plan with the current values of the inputs.
+    fn evaluate(&mut self) {
+        if let Some(x) = self.primitive_implementor.as_mut() {
+            if x.run_values(&mut self.values) {
+                return;
+            }
+            let mut inputs = PinValues::new();
+            for pin in self.pins.internal.values().filter(|pin| pin.kind == PinKind::Input) {
+                inputs.set(&pin.name, pin.index, self.values[pin.slot]);
+            }
+            let outputs = x.run(inputs);
+            for (key, value) in outputs.iter() {
+                let slot = self.pins.get(&key.name, key.index).unwrap().slot;
+                self.values[slot] = *value;
+            }
+            return;
+        }
+        if let Some(runs) = self.compiled_tock_plans.take() {
+            self.execute(&runs);
+            self.compiled_tock_plans = Some(runs);
         }
     }
-}
\ No newline at end of file
+
+    // Combinational children are only evaluated again when one of their
+    // inputs changed.
+    fn execute(&mut self, runs: &[GateRunPlan]) {
+        for run in runs {
+            let gate = &mut self.gates[run.gate_index];
+            let mut changed = gate.stateful || !gate.evaluated;
+            for (parent, child) in &run.reads {
+                let value = self.values[*parent];
+                if gate.values[*child] != value {
+                    gate.values[*child] = value;
+                    changed = true;
+                }
+            }
+            for (child, value) in &run.constants {
+                gate.values[*child] = *value;
+            }
+            if changed {
+                gate.evaluate();
+                gate.evaluated = true;
+            }
+            for (parent, child) in &run.writes {
+                self.values[*parent] = gate.values[*child];
+            }
+        }
+    }
+}
@@ -9,7 +9,6 @@ pub struct Graph {
 }
 
 struct Node {
-    i: i64,
     in_edges: Vec<i64>,
     out_edges: Vec<i64>
 }
@@ -22,8 +21,7 @@ impl Graph {
     }
 
     pub fn add_node(&mut self, i: i64) {
-        self.nodes.insert(i, Node { 
-            i: i,
+        self.nodes.insert(i, Node {
             in_edges: Vec::new(),
             out_edges: Vec::new()
         });
@@ -70,7 +68,7 @@ impl Graph {
                 return Err(GraphError::Cycle);
             }
         }
-        return Ok(results.iter().rev().cloned().collect());
+        Ok(results.iter().rev().cloned().collect())
     }
 
     
@@ -79,21 +77,21 @@ impl Graph {
             let node = self.nodes.get(&i).unwrap();
             node.in_edges.clone()
         };
-        if (visited.contains(&i)) {
+        if visited.contains(&i) {
             return false;
         };
         visited.insert(i);
         for x in neighbors.iter() {
-            if (!visited.contains(x)) {
-                if (self.dfs(*x, results, visited , finished)) {
+            if !visited.contains(x) {
+                if self.dfs(*x, results, visited , finished) {
                     return true;
                 }
-            } else if (!finished.contains(x)) {
+            } else if !finished.contains(x) {
                 return true;
             }
         }
         results.push(i);
         finished.insert(i);
-        return false;
+        false
     }
 }
\ No newline at end of file
@@ -0,0 +1,838 @@
+// nand2tetris style hardware description files
+//
+// CHIP Xor {
+//     IN a, b;
+//     OUT out;
+//     PARTS:
+//     Not(in=a, out=nota);
+//     ...
+// }
+//
+// Chip names are case insensitive and registered in lower case so that
+// `Not(...)` resolves to the builtin `not` gate. A chip defined in hdl
+// replaces a builtin of the same name.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::gates::factory::{GateFactory, GateFactoryFunction, GateSignature};
+use crate::gates::gate::{Gate, GateValidationError, PinKind};
+use crate::gates::utils::PinKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdlError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl HdlError {
+    fn new(file: &str, location: Location, message: &str) -> HdlError {
+        HdlError {
+            file: file.to_string(),
+            line: location.line,
+            column: location.column,
+            message: message.to_string()
+        }
+    }
+}
+
+// line 0 when there is no position, like for a file that can not be read or
+// a chip loaded from json
+impl fmt::Display for HdlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+        }
+    }
+}
+
+impl std::error::Error for HdlError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinDeclaration {
+    pub name: String,
+    pub size: i64,
+    pub location: Location
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinReference {
+    pub name: String,
+    // inclusive bit range as written in hdl, None when the whole pin is used
+    pub range: Option<(i64, i64)>,
+    pub location: Location
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartConnection {
+    pub inner: PinReference,
+    pub outer: PinReference
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartDefinition {
+    pub name: String,
+    pub connections: Vec<PartConnection>,
+    pub location: Location
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChipDefinition {
+    pub file: String,
+    pub name: String,
+    pub inputs: Vec<PinDeclaration>,
+    pub outputs: Vec<PinDeclaration>,
+    pub parts: Vec<PartDefinition>,
+    pub builtin: Option<String>,
+    pub location: Location
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Symbol(&'static str)
+}
+
+const SYMBOLS: [&str; 12] = ["..", "{", "}", "(", ")", "[", "]", ",", ";", ":", "=", "."];
+
+struct Lexer<'a> {
+    file: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+    location: Location
+}
+
+impl<'a> Lexer<'a> {
+    fn new(file: &'a str, source: &str) -> Lexer<'a> {
+        Lexer {
+            file,
+            chars: source.chars().collect(),
+            pos: 0,
+            location: Location { line: 1, column: 1 }
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek(0) {
+            self.pos += 1;
+            if c == '\n' {
+                self.location.line += 1;
+                self.location.column = 1;
+            } else {
+                self.location.column += 1;
+            }
+        }
+    }
+
+    fn skip_trivia(&mut self) -> Result<(), HdlError> {
+        loop {
+            match (self.peek(0), self.peek(1)) {
+                (Some(c), _) if c.is_whitespace() => self.bump(),
+                (Some('/'), Some('/')) => {
+                    while !matches!(self.peek(0), None | Some('\n')) {
+                        self.bump();
+                    }
+                },
+                (Some('/'), Some('*')) => {
+                    let start = self.location;
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match (self.peek(0), self.peek(1)) {
+                            (Some('*'), Some('/')) => {
+                                self.bump();
+                                self.bump();
+                                break;
+                            },
+                            (Some(_), _) => self.bump(),
+                            (None, _) => return Err(HdlError::new(self.file, start, "unterminated comment"))
+                        }
+                    }
+                },
+                _ => return Ok(())
+            }
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<(Token, Location)>, HdlError> {
+        let mut out = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            let location = self.location;
+            let c = match self.peek(0) {
+                Some(c) => c,
+                None => break
+            };
+            if c.is_ascii_alphabetic() || c == '_' {
+                let mut ident = String::new();
+                while let Some(c) = self.peek(0) {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    ident.push(c);
+                    self.bump();
+                }
+                out.push((Token::Ident(ident), location));
+            } else if c.is_ascii_digit() {
+                let mut number = String::new();
+                while let Some(c) = self.peek(0) {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    number.push(c);
+                    self.bump();
+                }
+                let value = number.parse().map_err(|_| HdlError::new(self.file, location, "number is too large"))?;
+                out.push((Token::Number(value), location));
+            } else {
+                let symbol = SYMBOLS.iter().find(|s| {
+                    s.chars().enumerate().all(|(i, x)| self.peek(i) == Some(x))
+                });
+                match symbol {
+                    Some(s) => {
+                        for _ in 0..s.len() {
+                            self.bump();
+                        }
+                        out.push((Token::Symbol(s), location));
+                    },
+                    None => {
+                        return Err(HdlError::new(self.file, location, &format!("unexpected character '{}'", c)));
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+struct Parser<'a> {
+    file: &'a str,
+    tokens: Vec<(Token, Location)>,
+    pos: usize,
+    end: Location
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn location(&self) -> Location {
+        self.tokens.get(self.pos).map(|(_, l)| *l).unwrap_or(self.end)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, HdlError> {
+        Err(HdlError::new(self.file, self.location(), message))
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(x)) => format!("'{}'", x),
+            Some(Token::Number(x)) => format!("'{}'", x),
+            Some(Token::Symbol(x)) => format!("'{}'", x),
+            None => "end of file".to_string()
+        }
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(x)) if *x == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(x)) if x == keyword)
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), HdlError> {
+        if !self.is_symbol(symbol) {
+            return self.error(&format!("expected '{}' but found {}", symbol, self.describe()));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), HdlError> {
+        if !self.is_keyword(keyword) {
+            return self.error(&format!("expected '{}' but found {}", keyword, self.describe()));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_ident(&mut self) -> Result<(String, Location), HdlError> {
+        let location = self.location();
+        match self.peek() {
+            Some(Token::Ident(x)) => {
+                let x = x.clone();
+                self.pos += 1;
+                Ok((x, location))
+            },
+            _ => self.error(&format!("expected identifier but found {}", self.describe()))
+        }
+    }
+
+    fn expect_number(&mut self) -> Result<i64, HdlError> {
+        match self.peek() {
+            Some(Token::Number(x)) => {
+                let x = *x;
+                self.pos += 1;
+                Ok(x)
+            },
+            _ => self.error(&format!("expected number but found {}", self.describe()))
+        }
+    }
+
+    fn parse_chip(&mut self) -> Result<ChipDefinition, HdlError> {
+        let location = self.location();
+        self.expect_keyword("CHIP")?;
+        let (name, _) = self.expect_ident()?;
+        self.expect_symbol("{")?;
+        let mut chip = ChipDefinition {
+            file: self.file.to_string(),
+            name,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            parts: Vec::new(),
+            builtin: None,
+            location
+        };
+        if self.is_keyword("IN") {
+            self.pos += 1;
+            chip.inputs = self.parse_declarations()?;
+        }
+        if self.is_keyword("OUT") {
+            self.pos += 1;
+            chip.outputs = self.parse_declarations()?;
+        }
+        if self.is_keyword("BUILTIN") {
+            self.pos += 1;
+            let (builtin, _) = self.expect_ident()?;
+            self.expect_symbol(";")?;
+            chip.builtin = Some(builtin);
+            if self.is_keyword("CLOCKED") {
+                self.pos += 1;
+                while !self.is_symbol(";") {
+                    self.expect_ident()?;
+                    if !self.is_symbol(";") {
+                        self.expect_symbol(",")?;
+                    }
+                }
+                self.pos += 1;
+            }
+        } else {
+            self.expect_keyword("PARTS")?;
+            self.expect_symbol(":")?;
+            while !self.is_symbol("}") {
+                if self.peek().is_none() {
+                    return self.error("expected '}' but found end of file");
+                }
+                chip.parts.push(self.parse_part()?);
+            }
+        }
+        self.expect_symbol("}")?;
+        if self.peek().is_some() {
+            return self.error(&format!("unexpected {} after chip definition", self.describe()));
+        }
+        Ok(chip)
+    }
+
+    fn parse_declarations(&mut self) -> Result<Vec<PinDeclaration>, HdlError> {
+        let mut out = Vec::new();
+        loop {
+            let (name, location) = self.expect_ident()?;
+            let mut size = 1;
+            if self.is_symbol("[") {
+                self.pos += 1;
+                size = self.expect_number()?;
+                if size == 0 {
+                    return Err(HdlError::new(self.file, location, "pin width must be positive"));
+                }
+                self.expect_symbol("]")?;
+            }
+            out.push(PinDeclaration { name, size, location });
+            if self.is_symbol(";") {
+                self.pos += 1;
+                return Ok(out);
+            }
+            self.expect_symbol(",")?;
+        }
+    }
+
+    fn parse_part(&mut self) -> Result<PartDefinition, HdlError> {
+        let (name, location) = self.expect_ident()?;
+        self.expect_symbol("(")?;
+        let mut connections = Vec::new();
+        loop {
+            let inner = self.parse_reference()?;
+            self.expect_symbol("=")?;
+            let outer = self.parse_reference()?;
+            connections.push(PartConnection { inner, outer });
+            if self.is_symbol(")") {
+                self.pos += 1;
+                break;
+            }
+            self.expect_symbol(",")?;
+        }
+        self.expect_symbol(";")?;
+        Ok(PartDefinition { name, connections, location })
+    }
+
+    fn parse_reference(&mut self) -> Result<PinReference, HdlError> {
+        let (name, location) = self.expect_ident()?;
+        let mut range = None;
+        if self.is_symbol("[") {
+            self.pos += 1;
+            let start = self.expect_number()?;
+            let mut end = start;
+            if self.is_symbol("..") {
+                self.pos += 1;
+                end = self.expect_number()?;
+            }
+            self.expect_symbol("]")?;
+            if end < start {
+                return Err(HdlError::new(self.file, location, &format!("invalid bit range {}..{}", start, end)));
+            }
+            range = Some((start, end));
+        }
+        Ok(PinReference { name, range, location })
+    }
+}
+
+pub fn parse_hdl(file: &str, source: &str) -> Result<ChipDefinition, HdlError> {
+    let tokens = Lexer::new(file, source).tokenize()?;
+    let end = match tokens.last() {
+        Some((_, l)) => Location { line: l.line, column: l.column + 1 },
+        None => Location { line: 1, column: 1 }
+    };
+    let mut parser = Parser { file, tokens, pos: 0, end };
+    parser.parse_chip()
+}
+
+#[derive(Debug, Clone)]
+enum WireSource {
+    Pin(String, i64),
+    Constant(bool)
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedPart {
+    name: String,
+    // child pin -> parent pin or constant
+    wires: Vec<(PinKey, WireSource)>,
+    location: Location
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedChip {
+    name: String,
+    signature: GateSignature,
+    internals: Vec<(String, i64)>,
+    parts: Vec<ResolvedPart>,
+    location: Location
+}
+
+fn chip_name(name: &str) -> String {
+    name.to_lowercase()
+}
+
+fn reference_range(file: &str, reference: &PinReference, size: i64) -> Result<(i64, i64), HdlError> {
+    match reference.range {
+        None => Ok((0, size)),
+        Some((start, end)) => {
+            if end >= size {
+                return Err(HdlError::new(file, reference.location,
+                    &format!("bit range {}..{} is out of bounds of {}[{}]", start, end, reference.name, size)));
+            }
+            Ok((start, end + 1))
+        }
+    }
+}
+
+fn resolve(chip: &ChipDefinition, factory: &GateFactory) -> Result<ResolvedChip, HdlError> {
+    let file = chip.file.as_str();
+    let mut signature = GateSignature::default();
+    let mut declared: BTreeSet<&str> = BTreeSet::new();
+    for (kind, declaration) in chip.inputs.iter().map(|x| (PinKind::Input, x))
+        .chain(chip.outputs.iter().map(|x| (PinKind::Output, x))) {
+        if declaration.name == "true" || declaration.name == "false" {
+            return Err(HdlError::new(file, declaration.location, "true and false are reserved pin names"));
+        }
+        if !declared.insert(&declaration.name) {
+            return Err(HdlError::new(file, declaration.location, &format!("pin {} is declared twice", declaration.name)));
+        }
+        let pin = (declaration.name.clone(), declaration.size);
+        match kind {
+            PinKind::Input => signature.inputs.push(pin),
+            _ => signature.outputs.push(pin)
+        }
+    }
+
+    let mut internals: BTreeMap<String, i64> = BTreeMap::new();
+    let mut internal_order: Vec<String> = Vec::new();
+    // internal reads are checked once every writer is known
+    let mut internal_reads: Vec<(&PinReference, i64)> = Vec::new();
+    let mut driven: BTreeSet<(String, i64)> = BTreeSet::new();
+    let mut parts = Vec::new();
+
+    for part in &chip.parts {
+        let name = chip_name(&part.name);
+        let part_signature = match factory.get(&name) {
+            Some(x) => &x.signature,
+            None => {
+                return Err(HdlError::new(file, part.location, &format!("chip {} is not defined", part.name)));
+            }
+        };
+        let mut wires = Vec::new();
+        let mut connected: BTreeSet<(String, i64)> = BTreeSet::new();
+        for connection in &part.connections {
+            let inner = &connection.inner;
+            let outer = &connection.outer;
+            let (inner_kind, inner_size) = match part_signature.size(&inner.name) {
+                Some(x) => x,
+                None => {
+                    return Err(HdlError::new(file, inner.location,
+                        &format!("chip {} has no pin named {}", part.name, inner.name)));
+                }
+            };
+            let (inner_start, inner_end) = reference_range(file, inner, inner_size)?;
+            let width = inner_end - inner_start;
+
+            if outer.name == "true" || outer.name == "false" {
+                if inner_kind != PinKind::Input {
+                    return Err(HdlError::new(file, outer.location, "a constant can only feed an input pin"));
+                }
+                if outer.range.is_some() {
+                    return Err(HdlError::new(file, outer.location, "a constant can not have a bit range"));
+                }
+                for i in inner_start..inner_end {
+                    if !connected.insert((inner.name.clone(), i)) {
+                        return Err(HdlError::new(file, inner.location, &format!("pin {}[{}] is connected twice", inner.name, i)));
+                    }
+                    wires.push((PinKey::new(&inner.name, i), WireSource::Constant(outer.name == "true")));
+                }
+                continue;
+            }
+
+            let outer_pin = signature.size(&outer.name);
+            let (outer_start, outer_end) = match outer_pin {
+                Some((kind, size)) => {
+                    match (inner_kind, kind) {
+                        (PinKind::Input, PinKind::Output) => {
+                            return Err(HdlError::new(file, outer.location,
+                                &format!("output pin {} can not be used as a part input", outer.name)));
+                        },
+                        (PinKind::Output, PinKind::Input) => {
+                            return Err(HdlError::new(file, outer.location,
+                                &format!("input pin {} can not be driven by a part", outer.name)));
+                        },
+                        _ => {}
+                    }
+                    reference_range(file, outer, size)?
+                },
+                None => {
+                    if outer.range.is_some() {
+                        return Err(HdlError::new(file, outer.location,
+                            &format!("a bit range of internal pin {} can not be used", outer.name)));
+                    }
+                    if inner_kind == PinKind::Output {
+                        if internals.contains_key(&outer.name) {
+                            return Err(HdlError::new(file, outer.location,
+                                &format!("internal pin {} is driven twice", outer.name)));
+                        }
+                        internals.insert(outer.name.clone(), width);
+                        internal_order.push(outer.name.clone());
+                    } else {
+                        internal_reads.push((outer, width));
+                    }
+                    (0, width)
+                }
+            };
+            if outer_end - outer_start != width {
+                return Err(HdlError::new(file, outer.location,
+                    &format!("width mismatch: {} is {} bits but {} is {} bits",
+                        inner.name, width, outer.name, outer_end - outer_start)));
+            }
+            for i in 0..width {
+                if inner_kind == PinKind::Input {
+                    if !connected.insert((inner.name.clone(), inner_start + i)) {
+                        return Err(HdlError::new(file, inner.location,
+                            &format!("pin {}[{}] is connected twice", inner.name, inner_start + i)));
+                    }
+                } else if let Some((PinKind::Output, _)) = outer_pin {
+                    if !driven.insert((outer.name.clone(), outer_start + i)) {
+                        return Err(HdlError::new(file, outer.location,
+                            &format!("output pin {}[{}] is driven twice", outer.name, outer_start + i)));
+                    }
+                }
+                wires.push((PinKey::new(&inner.name, inner_start + i), WireSource::Pin(outer.name.clone(), outer_start + i)));
+            }
+        }
+        parts.push(ResolvedPart { name, wires, location: part.location });
+    }
+
+    for (reference, width) in internal_reads {
+        match internals.get(&reference.name) {
+            None => {
+                return Err(HdlError::new(file, reference.location,
+                    &format!("internal pin {} is never driven", reference.name)));
+            },
+            Some(size) if *size != width => {
+                return Err(HdlError::new(file, reference.location,
+                    &format!("width mismatch: {} is {} bits but {} bits are used", reference.name, size, width)));
+            },
+            _ => {}
+        }
+    }
+
+    Ok(ResolvedChip {
+        name: chip_name(&chip.name),
+        signature,
+        internals: internal_order.into_iter().map(|x| {
+            let size = internals[&x];
+            (x, size)
+        }).collect(),
+        parts,
+        location: chip.location
+    })
+}
+
+fn build_chip(chip: &ResolvedChip, file: &str, f: &GateFactory) -> Result<Gate, HdlError> {
+    let mut gate = Gate::new(&chip.name);
+    for (name, size) in &chip.signature.inputs {
+        for i in 0..*size {
+            gate.insert_pin(PinKind::Input, name, *size, i);
+        }
+    }
+    for (name, size) in &chip.signature.outputs {
+        for i in 0..*size {
+            gate.insert_pin(PinKind::Output, name, *size, i);
+        }
+    }
+    for (name, size) in &chip.internals {
+        for i in 0..*size {
+            gate.insert_pin(PinKind::Internal, name, *size, i);
+        }
+    }
+    for part in &chip.parts {
+        let child = f.try_build(&part.name).map_err(|x| HdlError::new(file, part.location, &x))?;
+        let gi = gate.add_gate(child);
+        for (child, source) in &part.wires {
+            let res = match source {
+                WireSource::Pin(name, index) => gate.connect_pins(gi, &PinKey::new(name, *index), child),
+                WireSource::Constant(value) => gate.connect_constant(gi, child, *value)
+            };
+            if let Err(x) = res {
+                return Err(HdlError::new(file, part.location, &format!("failed to connect {} of {}: {}", child.name, part.name, x)));
+            }
+        }
+    }
+    if let Err(x) = gate.compile() {
+        let location = match x {
+            GateValidationError::CombinationalLoop => loop_part(chip, &gate).unwrap_or(chip.location),
+            _ => chip.location
+        };
+        return Err(HdlError::new(file, location, &format!("{} in chip {}", x, chip.name)));
+    }
+    Ok(gate)
+}
+
+// parent pin -> parent pins written by the parts reading it
+type PinEdges<'a> = BTreeMap<(&'a str, i64), Vec<((&'a str, i64), Location)>>;
+
+// Location of a part on a combinational loop: an edge goes from the parent
+// pin a part reads to the parent pin it writes when that output depends on
+// the input
+fn loop_part(chip: &ResolvedChip, gate: &Gate) -> Option<Location> {
+    let mut edges = PinEdges::new();
+    for (i, part) in chip.parts.iter().enumerate() {
+        let child = &gate.gates[i];
+        for (output, target) in &part.wires {
+            let target = match target {
+                WireSource::Pin(name, index) if child.get_pin(&output.name, output.index).map(|x| x.kind) == Some(PinKind::Output) => (name.as_str(), *index),
+                _ => continue
+            };
+            let inputs = match child.dependencies(&output.name) {
+                Some(x) => x,
+                None => continue
+            };
+            for (input, source) in &part.wires {
+                if let WireSource::Pin(name, index) = source {
+                    if inputs.contains(&input.name) {
+                        edges.entry((name.as_str(), *index)).or_default().push((target, part.location));
+                    }
+                }
+            }
+        }
+    }
+    // depth first search, 1 while a pin is on the path and 2 once done
+    fn visit<'a>(pin: (&'a str, i64), edges: &PinEdges<'a>, state: &mut BTreeMap<(&'a str, i64), u8>) -> Option<Location> {
+        state.insert(pin, 1);
+        for (next, location) in edges.get(&pin).into_iter().flatten() {
+            match state.get(next) {
+                Some(1) => return Some(*location),
+                Some(_) => {},
+                None => {
+                    if let Some(x) = visit(*next, edges, state) {
+                        return Some(x);
+                    }
+                }
+            }
+        }
+        state.insert(pin, 2);
+        None
+    }
+    let mut state = BTreeMap::new();
+    for pin in edges.keys() {
+        if !state.contains_key(pin) {
+            if let Some(x) = visit(*pin, &edges, &mut state) {
+                return Some(x);
+            }
+        }
+    }
+    None
+}
+
+// The chip is built once here so that a loop or a bad connection is an
+// error of the hdl when it is loaded and not of every later build
+pub fn chip_function(chip: &ChipDefinition, factory: &GateFactory) -> Result<GateFactoryFunction, HdlError> {
+    let resolved = Rc::new(resolve(chip, factory)?);
+    build_chip(&resolved, &chip.file, factory)?;
+    let generator = resolved.clone();
+    let file = chip.file.clone();
+    Ok(GateFactoryFunction {
+        name: resolved.name.clone(),
+        signature: resolved.signature.clone(),
+        generator: Rc::new(move |f: &GateFactory| build_chip(&generator, &file, f).map_err(|x| x.to_string()))
+    })
+}
+
+// Parses every .hdl file of a directory, by chip name
+fn read_hdl_dir(path: &Path) -> Result<BTreeMap<String, ChipDefinition>, HdlError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path).map_err(|e| read_error(path, e))? {
+        let entry = entry.map_err(|e| read_error(path, e))?.path();
+        if entry.extension().map(|x| x == "hdl").unwrap_or(false) {
+            files.push(entry);
+        }
+    }
+    files.sort();
+    let mut chips = BTreeMap::new();
+    for file in &files {
+        let source = fs::read_to_string(file).map_err(|e| read_error(file, e))?;
+        let chip = parse_hdl(&file.display().to_string(), &source)?;
+        chips.insert(chip_name(&chip.name), chip);
+    }
+    Ok(chips)
+}
+
+fn read_error(path: &Path, err: std::io::Error) -> HdlError {
+    HdlError::new(&path.display().to_string(), Location::default(), &err.to_string())
+}
+
+impl GateFactory {
+    pub fn register_chip(&mut self, chip: &ChipDefinition) -> Result<String, HdlError> {
+        if let Some(builtin) = &chip.builtin {
+            let name = chip_name(builtin);
+            let func = match self.get(&name) {
+                Some(x) => x,
+                None => {
+                    return Err(HdlError::new(&chip.file, chip.location, &format!("no builtin chip named {}", builtin)));
+                }
+            };
+            let signature = resolve(chip, self)?.signature;
+            if func.signature != signature {
+                return Err(HdlError::new(&chip.file, chip.location,
+                    &format!("pins of {} do not match the builtin chip {}", chip.name, builtin)));
+            }
+            if name != chip_name(&chip.name) {
+                let mut func = func.clone();
+                func.name = chip_name(&chip.name);
+                self.register_function(func);
+            }
+            return Ok(chip_name(&chip.name));
+        }
+        let func = chip_function(chip, self)?;
+        let name = func.name.clone();
+        self.register_function(func);
+        Ok(name)
+    }
+
+    pub fn load_hdl(&mut self, file: &str, source: &str) -> Result<String, HdlError> {
+        let chip = parse_hdl(file, source)?;
+        self.register_chip(&chip)
+    }
+
+    pub fn load_hdl_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String, HdlError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| read_error(path, e))?;
+        self.load_hdl(&path.display().to_string(), &source)
+    }
+
+    // Loads every .hdl file of a directory. Chips may use each other
+    // regardless of file order; they are registered after the chips they use.
+    pub fn load_hdl_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<String>, HdlError> {
+        let mut chips = read_hdl_dir(path.as_ref())?;
+        let mut out = Vec::new();
+        let mut visiting = BTreeSet::new();
+        let names: Vec<String> = chips.keys().cloned().collect();
+        for name in names {
+            self.register_ordered(&name, &mut chips, &mut visiting, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    // Loads an .hdl file with the chips of its directory that it uses, so
+    // they take the place of registered chips of the same name
+    pub fn load_hdl_with_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<String, HdlError> {
+        let path = path.as_ref();
+        let dir = match path.parent() {
+            Some(x) if !x.as_os_str().is_empty() => x,
+            _ => Path::new(".")
+        };
+        let mut chips = read_hdl_dir(dir)?;
+        let source = fs::read_to_string(path).map_err(|e| read_error(path, e))?;
+        let chip = parse_hdl(&path.display().to_string(), &source)?;
+        let name = chip_name(&chip.name);
+        chips.insert(name.clone(), chip);
+        let mut out = Vec::new();
+        self.register_ordered(&name, &mut chips, &mut BTreeSet::new(), &mut out)?;
+        Ok(name)
+    }
+
+    fn register_ordered(&mut self, name: &str, chips: &mut BTreeMap<String, ChipDefinition>,
+        visiting: &mut BTreeSet<String>, out: &mut Vec<String>) -> Result<(), HdlError> {
+        let chip = match chips.get(name) {
+            Some(x) => x.clone(),
+            None => return Ok(())
+        };
+        visiting.insert(name.to_string());
+        for part in &chip.parts {
+            let part_name = chip_name(&part.name);
+            if part_name == name || visiting.contains(&part_name) {
+                return Err(HdlError::new(&chip.file, part.location, &format!("chip {} is defined recursively", part.name)));
+            }
+            self.register_ordered(&part_name, chips, visiting, out)?;
+        }
+        visiting.remove(name);
+        chips.remove(name);
+        out.push(self.register_chip(&chip)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+// JSON form of chip definitions and pin values
+//
+// {
+//     "name": "And",
+//     "inputs": [{"name": "a", "width": 1}, {"name": "b", "width": 1}],
+//     "outputs": [{"name": "out", "width": 1}],
+//     "parts": [
+//         {"chip": "Nand", "connections": [
+//             {"inner": {"name": "a"}, "outer": {"name": "a"}},
+//             {"inner": {"name": "b"}, "outer": {"name": "b"}},
+//             {"inner": {"name": "out"}, "outer": {"name": "nandab"}}
+//         ]},
+//         ...
+//     ]
+// }
+//
+// It is the same as the hdl: a reference may have an inclusive "range"
+// [start, end], the outer pin may be true or false, and a chip with
+// "builtin": "And" instead of parts uses the builtin chip. Pin values are an
+// object from pin name to the bits, pin index 0 first, with null for the
+// bits that are not set.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::gates::factory::GateFactory;
+use crate::gates::hdl::{ChipDefinition, HdlError, Location, PartConnection, PartDefinition, PinDeclaration, PinReference};
+use crate::gates::utils::PinValues;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+// Errors found after parsing have no position, they name the path of the
+// field instead
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<JsonError> for HdlError {
+    fn from(x: JsonError) -> HdlError {
+        HdlError { file: x.file, line: x.line, column: x.column, message: x.message }
+    }
+}
+
+fn parse(file: &str, source: &str) -> Result<Value, JsonError> {
+    serde_json::from_str(source).map_err(|e| JsonError {
+        file: file.to_string(),
+        line: e.line(),
+        column: e.column(),
+        message: e.to_string()
+    })
+}
+
+// Reads the fields of a value, errors name the path of the field like
+// parts[0].chip
+struct Reader<'a> {
+    file: &'a str
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, path: &str, message: &str) -> JsonError {
+        JsonError { file: self.file.to_string(), line: 0, column: 0, message: format!("{}: {}", path, message) }
+    }
+
+    fn object<'v>(&self, value: &'v Value, path: &str) -> Result<&'v Map<String, Value>, JsonError> {
+        value.as_object().ok_or_else(|| self.error(path, "expected an object"))
+    }
+
+    fn field<'v>(&self, object: &'v Map<String, Value>, path: &str, name: &str) -> Result<&'v Value, JsonError> {
+        object.get(name).ok_or_else(|| self.error(path, &format!("missing field {}", name)))
+    }
+
+    fn string(&self, value: &Value, path: &str) -> Result<String, JsonError> {
+        value.as_str().map(|x| x.to_string()).ok_or_else(|| self.error(path, "expected a string"))
+    }
+
+    fn number(&self, value: &Value, path: &str) -> Result<i64, JsonError> {
+        value.as_i64().filter(|x| *x >= 0).ok_or_else(|| self.error(path, "expected a non negative integer"))
+    }
+
+    fn array<'v>(&self, value: &'v Value, path: &str) -> Result<&'v Vec<Value>, JsonError> {
+        value.as_array().ok_or_else(|| self.error(path, "expected an array"))
+    }
+
+    fn pins(&self, value: &Value, path: &str) -> Result<Vec<PinDeclaration>, JsonError> {
+        let mut out = Vec::new();
+        for (i, pin) in self.array(value, path)?.iter().enumerate() {
+            let path = format!("{}[{}]", path, i);
+            let object = self.object(pin, &path)?;
+            let name = self.string(self.field(object, &path, "name")?, &format!("{}.name", path))?;
+            let size = match object.get("width") {
+                Some(x) => self.number(x, &format!("{}.width", path))?,
+                None => 1
+            };
+            if size == 0 {
+                return Err(self.error(&format!("{}.width", path), "width must be at least 1"));
+            }
+            out.push(PinDeclaration { name, size, location: Location::default() });
+        }
+        Ok(out)
+    }
+
+    fn reference(&self, value: &Value, path: &str) -> Result<PinReference, JsonError> {
+        let object = self.object(value, path)?;
+        let name = self.string(self.field(object, path, "name")?, &format!("{}.name", path))?;
+        let range = match object.get("range") {
+            None | Some(Value::Null) => None,
+            Some(x) => {
+                let path = format!("{}.range", path);
+                let bounds = self.array(x, &path)?;
+                if bounds.len() != 2 {
+                    return Err(self.error(&path, "expected [start, end]"));
+                }
+                let (start, end) = (self.number(&bounds[0], &path)?, self.number(&bounds[1], &path)?);
+                if end < start {
+                    return Err(self.error(&path, &format!("invalid bit range {}..{}", start, end)));
+                }
+                Some((start, end))
+            }
+        };
+        Ok(PinReference { name, range, location: Location::default() })
+    }
+
+    fn chip(&self, value: &Value) -> Result<ChipDefinition, JsonError> {
+        let object = self.object(value, "chip")?;
+        let name = self.string(self.field(object, "chip", "name")?, "name")?;
+        let inputs = match object.get("inputs") {
+            Some(x) => self.pins(x, "inputs")?,
+            None => Vec::new()
+        };
+        let outputs = match object.get("outputs") {
+            Some(x) => self.pins(x, "outputs")?,
+            None => Vec::new()
+        };
+        let builtin = match object.get("builtin") {
+            None | Some(Value::Null) => None,
+            Some(x) => Some(self.string(x, "builtin")?)
+        };
+        let mut parts = Vec::new();
+        if let Some(value) = object.get("parts") {
+            for (i, part) in self.array(value, "parts")?.iter().enumerate() {
+                let path = format!("parts[{}]", i);
+                let object = self.object(part, &path)?;
+                let name = self.string(self.field(object, &path, "chip")?, &format!("{}.chip", path))?;
+                let mut connections = Vec::new();
+                let list = self.field(object, &path, "connections")?;
+                for (j, connection) in self.array(list, &format!("{}.connections", path))?.iter().enumerate() {
+                    let path = format!("{}.connections[{}]", path, j);
+                    let object = self.object(connection, &path)?;
+                    let inner = self.reference(self.field(object, &path, "inner")?, &format!("{}.inner", path))?;
+                    let outer = self.reference(self.field(object, &path, "outer")?, &format!("{}.outer", path))?;
+                    connections.push(PartConnection { inner, outer });
+                }
+                parts.push(PartDefinition { name, connections, location: Location::default() });
+            }
+        }
+        Ok(ChipDefinition { file: self.file.to_string(), name, inputs, outputs, parts, builtin, location: Location::default() })
+    }
+}
+
+fn pins_json(pins: &[PinDeclaration]) -> Value {
+    Value::Array(pins.iter().map(|x| json!({"name": x.name, "width": x.size})).collect())
+}
+
+fn reference_json(reference: &PinReference) -> Value {
+    match reference.range {
+        Some((start, end)) => json!({"name": reference.name, "range": [start, end]}),
+        None => json!({"name": reference.name})
+    }
+}
+
+impl ChipDefinition {
+    pub fn to_json_value(&self) -> Value {
+        let mut out = Map::new();
+        out.insert("name".to_string(), json!(self.name));
+        out.insert("inputs".to_string(), pins_json(&self.inputs));
+        out.insert("outputs".to_string(), pins_json(&self.outputs));
+        match &self.builtin {
+            Some(builtin) => {
+                out.insert("builtin".to_string(), json!(builtin));
+            },
+            None => {
+                let parts = self.parts.iter().map(|part| {
+                    let connections: Vec<Value> = part.connections.iter().map(|x| {
+                        json!({"inner": reference_json(&x.inner), "outer": reference_json(&x.outer)})
+                    }).collect();
+                    json!({"chip": part.name, "connections": connections})
+                }).collect();
+                out.insert("parts".to_string(), Value::Array(parts));
+            }
+        }
+        Value::Object(out)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json_value()).unwrap()
+    }
+
+    pub fn from_json_value(file: &str, value: &Value) -> Result<ChipDefinition, JsonError> {
+        Reader { file }.chip(value)
+    }
+
+    pub fn from_json(file: &str, source: &str) -> Result<ChipDefinition, JsonError> {
+        ChipDefinition::from_json_value(file, &parse(file, source)?)
+    }
+}
+
+impl PinValues {
+    pub fn to_json_value(&self) -> Value {
+        let mut out = Map::new();
+        for (key, value) in self.iter() {
+            let bits = out.entry(key.name.clone()).or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(bits) = bits {
+                while bits.len() < key.index as usize {
+                    bits.push(Value::Null);
+                }
+                bits.push(Value::Bool(*value));
+            }
+        }
+        Value::Object(out)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.to_json_value()).unwrap()
+    }
+
+    pub fn from_json_value(file: &str, value: &Value) -> Result<PinValues, JsonError> {
+        let reader = Reader { file };
+        let mut out = PinValues::new();
+        for (name, bits) in reader.object(value, "values")? {
+            for (i, bit) in reader.array(bits, name)?.iter().enumerate() {
+                match bit {
+                    Value::Bool(x) => out.set(name, i as i64, *x),
+                    Value::Null => {},
+                    _ => return Err(reader.error(&format!("{}[{}]", name, i), "expected true, false or null"))
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn from_json(file: &str, source: &str) -> Result<PinValues, JsonError> {
+        PinValues::from_json_value(file, &parse(file, source)?)
+    }
+}
+
+impl GateFactory {
+    pub fn load_json(&mut self, file: &str, source: &str) -> Result<String, HdlError> {
+        let chip = ChipDefinition::from_json(file, source)?;
+        self.register_chip(&chip)
+    }
+
+    pub fn load_json_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String, HdlError> {
+        let path = path.as_ref();
+        let file = path.display().to_string();
+        let source = fs::read_to_string(path).map_err(|e| HdlError { file: file.clone(), line: 0, column: 0, message: e.to_string() })?;
+        self.load_json(&file, &source)
+    }
+}
@@ -1,13 +1,12 @@
-
 use super::GateFactoryFunction;
-#[macro_use] use crate::build_gate_function;
-#[macro_use] use crate::connect;
+use crate::build_gate_function;
+use crate::connect;
 
 pub fn gate_not() -> GateFactoryFunction {
     build_gate_function! {
-        not(input => out):
+        not(in => out):
             | g: &mut Gate, f: &GateFactory | {
-                connect!(g, f, nand { a=input, b=input } => { out=out });
+                connect!(g, f, nand { a=in, b=in } => { out=out });
             }
     }
 }
@@ -16,9 +15,74 @@ pub fn gate_or() -> GateFactoryFunction {
     build_gate_function! {
         or(a, b => out):
             | g: &mut Gate, f: &GateFactory | {
-                connect!(g, f, not { input=a } => { out=nota });
-                connect!(g, f, not { input=b } => { out=notb });
+                connect!(g, f, not { in=a } => { out=nota });
+                connect!(g, f, not { in=b } => { out=notb });
                 connect!(g, f, nand { a=nota, b=notb } => { out=out });
             }
     }
 }
+
+pub fn gate_and() -> GateFactoryFunction {
+    build_gate_function! {
+        and(a, b => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, nand { a=a, b=b } => { out=nandab });
+                connect!(g, f, not { in=nandab } => { out=out });
+            }
+    }
+}
+
+pub fn gate_xor() -> GateFactoryFunction {
+    build_gate_function! {
+        xor(a, b => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, nand { a=a, b=b } => { out=nandab });
+                connect!(g, f, nand { a=a, b=nandab } => { out=x });
+                connect!(g, f, nand { a=b, b=nandab } => { out=y });
+                connect!(g, f, nand { a=x, b=y } => { out=out });
+            }
+    }
+}
+
+pub fn gate_nor() -> GateFactoryFunction {
+    build_gate_function! {
+        nor(a, b => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, or { a=a, b=b } => { out=orab });
+                connect!(g, f, not { in=orab } => { out=out });
+            }
+    }
+}
+
+pub fn gate_xnor() -> GateFactoryFunction {
+    build_gate_function! {
+        xnor(a, b => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, xor { a=a, b=b } => { out=xorab });
+                connect!(g, f, not { in=xorab } => { out=out });
+            }
+    }
+}
+
+pub fn gate_mux() -> GateFactoryFunction {
+    build_gate_function! {
+        mux(a, b, sel => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, not { in=sel } => { out=notsel });
+                connect!(g, f, nand { a=a, b=notsel } => { out=x });
+                connect!(g, f, nand { a=b, b=sel } => { out=y });
+                connect!(g, f, nand { a=x, b=y } => { out=out });
+            }
+    }
+}
+
+pub fn gate_dmux() -> GateFactoryFunction {
+    build_gate_function! {
+        dmux(in, sel => a, b):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, not { in=sel } => { out=notsel });
+                connect!(g, f, and { a=in, b=notsel } => { out=a });
+                connect!(g, f, and { a=in, b=sel } => { out=b });
+            }
+    }
+}
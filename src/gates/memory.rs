@@ -0,0 +1,126 @@
+use super::GateFactoryFunction;
+use crate::build_gate_function;
+use crate::connect;
+
+pub fn gate_bit() -> GateFactoryFunction {
+    build_gate_function! {
+        bit(in, load => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, mux { a=dffout, b=in, sel=load } => { out=muxout });
+                connect!(g, f, dff { in=muxout } => { out=dffout, out=out });
+            }
+    }
+}
+
+pub fn gate_register() -> GateFactoryFunction {
+    build_gate_function! {
+        register(in[16], load => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                for i in 0..16 {
+                    connect!(g, f, bit { in=in[i], load=load } => { out=out[i] });
+                }
+            }
+    }
+}
+
+pub fn gate_ram8() -> GateFactoryFunction {
+    build_gate_function! {
+        ram8(in[16], load, address[3] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, dmux8way { in=load, sel=address } => { a=la, b=lb, c=lc, d=ld, e=le, f=lf, g=lg, h=lh });
+                connect!(g, f, register { in=in, load=la } => { out=ra });
+                connect!(g, f, register { in=in, load=lb } => { out=rb });
+                connect!(g, f, register { in=in, load=lc } => { out=rc });
+                connect!(g, f, register { in=in, load=ld } => { out=rd });
+                connect!(g, f, register { in=in, load=le } => { out=re });
+                connect!(g, f, register { in=in, load=lf } => { out=rf });
+                connect!(g, f, register { in=in, load=lg } => { out=rg });
+                connect!(g, f, register { in=in, load=lh } => { out=rh });
+                connect!(g, f, mux8way16 { a=ra, b=rb, c=rc, d=rd, e=re, f=rf, g=rg, h=rh, sel=address } => { out=out });
+            }
+    }
+}
+
+// The bigger memories are eight (four for ram16k) of the next smaller one,
+// selected by the high bits of the address.
+pub fn gate_ram64() -> GateFactoryFunction {
+    build_gate_function! {
+        ram64(in[16], load, address[6] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, dmux8way { in=load, sel=address[3, 6] } => { a=la, b=lb, c=lc, d=ld, e=le, f=lf, g=lg, h=lh });
+                connect!(g, f, ram8 { in=in, load=la, address=address[0, 3] } => { out=ra });
+                connect!(g, f, ram8 { in=in, load=lb, address=address[0, 3] } => { out=rb });
+                connect!(g, f, ram8 { in=in, load=lc, address=address[0, 3] } => { out=rc });
+                connect!(g, f, ram8 { in=in, load=ld, address=address[0, 3] } => { out=rd });
+                connect!(g, f, ram8 { in=in, load=le, address=address[0, 3] } => { out=re });
+                connect!(g, f, ram8 { in=in, load=lf, address=address[0, 3] } => { out=rf });
+                connect!(g, f, ram8 { in=in, load=lg, address=address[0, 3] } => { out=rg });
+                connect!(g, f, ram8 { in=in, load=lh, address=address[0, 3] } => { out=rh });
+                connect!(g, f, mux8way16 { a=ra, b=rb, c=rc, d=rd, e=re, f=rf, g=rg, h=rh, sel=address[3, 6] } => { out=out });
+            }
+    }
+}
+
+pub fn gate_ram512() -> GateFactoryFunction {
+    build_gate_function! {
+        ram512(in[16], load, address[9] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, dmux8way { in=load, sel=address[6, 9] } => { a=la, b=lb, c=lc, d=ld, e=le, f=lf, g=lg, h=lh });
+                connect!(g, f, ram64 { in=in, load=la, address=address[0, 6] } => { out=ra });
+                connect!(g, f, ram64 { in=in, load=lb, address=address[0, 6] } => { out=rb });
+                connect!(g, f, ram64 { in=in, load=lc, address=address[0, 6] } => { out=rc });
+                connect!(g, f, ram64 { in=in, load=ld, address=address[0, 6] } => { out=rd });
+                connect!(g, f, ram64 { in=in, load=le, address=address[0, 6] } => { out=re });
+                connect!(g, f, ram64 { in=in, load=lf, address=address[0, 6] } => { out=rf });
+                connect!(g, f, ram64 { in=in, load=lg, address=address[0, 6] } => { out=rg });
+                connect!(g, f, ram64 { in=in, load=lh, address=address[0, 6] } => { out=rh });
+                connect!(g, f, mux8way16 { a=ra, b=rb, c=rc, d=rd, e=re, f=rf, g=rg, h=rh, sel=address[6, 9] } => { out=out });
+            }
+    }
+}
+
+pub fn gate_ram4k() -> GateFactoryFunction {
+    build_gate_function! {
+        ram4k(in[16], load, address[12] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, dmux8way { in=load, sel=address[9, 12] } => { a=la, b=lb, c=lc, d=ld, e=le, f=lf, g=lg, h=lh });
+                connect!(g, f, ram512 { in=in, load=la, address=address[0, 9] } => { out=ra });
+                connect!(g, f, ram512 { in=in, load=lb, address=address[0, 9] } => { out=rb });
+                connect!(g, f, ram512 { in=in, load=lc, address=address[0, 9] } => { out=rc });
+                connect!(g, f, ram512 { in=in, load=ld, address=address[0, 9] } => { out=rd });
+                connect!(g, f, ram512 { in=in, load=le, address=address[0, 9] } => { out=re });
+                connect!(g, f, ram512 { in=in, load=lf, address=address[0, 9] } => { out=rf });
+                connect!(g, f, ram512 { in=in, load=lg, address=address[0, 9] } => { out=rg });
+                connect!(g, f, ram512 { in=in, load=lh, address=address[0, 9] } => { out=rh });
+                connect!(g, f, mux8way16 { a=ra, b=rb, c=rc, d=rd, e=re, f=rf, g=rg, h=rh, sel=address[9, 12] } => { out=out });
+            }
+    }
+}
+
+pub fn gate_ram16k() -> GateFactoryFunction {
+    build_gate_function! {
+        ram16k(in[16], load, address[14] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, dmux4way { in=load, sel=address[12, 14] } => { a=la, b=lb, c=lc, d=ld });
+                connect!(g, f, ram4k { in=in, load=la, address=address[0, 12] } => { out=ra });
+                connect!(g, f, ram4k { in=in, load=lb, address=address[0, 12] } => { out=rb });
+                connect!(g, f, ram4k { in=in, load=lc, address=address[0, 12] } => { out=rc });
+                connect!(g, f, ram4k { in=in, load=ld, address=address[0, 12] } => { out=rd });
+                connect!(g, f, mux4way16 { a=ra, b=rb, c=rc, d=rd, sel=address[12, 14] } => { out=out });
+            }
+    }
+}
+
+// reset takes priority over load, load over inc
+pub fn gate_pc() -> GateFactoryFunction {
+    build_gate_function! {
+        pc(in[16], load, inc, reset => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, inc16 { in=pcout } => { out=incremented });
+                connect!(g, f, mux16 { a=pcout, b=incremented, sel=inc } => { out=afterinc });
+                connect!(g, f, mux16 { a=afterinc, b=in, sel=load } => { out=afterload });
+                connect!(g, f, mux16 { a=afterload, b=false, sel=reset } => { out=next });
+                connect!(g, f, register { in=next, load=true } => { out=pcout, out=out });
+            }
+    }
+}
@@ -4,23 +4,100 @@ mod graph;
 mod gate;
 mod utils;
 mod logics;
+mod multi;
+mod arithmetic;
+mod memory;
+mod builtins;
+mod computer;
+mod hdl;
+mod script;
+mod vcd;
+mod dot;
+mod json;
+mod netlist;
+mod parallel;
+mod truth;
+mod bdd;
+mod optimize;
+mod stats;
+mod timing;
 
-pub use factory::{GateFactory, GateFactoryFunction};
-pub use utils::{PinValues};
+pub use factory::{GateFactory, GateFactoryFunction, GateGenerator, GateSignature};
+pub use gate::{Gate, Pin, PinKind, Connection, GateValidationError, PrimitiveGateImplementor};
+pub use utils::{PinKey, PinValues};
+pub use hdl::{parse_hdl, chip_function, ChipDefinition, HdlError, Location, PartConnection, PartDefinition, PinDeclaration, PinReference};
+pub use script::{load_script, parse_script, run_script_file, Command, Comparison, Format, OutputColumn, ScriptError, ScriptRunner, Statement, TestScript};
+pub use vcd::{part_names, VcdError, VcdRecorder};
+pub use dot::{to_dot, DotOptions};
+pub use json::JsonError;
+pub use netlist::{Cell, CellKind, Driver, Net, Netlist, Port};
+pub use parallel::{ParallelSimulator, LANES};
+pub use truth::{check_equivalence, Counterexample, TruthTable, TruthTableError, MAX_TRUTH_TABLE_INPUTS};
+pub use bdd::{prove_equivalence, symbolic_outputs, variable_order, Bdd, BddNode, BDD_FALSE, BDD_TRUE, MAX_BDD_NODES};
+pub use optimize::{optimize, OptimizeReport};
+pub use stats::ChipStats;
+pub use timing::{Delays, Glitch, TimingReport, TimingSimulator};
 
-#[macro_use] use crate::build_gate_function;
-#[macro_use] use crate::connect;
-
-use primitives::gate_nand;
-use logics::{gate_or, gate_not};
+use primitives::{gate_nand, gate_dff};
+use logics::{gate_or, gate_not, gate_and, gate_xor, gate_nor, gate_xnor, gate_mux, gate_dmux};
+use multi::{gate_not16, gate_and16, gate_or16, gate_mux16, gate_or8way, gate_mux4way16, gate_mux8way16, gate_dmux4way, gate_dmux8way};
+use arithmetic::{gate_halfadder, gate_fulladder, gate_add16, gate_inc16, gate_alu};
+use memory::{gate_bit, gate_register, gate_ram8, gate_ram64, gate_ram512, gate_ram4k, gate_ram16k, gate_pc};
+use builtins::{gate_rom32k, gate_screen, gate_keyboard, gate_builtin_ram4k, gate_builtin_ram16k};
+use computer::{gate_cpu, gate_memory, gate_computer};
 
 impl GateFactory {
     pub fn new() -> GateFactory {
         let mut out = GateFactory::default();
         out.register(gate_not);
         out.register(gate_nand);
+        out.register(gate_dff);
         out.register(gate_or);
+        out.register(gate_and);
+        out.register(gate_xor);
+        out.register(gate_nor);
+        out.register(gate_xnor);
+        out.register(gate_mux);
+        out.register(gate_dmux);
+        out.register(gate_not16);
+        out.register(gate_and16);
+        out.register(gate_or16);
+        out.register(gate_mux16);
+        out.register(gate_or8way);
+        out.register(gate_mux4way16);
+        out.register(gate_mux8way16);
+        out.register(gate_dmux4way);
+        out.register(gate_dmux8way);
+        out.register(gate_halfadder);
+        out.register(gate_fulladder);
+        out.register(gate_add16);
+        out.register(gate_inc16);
+        out.register(gate_alu);
+        out.register(gate_bit);
+        out.register(gate_register);
+        out.register(gate_ram8);
+        out.register(gate_ram64);
+        out.register(gate_ram512);
+        out.register(gate_ram4k);
+        out.register(gate_ram16k);
+        out.register(gate_pc);
+        out.register(gate_rom32k);
+        out.register(gate_screen);
+        out.register(gate_keyboard);
+        out.register(gate_cpu);
+        out.register(gate_memory);
+        out.register(gate_computer);
+
+        out
+    }
 
+    // Same as new with ram4k and ram16k implemented natively, the ones built
+    // from registers need gigabytes. Memory and computer use the builtin
+    // ram16k with either factory.
+    pub fn with_builtin_memory() -> GateFactory {
+        let mut out = GateFactory::new();
+        out.register(gate_builtin_ram4k);
+        out.register(gate_builtin_ram16k);
         out
     }
 }
@@ -0,0 +1,106 @@
+use super::GateFactoryFunction;
+use crate::build_gate_function;
+use crate::connect;
+
+pub fn gate_not16() -> GateFactoryFunction {
+    build_gate_function! {
+        not16(in[16] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                for i in 0..16 {
+                    connect!(g, f, not { in=in[i] } => { out=out[i] });
+                }
+            }
+    }
+}
+
+pub fn gate_and16() -> GateFactoryFunction {
+    build_gate_function! {
+        and16(a[16], b[16] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                for i in 0..16 {
+                    connect!(g, f, and { a=a[i], b=b[i] } => { out=out[i] });
+                }
+            }
+    }
+}
+
+pub fn gate_or16() -> GateFactoryFunction {
+    build_gate_function! {
+        or16(a[16], b[16] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                for i in 0..16 {
+                    connect!(g, f, or { a=a[i], b=b[i] } => { out=out[i] });
+                }
+            }
+    }
+}
+
+pub fn gate_mux16() -> GateFactoryFunction {
+    build_gate_function! {
+        mux16(a[16], b[16], sel => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                for i in 0..16 {
+                    connect!(g, f, mux { a=a[i], b=b[i], sel=sel } => { out=out[i] });
+                }
+            }
+    }
+}
+
+pub fn gate_or8way() -> GateFactoryFunction {
+    build_gate_function! {
+        or8way(in[8] => out):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, or { a=in[0], b=in[1] } => { out=or01 });
+                connect!(g, f, or { a=in[2], b=in[3] } => { out=or23 });
+                connect!(g, f, or { a=in[4], b=in[5] } => { out=or45 });
+                connect!(g, f, or { a=in[6], b=in[7] } => { out=or67 });
+                connect!(g, f, or { a=or01, b=or23 } => { out=or0123 });
+                connect!(g, f, or { a=or45, b=or67 } => { out=or4567 });
+                connect!(g, f, or { a=or0123, b=or4567 } => { out=out });
+            }
+    }
+}
+
+pub fn gate_mux4way16() -> GateFactoryFunction {
+    build_gate_function! {
+        mux4way16(a[16], b[16], c[16], d[16], sel[2] => out[16]):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, mux16 { a=a, b=b, sel=sel[0] } => { out=ab });
+                connect!(g, f, mux16 { a=c, b=d, sel=sel[0] } => { out=cd });
+                connect!(g, f, mux16 { a=ab, b=cd, sel=sel[1] } => { out=out });
+            }
+    }
+}
+
+pub fn gate_mux8way16() -> GateFactoryFunction {
+    build_gate_function! {
+        mux8way16(a[16], b[16], c[16], d[16], e[16], f[16], g[16], h[16], sel[3] => out[16]):
+            | gate: &mut Gate, factory: &GateFactory | {
+                connect!(gate, factory, mux4way16 { a=a, b=b, c=c, d=d, sel=sel[0, 2] } => { out=abcd });
+                connect!(gate, factory, mux4way16 { a=e, b=f, c=g, d=h, sel=sel[0, 2] } => { out=efgh });
+                connect!(gate, factory, mux16 { a=abcd, b=efgh, sel=sel[2] } => { out=out });
+            }
+    }
+}
+
+pub fn gate_dmux4way() -> GateFactoryFunction {
+    build_gate_function! {
+        dmux4way(in, sel[2] => a, b, c, d):
+            | g: &mut Gate, f: &GateFactory | {
+                connect!(g, f, dmux { in=in, sel=sel[1] } => { a=ab, b=cd });
+                connect!(g, f, dmux { in=ab, sel=sel[0] } => { a=a, b=b });
+                connect!(g, f, dmux { in=cd, sel=sel[0] } => { a=c, b=d });
+            }
+    }
+}
+
+pub fn gate_dmux8way() -> GateFactoryFunction {
+    build_gate_function! {
+        dmux8way(in, sel[3] => a, b, c, d, e, f, g, h):
+            | gate: &mut Gate, factory: &GateFactory | {
+                connect!(gate, factory, dmux { in=in, sel=sel[2] } => { a=abcd, b=efgh });
+                connect!(gate, factory, dmux4way { in=abcd, sel=sel[0, 2] } => { a=a, b=b, c=c, d=d });
+                connect!(gate, factory, dmux4way { in=efgh, sel=sel[0, 2] } => { a=e, b=f, c=g, d=h });
+            }
+    }
+}
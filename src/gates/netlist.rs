@@ -0,0 +1,355 @@
+// Flat netlist of a gate
+//
+// Every part is resolved down to its primitives: nand, dff and the builtin
+// chips. Each bit that is driven by something is a numbered net, the pins
+// wired together in the hierarchy share it. Nets 0 and 1 are the constants
+// false and true, the bits of the inputs of the gate come next and then the
+// outputs of the cells in order. Bits that nothing drives are false like in
+// the gate simulator.
+
+use crate::gates::gate::{Connection, Gate, GateValidationError, PinKind};
+use crate::gates::vcd::part_names;
+
+pub type Net = usize;
+
+pub const FALSE: Net = 0;
+pub const TRUE: Net = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellKind {
+    // inputs a, b and output out
+    Nand,
+    // input in and output out, out[t+1] = in[t]
+    Dff,
+    // builtin chip like ram16k, its pins in declaration order. `dependencies`
+    // has the inputs each output depends on combinationally.
+    Builtin { chip: String, stateful: bool, dependencies: Vec<Vec<usize>> }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub kind: CellKind,
+    // like computer.cpu.alu.not16_0.not_15.nand, see part_names
+    pub path: String,
+    pub inputs: Vec<Net>,
+    pub outputs: Vec<Net>
+}
+
+impl Cell {
+    pub fn is_stateful(&self) -> bool {
+        match &self.kind {
+            CellKind::Nand => false,
+            CellKind::Dff => true,
+            CellKind::Builtin { stateful, .. } => *stateful
+        }
+    }
+
+    // inputs that output `index` depends on combinationally
+    pub fn dependencies(&self, index: usize) -> Vec<usize> {
+        match &self.kind {
+            CellKind::Nand => vec![0, 1],
+            CellKind::Dff => Vec::new(),
+            CellKind::Builtin { dependencies, .. } => dependencies[index].clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Driver {
+    Constant(bool),
+    // pin and bit of the inputs of the netlist
+    Input(usize, usize),
+    // cell and output of the cell
+    Cell(usize, usize)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Port {
+    pub name: String,
+    // nets of the bits, index 0 first
+    pub nets: Vec<Net>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Netlist {
+    pub name: String,
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+    pub cells: Vec<Cell>,
+    drivers: Vec<Driver>,
+    names: Vec<String>
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    // the smaller node is the root so that the net is named after the pin
+    // highest in the hierarchy
+    if a < b {
+        parent[b] = a;
+    } else {
+        parent[a] = b;
+    }
+}
+
+// pins of a gate of one kind ordered by slot as (name, index, slot)
+fn ports(gate: &Gate, kind: PinKind) -> Vec<(String, i64, usize)> {
+    let mut out: Vec<(String, i64, usize)> = gate.pins()
+        .filter(|x| x.kind == kind)
+        .map(|x| (x.name.clone(), x.index, x.slot))
+        .collect();
+    out.sort_by_key(|x| x.2);
+    out
+}
+
+struct Builder<'a> {
+    parent: Vec<usize>,
+    // (first node, gate, scope) of every gate in the order they are visited
+    gates: Vec<(usize, &'a Gate, String)>,
+    // (first node, gate, path) of the primitives
+    cells: Vec<(usize, &'a Gate, String)>
+}
+
+impl<'a> Builder<'a> {
+    fn allocate(&mut self, size: usize) -> usize {
+        let base = self.parent.len();
+        self.parent.extend(base..base + size);
+        base
+    }
+
+    fn walk(&mut self, gate: &'a Gate, base: usize, scope: String) {
+        self.gates.push((base, gate, scope.clone()));
+        let names = part_names(gate);
+        for (i, part) in gate.gates.iter().enumerate() {
+            let part_base = self.allocate(part.values().len());
+            for pin in part.pins() {
+                for connection in pin.connections() {
+                    match connection {
+                        Connection::ToParent(name, index) => {
+                            if let Some(x) = gate.get_pin(name, *index) {
+                                union(&mut self.parent, part_base + pin.slot, base + x.slot);
+                            }
+                        },
+                        Connection::Constant(value) => {
+                            let constant = if *value { TRUE } else { FALSE };
+                            union(&mut self.parent, part_base + pin.slot, constant);
+                        },
+                        Connection::ToChild(..) => {}
+                    }
+                }
+            }
+            let part_scope = format!("{}.{}", scope, names[i]);
+            if part.gates.is_empty() {
+                self.gates.push((part_base, part, part_scope.clone()));
+                self.cells.push((part_base, part, part_scope));
+            } else {
+                self.walk(part, part_base, part_scope);
+            }
+        }
+    }
+}
+
+fn cell_kind(gate: &Gate) -> CellKind {
+    match gate.name.as_str() {
+        "nand" => CellKind::Nand,
+        "dff" => CellKind::Dff,
+        chip => {
+            let inputs = ports(gate, PinKind::Input);
+            let dependencies = ports(gate, PinKind::Output).iter().map(|(name, _, _)| {
+                let names = gate.dependencies(name).cloned().unwrap_or_default();
+                (0..inputs.len()).filter(|i| names.contains(&inputs[*i].0)).collect()
+            }).collect();
+            CellKind::Builtin { chip: chip.to_string(), stateful: gate.is_stateful(), dependencies }
+        }
+    }
+}
+
+fn net_name(scope: &str, name: &str, index: i64, size: i64) -> String {
+    if size > 1 {
+        format!("{}:{}[{}]", scope, name, index)
+    } else {
+        format!("{}:{}", scope, name)
+    }
+}
+
+impl Netlist {
+    // Resolves a compiled gate down to its primitives
+    pub fn flatten(gate: &Gate) -> Netlist {
+        let mut builder = Builder { parent: vec![FALSE, TRUE], gates: Vec::new(), cells: Vec::new() };
+        let base = builder.allocate(gate.values().len());
+        builder.walk(gate, base, gate.name.clone());
+        // a primitive is a netlist of one cell
+        if gate.gates.is_empty() {
+            builder.cells.push((base, gate, gate.name.clone()));
+        }
+        let Builder { mut parent, gates, cells } = builder;
+
+        // nets of the roots of the drivers
+        let mut nets = vec![usize::MAX; parent.len()];
+        let mut drivers = vec![Driver::Constant(false), Driver::Constant(true)];
+        nets[FALSE] = FALSE;
+        nets[TRUE] = TRUE;
+        let mut inputs = Vec::new();
+        for (name, index, slot) in ports(gate, PinKind::Input) {
+            if index == 0 {
+                inputs.push(Port { name: name.clone(), nets: Vec::new() });
+            }
+            let port = inputs.len() - 1;
+            let root = find(&mut parent, base + slot);
+            nets[root] = drivers.len();
+            inputs[port].nets.push(drivers.len());
+            drivers.push(Driver::Input(port, index as usize));
+        }
+        let mut out_cells = Vec::new();
+        for (i, (cell_base, part, path)) in cells.iter().enumerate() {
+            let mut outputs = Vec::new();
+            for (j, (_, _, slot)) in ports(part, PinKind::Output).iter().enumerate() {
+                let root = find(&mut parent, cell_base + slot);
+                nets[root] = drivers.len();
+                outputs.push(drivers.len());
+                drivers.push(Driver::Cell(i, j));
+            }
+            out_cells.push(Cell { kind: cell_kind(part), path: path.clone(), inputs: Vec::new(), outputs });
+        }
+
+        let net = |parent: &mut Vec<usize>, node: usize| {
+            let x = nets[find(parent, node)];
+            if x == usize::MAX { FALSE } else { x }
+        };
+        for (i, (cell_base, part, _)) in cells.iter().enumerate() {
+            out_cells[i].inputs = ports(part, PinKind::Input).iter().map(|x| net(&mut parent, cell_base + x.2)).collect();
+        }
+        let mut outputs: Vec<Port> = Vec::new();
+        for (name, index, slot) in ports(gate, PinKind::Output) {
+            if index == 0 {
+                outputs.push(Port { name: name.clone(), nets: Vec::new() });
+            }
+            let x = net(&mut parent, base + slot);
+            outputs.last_mut().unwrap().nets.push(x);
+        }
+
+        // a net is named after the first pin found on it
+        let mut names = vec![String::new(); drivers.len()];
+        names[FALSE] = "false".to_string();
+        names[TRUE] = "true".to_string();
+        for (gate_base, part, scope) in &gates {
+            for pin in part.pins() {
+                let x = nets[find(&mut parent, gate_base + pin.slot)];
+                if x != usize::MAX && names[x].is_empty() {
+                    names[x] = net_name(scope, &pin.name, pin.index, pin.size);
+                }
+            }
+        }
+
+        Netlist { name: gate.name.clone(), inputs, outputs, cells: out_cells, drivers, names }
+    }
+
+    // Netlist of cells numbered like those of flatten, `names` has a name
+    // for every net
+    pub(crate) fn from_cells(name: String, inputs: Vec<Port>, outputs: Vec<Port>, cells: Vec<Cell>, names: Vec<String>) -> Netlist {
+        let mut drivers = vec![Driver::Constant(false); names.len()];
+        drivers[TRUE] = Driver::Constant(true);
+        for (i, port) in inputs.iter().enumerate() {
+            for (j, net) in port.nets.iter().enumerate() {
+                drivers[*net] = Driver::Input(i, j);
+            }
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            for (j, net) in cell.outputs.iter().enumerate() {
+                drivers[*net] = Driver::Cell(i, j);
+            }
+        }
+        Netlist { name, inputs, outputs, cells, drivers, names }
+    }
+
+    pub fn net_count(&self) -> usize {
+        self.drivers.len()
+    }
+
+    pub fn driver(&self, net: Net) -> Driver {
+        self.drivers[net]
+    }
+
+    // like and:nandab or computer.cpu:areg[3]
+    pub fn net_name(&self, net: Net) -> &str {
+        &self.names[net]
+    }
+
+    pub fn input(&self, name: &str) -> Option<&Port> {
+        self.inputs.iter().find(|x| x.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&Port> {
+        self.outputs.iter().find(|x| x.name == name)
+    }
+
+    pub fn is_stateful(&self) -> bool {
+        self.cells.iter().any(|x| x.is_stateful())
+    }
+
+    // number of cells of each kind, builtins by chip
+    pub fn count(&self, kind: &str) -> usize {
+        self.cells.iter().filter(|x| match &x.kind {
+            CellKind::Nand => kind == "nand",
+            CellKind::Dff => kind == "dff",
+            CellKind::Builtin { chip, .. } => chip == kind
+        }).count()
+    }
+
+    // Cells in an order where every cell comes after the cells that drive
+    // its inputs combinationally. Outputs of dffs only depend on the state.
+    pub fn order(&self) -> Result<Vec<usize>, GateValidationError> {
+        // cells waiting for each net and inputs left for each cell output
+        let mut readers: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.drivers.len()];
+        let mut waiting: Vec<Vec<usize>> = Vec::new();
+        for (i, cell) in self.cells.iter().enumerate() {
+            let mut counts = Vec::new();
+            for j in 0..cell.outputs.len() {
+                let mut count = 0;
+                for k in cell.dependencies(j) {
+                    let net = cell.inputs[k];
+                    if let Driver::Cell(..) = self.drivers[net] {
+                        readers[net].push((i, j));
+                        count += 1;
+                    }
+                }
+                counts.push(count);
+            }
+            waiting.push(counts);
+        }
+        let mut ready: Vec<usize> = Vec::new();
+        let mut remaining: Vec<usize> = waiting.iter().map(|x| x.iter().filter(|x| **x > 0).count()).collect();
+        for (i, count) in remaining.iter().enumerate() {
+            if *count == 0 {
+                ready.push(i);
+            }
+        }
+        // a cell is ready when every output can be computed
+        let mut out = Vec::with_capacity(self.cells.len());
+        while let Some(i) = ready.pop() {
+            out.push(i);
+            for net in &self.cells[i].outputs {
+                for (cell, output) in &readers[*net] {
+                    waiting[*cell][*output] -= 1;
+                    if waiting[*cell][*output] == 0 {
+                        remaining[*cell] -= 1;
+                        if remaining[*cell] == 0 {
+                            ready.push(*cell);
+                        }
+                    }
+                }
+            }
+        }
+        if out.len() != self.cells.len() {
+            return Err(GateValidationError::CombinationalLoop);
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,232 @@
+// Optimization of a netlist
+//
+// Every pass rebuilds the netlist cell by cell in evaluation order:
+//
+// nand(x, false)         true
+// nand(x, true)          not x
+// nand(x, x)             not x
+// nand(x, not x)         true
+// not not x              x
+// dff(false)             false, dffs start at 0
+//
+// and nands with the same inputs are built once. Cells that no output and no
+// builtin chip depends on are removed afterwards. Passes repeat until the
+// netlist stops shrinking, a dff folded in one pass can fold more logic in
+// the next.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::gates::gate::GateValidationError;
+use crate::gates::netlist::{Cell, CellKind, Driver, Net, Netlist, Port, FALSE, TRUE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizeReport {
+    pub nands_before: usize,
+    pub nands_after: usize,
+    pub dffs_before: usize,
+    pub dffs_after: usize,
+    pub passes: usize
+}
+
+impl fmt::Display for OptimizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "nand {} -> {}, dff {} -> {} in {} passes",
+            self.nands_before, self.nands_after, self.dffs_before, self.dffs_after, self.passes)
+    }
+}
+
+const MAX_PASSES: usize = 16;
+
+struct Pass<'a> {
+    old: &'a Netlist,
+    cells: Vec<Cell>,
+    names: Vec<String>,
+    // nands by their inputs, the smaller first
+    nands: HashMap<(Net, Net), Net>,
+    // x for the nets that are not x
+    inverse: Vec<Option<Net>>
+}
+
+impl<'a> Pass<'a> {
+    fn net(&mut self) -> Net {
+        self.names.push(String::new());
+        self.inverse.push(None);
+        self.names.len() - 1
+    }
+
+    fn not(&mut self, x: Net, path: &str) -> Net {
+        match x {
+            FALSE => TRUE,
+            TRUE => FALSE,
+            _ => match self.inverse[x] {
+                Some(y) => y,
+                None => self.nand(x, x, path)
+            }
+        }
+    }
+
+    fn nand(&mut self, a: Net, b: Net, path: &str) -> Net {
+        if a == FALSE || b == FALSE || self.inverse[a] == Some(b) {
+            return TRUE;
+        }
+        if a == TRUE {
+            return self.not(b, path);
+        }
+        if b == TRUE {
+            return self.not(a, path);
+        }
+        if a == b {
+            if let Some(y) = self.inverse[a] {
+                return y;
+            }
+        }
+        let key = if a < b { (a, b) } else { (b, a) };
+        if let Some(x) = self.nands.get(&key) {
+            return *x;
+        }
+        let out = self.net();
+        self.nands.insert(key, out);
+        if a == b {
+            self.inverse[out] = Some(a);
+            self.inverse[a] = Some(out);
+        }
+        self.cells.push(Cell { kind: CellKind::Nand, path: path.to_string(), inputs: vec![a, b], outputs: vec![out] });
+        out
+    }
+
+    fn run(mut self) -> Result<Netlist, GateValidationError> {
+        let old = self.old;
+        let mut map: Vec<Option<Net>> = vec![None; old.net_count()];
+        map[FALSE] = Some(FALSE);
+        map[TRUE] = Some(TRUE);
+        for port in &old.inputs {
+            for net in &port.nets {
+                map[*net] = Some(self.net());
+            }
+        }
+        // state holding cells first, their inputs once everything is built
+        let mut held = Vec::new();
+        for (i, cell) in old.cells.iter().enumerate() {
+            if cell.kind == CellKind::Nand {
+                continue;
+            }
+            if cell.kind == CellKind::Dff && cell.inputs[0] == FALSE {
+                map[cell.outputs[0]] = Some(FALSE);
+                continue;
+            }
+            let outputs: Vec<Net> = cell.outputs.iter().map(|_| self.net()).collect();
+            for (old_net, net) in cell.outputs.iter().zip(&outputs) {
+                map[*old_net] = Some(*net);
+            }
+            held.push((i, self.cells.len()));
+            self.cells.push(Cell { kind: cell.kind.clone(), path: cell.path.clone(), inputs: Vec::new(), outputs });
+        }
+        for i in old.order()? {
+            let cell = &old.cells[i];
+            if cell.kind != CellKind::Nand {
+                continue;
+            }
+            let a = map[cell.inputs[0]].unwrap_or(FALSE);
+            let b = map[cell.inputs[1]].unwrap_or(FALSE);
+            map[cell.outputs[0]] = Some(self.nand(a, b, &cell.path));
+        }
+        for (old_index, index) in held {
+            self.cells[index].inputs = old.cells[old_index].inputs.iter().map(|x| map[*x].unwrap_or(FALSE)).collect();
+        }
+        for (old_net, net) in map.iter().enumerate() {
+            if let Some(net) = net {
+                if self.names[*net].is_empty() {
+                    self.names[*net] = old.net_name(old_net).to_string();
+                }
+            }
+        }
+        let outputs = old.outputs.iter().map(|port| Port {
+            name: port.name.clone(),
+            nets: port.nets.iter().map(|x| map[*x].unwrap_or(FALSE)).collect()
+        }).collect();
+        let inputs = old.inputs.iter().map(|port| Port {
+            name: port.name.clone(),
+            nets: port.nets.iter().map(|x| map[*x].unwrap()).collect()
+        }).collect();
+        Ok(sweep(Netlist::from_cells(old.name.clone(), inputs, outputs, self.cells, self.names)))
+    }
+}
+
+// Removes the cells nothing depends on and numbers the nets again
+fn sweep(netlist: Netlist) -> Netlist {
+    let mut live = vec![false; netlist.cells.len()];
+    let mut stack: Vec<Net> = netlist.outputs.iter().flat_map(|x| x.nets.iter().copied()).collect();
+    for (i, cell) in netlist.cells.iter().enumerate() {
+        if let CellKind::Builtin { .. } = cell.kind {
+            live[i] = true;
+            stack.extend(cell.inputs.iter().copied());
+        }
+    }
+    while let Some(net) = stack.pop() {
+        if let Driver::Cell(i, _) = netlist.driver(net) {
+            if !live[i] {
+                live[i] = true;
+                stack.extend(netlist.cells[i].inputs.iter().copied());
+            }
+        }
+    }
+
+    let mut map: Vec<Net> = (0..netlist.net_count()).collect();
+    let mut names = vec!["false".to_string(), "true".to_string()];
+    for port in &netlist.inputs {
+        for net in &port.nets {
+            map[*net] = names.len();
+            names.push(netlist.net_name(*net).to_string());
+        }
+    }
+    let mut cells = Vec::new();
+    for (i, cell) in netlist.cells.iter().enumerate() {
+        if !live[i] {
+            continue;
+        }
+        for net in &cell.outputs {
+            map[*net] = names.len();
+            // new inverters have no pin of the gate
+            let name = netlist.net_name(*net);
+            names.push(if name.is_empty() { format!("{}:out", cell.path) } else { name.to_string() });
+        }
+        cells.push(cell.clone());
+    }
+    for cell in cells.iter_mut() {
+        cell.inputs.iter_mut().for_each(|x| *x = map[*x]);
+        cell.outputs.iter_mut().for_each(|x| *x = map[*x]);
+    }
+    let renumber = |ports: &[Port]| -> Vec<Port> {
+        ports.iter().map(|x| Port { name: x.name.clone(), nets: x.nets.iter().map(|x| map[*x]).collect() }).collect()
+    };
+    Netlist::from_cells(netlist.name.clone(), renumber(&netlist.inputs), renumber(&netlist.outputs), cells, names)
+}
+
+fn pass(netlist: &Netlist) -> Result<Netlist, GateValidationError> {
+    let names = vec!["false".to_string(), "true".to_string()];
+    Pass { old: netlist, cells: Vec::new(), names, nands: HashMap::new(), inverse: vec![None, None] }.run()
+}
+
+// Optimized netlist with the same outputs for every input and state
+pub fn optimize(netlist: &Netlist) -> Result<(Netlist, OptimizeReport), GateValidationError> {
+    let mut out = pass(netlist)?;
+    let mut passes = 1;
+    while passes < MAX_PASSES {
+        let next = pass(&out)?;
+        passes += 1;
+        let shrunk = next.cells.len() < out.cells.len();
+        out = next;
+        if !shrunk {
+            break;
+        }
+    }
+    let report = OptimizeReport {
+        nands_before: netlist.count("nand"),
+        nands_after: out.count("nand"),
+        dffs_before: netlist.count("dff"),
+        dffs_after: out.count("dff"),
+        passes
+    };
+    Ok((out, report))
+}
@@ -0,0 +1,173 @@
+// Bit-parallel simulation of a netlist
+//
+// Every net holds a u64 whose bits are 64 independent lanes, so one pass
+// over the levelized nands evaluates 64 input vectors. The PinValues methods
+// work like those of Gate: inputs are copied to every lane and outputs are
+// read from lane 0. Only nands and dffs are supported.
+
+use crate::gates::gate::{Gate, GateValidationError};
+use crate::gates::netlist::{CellKind, Netlist, TRUE};
+use crate::gates::utils::PinValues;
+
+pub const LANES: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct ParallelSimulator {
+    netlist: Netlist,
+    // (a, b, out) nets in evaluation order
+    nands: Vec<(u32, u32, u32)>,
+    // (in, out) nets of the dffs
+    dffs: Vec<(u32, u32)>,
+    values: Vec<u64>,
+    state: Vec<u64>,
+    next: Vec<u64>
+}
+
+impl ParallelSimulator {
+    pub fn new(netlist: Netlist) -> Result<ParallelSimulator, GateValidationError> {
+        let mut nands = Vec::new();
+        let mut dffs = Vec::new();
+        for i in netlist.order()? {
+            let cell = &netlist.cells[i];
+            match &cell.kind {
+                CellKind::Nand => nands.push((cell.inputs[0] as u32, cell.inputs[1] as u32, cell.outputs[0] as u32)),
+                CellKind::Dff => dffs.push((cell.inputs[0] as u32, cell.outputs[0] as u32)),
+                CellKind::Builtin { chip, .. } => return Err(GateValidationError::UnsupportedPrimitive(chip.clone()))
+            }
+        }
+        let mut values = vec![0; netlist.net_count()];
+        values[TRUE] = !0;
+        let state = vec![0; dffs.len()];
+        let next = vec![0; dffs.len()];
+        Ok(ParallelSimulator { netlist, nands, dffs, values, state, next })
+    }
+
+    pub fn from_gate(gate: &Gate) -> Result<ParallelSimulator, GateValidationError> {
+        ParallelSimulator::new(Netlist::flatten(gate))
+    }
+
+    pub fn netlist(&self) -> &Netlist {
+        &self.netlist
+    }
+
+    pub fn is_stateful(&self) -> bool {
+        !self.dffs.is_empty()
+    }
+
+    // Lanes of a bit of an input, bit i of `lanes` goes to lane i
+    pub fn set_lanes(&mut self, name: &str, index: i64, lanes: u64) {
+        if let Some(net) = self.netlist.input(name).and_then(|x| x.nets.get(index as usize)) {
+            self.values[*net] = lanes;
+        }
+    }
+
+    // Lanes of a bit of an input or output after the last evaluation
+    pub fn lanes(&self, name: &str, index: i64) -> Option<u64> {
+        let port = self.netlist.input(name).or_else(|| self.netlist.output(name))?;
+        port.nets.get(index as usize).map(|x| self.values[*x])
+    }
+
+    // Sets an input to numbers[i] in lane i, at most 64 numbers
+    pub fn set_numbers(&mut self, name: &str, numbers: &[u64]) {
+        let nets = match self.netlist.input(name) {
+            Some(x) => x.nets.clone(),
+            None => return
+        };
+        for (bit, net) in nets.iter().enumerate() {
+            let mut lanes = 0;
+            for (lane, number) in numbers.iter().take(LANES).enumerate() {
+                lanes |= ((number >> bit) & 1) << lane;
+            }
+            self.values[*net] = lanes;
+        }
+    }
+
+    // the number on an input or output in each lane
+    pub fn numbers(&self, name: &str) -> Vec<u64> {
+        let port = match self.netlist.input(name).or_else(|| self.netlist.output(name)) {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+        let mut out = vec![0; LANES];
+        for (bit, net) in port.nets.iter().enumerate() {
+            let lanes = self.values[*net];
+            for (lane, number) in out.iter_mut().enumerate() {
+                *number |= ((lanes >> lane) & 1) << bit;
+            }
+        }
+        out
+    }
+
+    // Evaluates every lane for the current state
+    pub fn evaluate(&mut self) {
+        for (i, (_, out)) in self.dffs.iter().enumerate() {
+            self.values[*out as usize] = self.state[i];
+        }
+        let values = &mut self.values;
+        for (a, b, out) in &self.nands {
+            values[*out as usize] = !(values[*a as usize] & values[*b as usize]);
+        }
+    }
+
+    // Rising edge on the values of the last evaluation
+    pub fn latch(&mut self) {
+        for (i, (input, _)) in self.dffs.iter().enumerate() {
+            self.next[i] = self.values[*input as usize];
+        }
+    }
+
+    // Falling edge, followed by an evaluation
+    pub fn commit(&mut self) {
+        self.state.copy_from_slice(&self.next);
+        self.evaluate();
+    }
+
+    // Every dff of every lane back to 0
+    pub fn reset(&mut self) {
+        self.state.iter_mut().for_each(|x| *x = 0);
+        self.next.iter_mut().for_each(|x| *x = 0);
+    }
+
+    fn set_inputs(&mut self, inputs: &PinValues) {
+        for (key, value) in inputs.iter() {
+            self.set_lanes(&key.name, key.index, if *value { !0 } else { 0 });
+        }
+    }
+
+    // outputs of one lane
+    pub fn outputs(&self, lane: usize) -> PinValues {
+        let mut out = PinValues::new();
+        for port in &self.netlist.outputs {
+            for (i, net) in port.nets.iter().enumerate() {
+                out.set(&port.name, i as i64, (self.values[*net] >> lane) & 1 == 1);
+            }
+        }
+        out
+    }
+
+    pub fn eval(&mut self, inputs: PinValues) -> PinValues {
+        self.set_inputs(&inputs);
+        self.evaluate();
+        self.outputs(0)
+    }
+
+    pub fn run(&mut self, inputs: PinValues) -> PinValues {
+        if !self.is_stateful() {
+            return self.eval(inputs);
+        }
+        self.tick(inputs);
+        self.tock()
+    }
+
+    pub fn tick(&mut self, inputs: PinValues) -> PinValues {
+        self.set_inputs(&inputs);
+        self.evaluate();
+        self.latch();
+        self.outputs(0)
+    }
+
+    pub fn tock(&mut self) -> PinValues {
+        self.commit();
+        self.outputs(0)
+    }
+}
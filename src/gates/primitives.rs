@@ -1,9 +1,8 @@
 use crate::gates::utils::PinValues;
 use crate::gates::gate::PrimitiveGateImplementor;
-pub use super::factory::{GateFactory, GateFactoryFunction};
+use super::factory::GateFactoryFunction;
 
-#[macro_use] use crate::build_gate_function;
-#[macro_use] use crate::connect;
+use crate::build_gate_function;
 
 #[derive(Debug)]
 struct NandImplementor { }
@@ -14,15 +13,79 @@ impl PrimitiveGateImplementor for NandImplementor {
         let b = inputs.get("b", 0);
         let mut out =  PinValues::new();
         out.set("out", 0, !(a && b));
-        return out;
+        out
+    }
+
+    // a, b, out
+    fn run_values(&mut self, values: &mut [bool]) -> bool {
+        values[2] = !(values[0] && values[1]);
+        true
     }
 }
 
 pub fn gate_nand() -> GateFactoryFunction {
     build_gate_function! {
         nand(a, b => out):
-            | g: &mut Gate, f: &GateFactory | {
+            | g: &mut Gate, _f: &GateFactory | {
                g.primitive_implementor = Some(Box::new(NandImplementor {}))
             }
     }
-}
\ No newline at end of file
+}
+
+// out[t+1] = in[t]
+#[derive(Debug, Default)]
+struct DffImplementor {
+    state: bool,
+    pending: bool,
+    next: bool
+}
+
+impl PrimitiveGateImplementor for DffImplementor {
+    fn run(&mut self, inputs: PinValues) -> PinValues {
+        self.pending = inputs.get("in", 0);
+        self.state()
+    }
+
+    // in, out
+    fn run_values(&mut self, values: &mut [bool]) -> bool {
+        self.pending = values[0];
+        values[1] = self.state;
+        true
+    }
+
+    fn dependencies(&self, _output: &str) -> Option<Vec<String>> {
+        Some(Vec::new())
+    }
+
+    fn stateful(&self) -> bool {
+        true
+    }
+
+    fn state(&self) -> PinValues {
+        let mut out = PinValues::new();
+        out.set("out", 0, self.state);
+        out
+    }
+
+    fn state_values(&self, values: &mut [bool]) -> bool {
+        values[1] = self.state;
+        true
+    }
+
+    fn tick(&mut self) {
+        self.next = self.pending;
+    }
+
+    fn tock(&mut self) {
+        self.state = self.next;
+    }
+}
+
+pub fn gate_dff() -> GateFactoryFunction {
+    build_gate_function! {
+        dff(in => out):
+            | g: &mut Gate, _f: &GateFactory | {
+               g.primitive_implementor = Some(Box::new(DffImplementor::default()))
+            }
+    }
+}
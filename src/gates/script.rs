@@ -0,0 +1,737 @@
+// nand2tetris style test scripts
+//
+// load Xor.hdl,
+// output-file Xor.out,
+// compare-to Xor.cmp,
+// output-list a%B3.1.3 b%B3.1.3 out%B3.1.3;
+// set a 0, set b 1, eval, output;
+//
+// Every `output` line is written to the output file and compared with the
+// same line of the compare file. The script stops at the first mismatch.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::gates::factory::GateFactory;
+use crate::gates::gate::{Gate, PinKind};
+use crate::gates::hdl::HdlError;
+use crate::gates::utils::PinValues;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError {
+    Syntax { file: String, line: usize, column: usize, message: String },
+    Runtime { file: String, line: usize, column: usize, message: String },
+    Comparison { file: String, line: usize, expected: String, actual: String },
+    Hdl(HdlError)
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Syntax { file, line, column, message } =>
+                write!(f, "{}:{}:{}: {}", file, line, column, message),
+            // a file that can not be read or written has no position
+            ScriptError::Runtime { file, line: 0, message, .. } => write!(f, "{}: {}", file, message),
+            ScriptError::Runtime { file, line, column, message } =>
+                write!(f, "{}:{}:{}: {}", file, line, column, message),
+            ScriptError::Comparison { file, line, expected, actual } =>
+                write!(f, "{}:{}: comparison failure\nexpected: {}\nactual:   {}", file, line, expected, actual),
+            ScriptError::Hdl(x) => write!(f, "{}", x)
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Binary,
+    Decimal,
+    Hex,
+    String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputColumn {
+    pub name: String,
+    // inclusive bit range, None when the whole pin is printed
+    pub range: Option<(i64, i64)>,
+    pub format: Format,
+    pub pad_left: usize,
+    pub len: usize,
+    pub pad_right: usize
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Load(String),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputColumn>),
+    Set(String, Option<(i64, i64)>, i64),
+    Eval,
+    Tick,
+    Tock,
+    Output,
+    Echo(String),
+    Repeat(usize, Vec<Statement>),
+    While(String, Option<(i64, i64)>, Comparison, i64, Vec<Statement>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub command: Command,
+    pub line: usize,
+    pub column: usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestScript {
+    pub file: String,
+    pub statements: Vec<Statement>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Word {
+    text: String,
+    line: usize,
+    column: usize
+}
+
+fn tokenize(file: &str, source: &str) -> Result<Vec<Word>, ScriptError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    let mut i = 0;
+    let mut current: Option<Word> = None;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+        let split = c.is_whitespace() || ",;{}!".contains(c) || c == '"'
+            || (c == '/' && (next == Some('/') || next == Some('*')));
+        if split {
+            if let Some(word) = current.take() {
+                out.push(word);
+            }
+        }
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+                column += 1;
+            }
+            continue;
+        } else if c == '/' && next == Some('*') {
+            let (start_line, start_column) = (line, column);
+            i += 2;
+            column += 2;
+            loop {
+                if i + 1 >= chars.len() {
+                    return Err(ScriptError::Syntax {
+                        file: file.to_string(), line: start_line, column: start_column,
+                        message: "unterminated comment".to_string()
+                    });
+                }
+                if chars[i] == '*' && chars[i + 1] == '/' {
+                    i += 2;
+                    column += 2;
+                    break;
+                }
+                if chars[i] == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+                i += 1;
+            }
+            continue;
+        } else if c == '"' {
+            let (start_line, start_column) = (line, column);
+            let mut text = String::from("\"");
+            i += 1;
+            column += 1;
+            while i < chars.len() && chars[i] != '"' && chars[i] != '\n' {
+                text.push(chars[i]);
+                i += 1;
+                column += 1;
+            }
+            if i >= chars.len() || chars[i] != '"' {
+                return Err(ScriptError::Syntax {
+                    file: file.to_string(), line: start_line, column: start_column,
+                    message: "unterminated string".to_string()
+                });
+            }
+            out.push(Word { text, line: start_line, column: start_column });
+        } else if c.is_whitespace() {
+            if c == '\n' {
+                line += 1;
+                column = 0;
+            }
+        } else if split {
+            out.push(Word { text: c.to_string(), line, column });
+        } else {
+            match current.as_mut() {
+                Some(word) => word.text.push(c),
+                None => current = Some(Word { text: c.to_string(), line, column })
+            }
+        }
+        i += 1;
+        column += 1;
+    }
+    if let Some(word) = current.take() {
+        out.push(word);
+    }
+    Ok(out)
+}
+
+struct Parser<'a> {
+    file: &'a str,
+    words: Vec<Word>,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&str> {
+        self.words.get(self.pos).map(|w| w.text.as_str())
+    }
+
+    fn location(&self) -> (usize, usize) {
+        match self.words.get(self.pos).or_else(|| self.words.last()) {
+            Some(w) => (w.line, w.column),
+            None => (1, 1)
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, ScriptError> {
+        let (line, column) = self.location();
+        Err(ScriptError::Syntax { file: self.file.to_string(), line, column, message: message.to_string() })
+    }
+
+    // error at the word next() returned last
+    fn error_before<T>(&self, message: &str) -> Result<T, ScriptError> {
+        let word = &self.words[self.pos - 1];
+        Err(ScriptError::Syntax { file: self.file.to_string(), line: word.line, column: word.column, message: message.to_string() })
+    }
+
+    fn next(&mut self) -> Result<String, ScriptError> {
+        match self.peek() {
+            Some(x) => {
+                let x = x.to_string();
+                self.pos += 1;
+                Ok(x)
+            },
+            None => self.error("unexpected end of script")
+        }
+    }
+
+    fn is_terminator(&self) -> bool {
+        matches!(self.peek(), Some(",") | Some(";") | Some("!"))
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<Statement>, ScriptError> {
+        if self.peek() != Some("{") {
+            return self.error("expected '{'");
+        }
+        self.pos += 1;
+        let mut out = Vec::new();
+        while self.peek() != Some("}") {
+            if self.peek().is_none() {
+                return self.error("expected '}' but found end of script");
+            }
+            out.push(self.parse_statement()?);
+        }
+        self.pos += 1;
+        Ok(out)
+    }
+
+    fn parse_statements(&mut self) -> Result<Vec<Statement>, ScriptError> {
+        let mut out = Vec::new();
+        while self.peek().is_some() {
+            out.push(self.parse_statement()?);
+        }
+        Ok(out)
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, ScriptError> {
+        let (line, column) = self.location();
+        let word = self.next()?;
+        let command = match word.as_str() {
+            "load" => Command::Load(self.next()?),
+            "output-file" => Command::OutputFile(self.next()?),
+            "compare-to" => Command::CompareTo(self.next()?),
+            "output-list" => {
+                let mut columns = Vec::new();
+                while !self.is_terminator() && self.peek().is_some() {
+                    let column = self.next()?;
+                    columns.push(self.parse_column(&column)?);
+                }
+                Command::OutputList(columns)
+            },
+            "set" => {
+                let pin = self.next()?;
+                let (name, range) = self.parse_pin(&pin)?;
+                let value = self.next()?;
+                let value = self.parse_value(&value)?;
+                Command::Set(name, range, value)
+            },
+            "eval" => Command::Eval,
+            "tick" => Command::Tick,
+            "tock" => Command::Tock,
+            "output" => Command::Output,
+            "echo" => {
+                let text = self.next()?;
+                Command::Echo(text.trim_start_matches('"').to_string())
+            },
+            "repeat" => {
+                let count = match self.next()?.parse() {
+                    Ok(x) => x,
+                    Err(_) => return self.error_before("expected repeat count")
+                };
+                let block = self.parse_block()?;
+                return Ok(Statement { command: Command::Repeat(count, block), line, column });
+            },
+            "while" => {
+                let pin = self.next()?;
+                let (pin, range) = self.parse_pin(&pin)?;
+                let comparison = match self.next()?.as_str() {
+                    "=" => Comparison::Equal,
+                    "<>" => Comparison::NotEqual,
+                    "<" => Comparison::Less,
+                    "<=" => Comparison::LessEqual,
+                    ">" => Comparison::Greater,
+                    ">=" => Comparison::GreaterEqual,
+                    _ => return self.error_before("expected comparison operator")
+                };
+                let value = self.next()?;
+                let value = self.parse_value(&value)?;
+                let block = self.parse_block()?;
+                return Ok(Statement { command: Command::While(pin, range, comparison, value, block), line, column });
+            },
+            x => return Err(ScriptError::Syntax {
+                file: self.file.to_string(), line, column,
+                message: format!("unknown command {}", x)
+            })
+        };
+        if !self.is_terminator() {
+            return self.error("expected ',' or ';'");
+        }
+        self.pos += 1;
+        Ok(Statement { command, line, column })
+    }
+
+    fn parse_pin(&self, text: &str) -> Result<(String, Option<(i64, i64)>), ScriptError> {
+        let open = match text.find('[') {
+            Some(x) => x,
+            None => return Ok((text.to_string(), None))
+        };
+        if !text.ends_with(']') {
+            return self.error_before(&format!("invalid pin {}", text));
+        }
+        let inner = &text[open + 1..text.len() - 1];
+        let parts: Vec<&str> = inner.split("..").collect();
+        let numbers: Result<Vec<i64>, _> = parts.iter().map(|x| x.parse::<i64>()).collect();
+        match numbers.as_deref() {
+            Ok([x]) if *x >= 0 => Ok((text[..open].to_string(), Some((*x, *x)))),
+            Ok([x, y]) if 0 <= *x && x <= y => Ok((text[..open].to_string(), Some((*x, *y)))),
+            _ => self.error_before(&format!("invalid pin {}", text))
+        }
+    }
+
+    fn parse_value(&self, text: &str) -> Result<i64, ScriptError> {
+        let (radix, digits) = match text.get(..2) {
+            Some("%B") => (2, &text[2..]),
+            Some("%X") => (16, &text[2..]),
+            Some("%D") => (10, &text[2..]),
+            _ => (10, text)
+        };
+        match i64::from_str_radix(digits, radix) {
+            Ok(x) => Ok(x),
+            Err(_) => self.error_before(&format!("invalid value {}", text))
+        }
+    }
+
+    fn parse_column(&self, text: &str) -> Result<OutputColumn, ScriptError> {
+        let (pin, format) = match text.find('%') {
+            Some(x) => (&text[..x], Some(&text[x + 1..])),
+            None => (text, None)
+        };
+        let (name, range) = self.parse_pin(pin)?;
+        let mut column = OutputColumn { name, range, format: Format::Binary, pad_left: 1, len: 0, pad_right: 1 };
+        if let Some(format) = format {
+            column.format = match format.chars().next() {
+                Some('B') => Format::Binary,
+                Some('D') => Format::Decimal,
+                Some('X') => Format::Hex,
+                Some('S') => Format::String,
+                _ => return self.error_before(&format!("invalid output format {}", text))
+            };
+            let sizes: Result<Vec<usize>, _> = format[1..].split('.').map(|x| x.parse::<usize>()).collect();
+            match sizes.as_deref() {
+                Ok([l, m, r]) => {
+                    column.pad_left = *l;
+                    column.len = *m;
+                    column.pad_right = *r;
+                },
+                _ => return self.error_before(&format!("invalid output format {}", text))
+            }
+        }
+        Ok(column)
+    }
+}
+
+pub fn parse_script(file: &str, source: &str) -> Result<TestScript, ScriptError> {
+    let words = tokenize(file, source)?;
+    let mut parser = Parser { file, words, pos: 0 };
+    Ok(TestScript { file: file.to_string(), statements: parser.parse_statements()? })
+}
+
+pub(crate) fn format_value(value: u64, width: i64, format: Format, len: usize) -> String {
+    match format {
+        Format::Binary => {
+            let out: String = (0..width).rev().map(|i| if (value >> i) & 1 == 1 { '1' } else { '0' }).collect();
+            if out.len() > len {
+                out[out.len() - len..].to_string()
+            } else {
+                format!("{:0>1$}", out, len)
+            }
+        },
+        Format::Hex => {
+            let out = format!("{:X}", value);
+            format!("{:0>1$}", out, len)
+        },
+        // 16 bit values are words of the hack computer and therefore signed
+        Format::Decimal if width >= 16 => {
+            let shift = 64 - width;
+            format!("{:>1$}", ((value << shift) as i64) >> shift, len)
+        },
+        Format::Decimal => format!("{:>1$}", value, len),
+        Format::String => format!("{:<1$}", value, len)
+    }
+}
+
+pub(crate) fn center(text: &str, width: usize) -> String {
+    if text.len() >= width {
+        return text[..width].to_string();
+    }
+    let left = (width - text.len()) / 2;
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(width - text.len() - left))
+}
+
+pub struct ScriptRunner<'a> {
+    factory: &'a mut GateFactory,
+    dir: PathBuf,
+    gate: Option<Gate>,
+    inputs: PinValues,
+    outputs: PinValues,
+    time: usize,
+    ticked: bool,
+    columns: Vec<OutputColumn>,
+    output_file: Option<PathBuf>,
+    compare: Option<(String, Vec<String>)>,
+    lines: Vec<String>,
+    echoes: Vec<String>
+}
+
+impl<'a> ScriptRunner<'a> {
+    // files named in the script are resolved relative to `dir`
+    pub fn new<P: AsRef<Path>>(factory: &'a mut GateFactory, dir: P) -> ScriptRunner<'a> {
+        ScriptRunner {
+            factory,
+            dir: dir.as_ref().to_path_buf(),
+            gate: None,
+            inputs: PinValues::new(),
+            outputs: PinValues::new(),
+            time: 0,
+            ticked: false,
+            columns: Vec::new(),
+            output_file: None,
+            compare: None,
+            lines: Vec::new(),
+            echoes: Vec::new()
+        }
+    }
+
+    pub fn gate(&self) -> Option<&Gate> {
+        self.gate.as_ref()
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    // texts of the echo commands run so far, for the caller to show
+    pub fn echoes(&self) -> &[String] {
+        &self.echoes
+    }
+
+    // Runs the script and returns the produced output lines.
+    pub fn run(&mut self, script: &TestScript) -> Result<Vec<String>, ScriptError> {
+        let res = self.execute(script, &script.statements);
+        self.flush(script)?;
+        res.map(|_| self.lines.clone())
+    }
+
+    fn flush(&self, script: &TestScript) -> Result<(), ScriptError> {
+        if let Some(path) = &self.output_file {
+            let mut text = self.lines.join("\n");
+            text.push('\n');
+            if let Err(e) = fs::write(path, text) {
+                return Err(ScriptError::Runtime {
+                    file: script.file.clone(), line: 0, column: 0,
+                    message: format!("{}: {}", path.display(), e)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, script: &TestScript, statements: &[Statement]) -> Result<(), ScriptError> {
+        for statement in statements {
+            self.execute_statement(script, statement)?;
+        }
+        Ok(())
+    }
+
+    fn execute_statement(&mut self, script: &TestScript, statement: &Statement) -> Result<(), ScriptError> {
+        let error = |message: String| ScriptError::Runtime {
+            file: script.file.clone(), line: statement.line, column: statement.column, message
+        };
+        match &statement.command {
+            Command::Load(file) => {
+                let path = self.dir.join(file);
+                // chips next to the script are used before the registered ones
+                let name = if path.exists() {
+                    self.factory.load_hdl_with_dir(&path).map_err(ScriptError::Hdl)?
+                } else {
+                    let stem = Path::new(file).file_stem().map(|x| x.to_string_lossy().to_lowercase());
+                    match stem {
+                        Some(x) if self.factory.contains(&x) => x,
+                        _ => return Err(error(format!("can not find {}", file)))
+                    }
+                };
+                let gate = self.factory.try_build(&name).map_err(error)?;
+                self.inputs = PinValues::new();
+                self.outputs = PinValues::new();
+                for pin in gate.pins() {
+                    match pin.kind {
+                        PinKind::Input => self.inputs.set(&pin.name, pin.index, false),
+                        PinKind::Output => self.outputs.set(&pin.name, pin.index, false),
+                        PinKind::Internal => {}
+                    }
+                }
+                self.gate = Some(gate);
+                self.time = 0;
+                self.ticked = false;
+            },
+            Command::OutputFile(file) => {
+                self.output_file = Some(self.dir.join(file));
+            },
+            Command::CompareTo(file) => {
+                let path = self.dir.join(file);
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| error(format!("{}: {}", path.display(), e)))?;
+                let lines = text.lines().map(|x| x.to_string()).collect();
+                self.compare = Some((path.display().to_string(), lines));
+            },
+            Command::OutputList(columns) => {
+                for column in columns {
+                    if column.name != "time" {
+                        self.pin_range(&column.name, column.range).map_err(error)?;
+                    }
+                }
+                self.columns = columns.iter().map(|x| {
+                    let mut x = x.clone();
+                    if x.len == 0 {
+                        x.len = match x.range {
+                            Some((start, end)) => (end - start + 1) as usize,
+                            None => self.pin_size(&x.name).unwrap_or(1) as usize
+                        };
+                    }
+                    x
+                }).collect();
+                let header = self.columns.iter()
+                    .map(|x| center(&x.name, x.pad_left + x.len + x.pad_right))
+                    .collect::<Vec<String>>();
+                self.write_line(format!("|{}|", header.join("|")))?;
+            },
+            Command::Set(name, range, value) => {
+                let (start, end) = self.pin_range(name, *range).map_err(error)?;
+                let gate = self.gate.as_ref().unwrap();
+                if gate.get_pin(name, 0).unwrap().kind != PinKind::Input {
+                    return Err(error(format!("{} is not an input pin", name)));
+                }
+                let width = end - start + 1;
+                let min = if width > 1 { -(1 << (width - 1)) } else { 0 };
+                if width < 64 && (*value >= 1 << width || *value < min) {
+                    return Err(error(format!("value {} does not fit in {} bits", value, width)));
+                }
+                for i in 0..width {
+                    self.inputs.set(name, start + i, (value >> i) & 1 == 1);
+                }
+            },
+            Command::Eval => {
+                let inputs = self.inputs.clone();
+                self.outputs = self.loaded(&error)?.eval(inputs);
+            },
+            Command::Tick => {
+                let inputs = self.inputs.clone();
+                self.outputs = self.loaded(&error)?.tick(inputs);
+                self.ticked = true;
+            },
+            Command::Tock => {
+                self.outputs = self.loaded(&error)?.tock();
+                self.time += 1;
+                self.ticked = false;
+            },
+            Command::Output => {
+                self.loaded(&error)?;
+                let values = self.columns.iter().map(|x| self.format_column(x)).collect::<Result<Vec<String>, String>>().map_err(error)?;
+                self.write_line(format!("|{}|", values.join("|")))?;
+            },
+            Command::Echo(text) => {
+                self.echoes.push(text.clone());
+            },
+            Command::Repeat(count, block) => {
+                for _ in 0..*count {
+                    self.execute(script, block)?;
+                }
+            },
+            Command::While(name, range, comparison, value, block) => {
+                loop {
+                    let current = self.read_pin(name, *range).map_err(error)?;
+                    let go = match comparison {
+                        Comparison::Equal => current == *value,
+                        Comparison::NotEqual => current != *value,
+                        Comparison::Less => current < *value,
+                        Comparison::LessEqual => current <= *value,
+                        Comparison::Greater => current > *value,
+                        Comparison::GreaterEqual => current >= *value
+                    };
+                    if !go {
+                        break;
+                    }
+                    self.execute(script, block)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn loaded<F: Fn(String) -> ScriptError>(&mut self, error: &F) -> Result<&mut Gate, ScriptError> {
+        match self.gate.as_mut() {
+            Some(x) => Ok(x),
+            None => Err(error("no chip is loaded".to_string()))
+        }
+    }
+
+    fn pin_size(&self, name: &str) -> Result<i64, String> {
+        let gate = match &self.gate {
+            Some(x) => x,
+            None => return Err("no chip is loaded".to_string())
+        };
+        match gate.get_pin(name, 0) {
+            Some(x) if x.kind != PinKind::Internal => Ok(x.size),
+            _ => Err(format!("chip {} has no pin named {}", gate.name, name))
+        }
+    }
+
+    // Inclusive bit range of a pin, the whole pin when `range` is None
+    fn pin_range(&self, name: &str, range: Option<(i64, i64)>) -> Result<(i64, i64), String> {
+        let size = self.pin_size(name)?;
+        match range {
+            None => Ok((0, size - 1)),
+            Some((start, end)) if 0 <= start && start <= end && end < size => Ok((start, end)),
+            Some((start, end)) => Err(format!("bit range {}..{} is out of bounds of {}[{}]", start, end, name, size))
+        }
+    }
+
+    fn values_of(&self, name: &str) -> &PinValues {
+        if self.inputs.contains(name, 0) {
+            &self.inputs
+        } else {
+            &self.outputs
+        }
+    }
+
+    // 16 bit values are read as signed words
+    fn read_pin(&self, name: &str, range: Option<(i64, i64)>) -> Result<i64, String> {
+        if name == "time" {
+            return Ok(self.time as i64);
+        }
+        let (start, end) = self.pin_range(name, range)?;
+        let values = self.values_of(name);
+        let mut out: i64 = 0;
+        for i in start..=end {
+            if values.get(name, i) {
+                out |= 1 << (i - start);
+            }
+        }
+        let width = end - start + 1;
+        if (16..64).contains(&width) {
+            let shift = 64 - width;
+            out = (out << shift) >> shift;
+        }
+        Ok(out)
+    }
+
+    fn format_column(&self, column: &OutputColumn) -> Result<String, String> {
+        let text = if column.name == "time" {
+            let time = format!("{}{}", self.time, if self.ticked { "+" } else { "" });
+            format!("{:<1$}", time, column.len)
+        } else {
+            let (start, end) = self.pin_range(&column.name, column.range)?;
+            let width = end - start + 1;
+            let value = self.read_pin(&column.name, column.range)? as u64 & (u64::MAX >> (64 - width));
+            format_value(value, width, column.format, column.len)
+        };
+        Ok(format!("{}{}{}", " ".repeat(column.pad_left), text, " ".repeat(column.pad_right)))
+    }
+
+    fn write_line(&mut self, line: String) -> Result<(), ScriptError> {
+        self.lines.push(line);
+        let line = self.lines.last().unwrap();
+        if let Some((file, expected)) = &self.compare {
+            let index = self.lines.len() - 1;
+            let matches = match expected.get(index) {
+                Some(x) => x.trim_end() == line.trim_end(),
+                None => false
+            };
+            if !matches {
+                return Err(ScriptError::Comparison {
+                    file: file.clone(),
+                    line: index + 1,
+                    expected: expected.get(index).cloned().unwrap_or_default(),
+                    actual: line.clone()
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn load_script<P: AsRef<Path>>(path: P) -> Result<TestScript, ScriptError> {
+    let path = path.as_ref();
+    let file = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|e| ScriptError::Runtime {
+        file: file.clone(), line: 0, column: 0, message: e.to_string()
+    })?;
+    parse_script(&file, &source)
+}
+
+pub fn run_script_file<P: AsRef<Path>>(factory: &mut GateFactory, path: P) -> Result<Vec<String>, ScriptError> {
+    let path = path.as_ref();
+    let script = load_script(path)?;
+    let dir = path.parent().map(|x| x.to_path_buf()).unwrap_or_default();
+    ScriptRunner::new(factory, dir).run(&script)
+}
@@ -0,0 +1,141 @@
+// Cost and speed of a chip
+//
+// The cost is the number of primitives of the flat netlist and the number of
+// parts of each chip in the hierarchy. The speed is the depth of the
+// combinational logic in nand levels: the longest path from an input of the
+// chip or the output of a dff or builtin memory to an output of the chip or
+// the input of a dff or builtin memory. Builtin chips add no levels.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::gates::gate::{Gate, GateValidationError};
+use crate::gates::netlist::{CellKind, Net, Netlist};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChipStats {
+    pub chip: String,
+    // cells of the netlist by kind: nand, dff and the builtin chips
+    pub primitives: BTreeMap<String, usize>,
+    // parts of every chip at every level of the hierarchy
+    pub parts: BTreeMap<String, usize>,
+    pub depth: usize,
+    // net names of a longest path, its start first
+    pub critical_path: Vec<String>
+}
+
+fn count_parts(gate: &Gate, out: &mut BTreeMap<String, usize>) {
+    for part in &gate.gates {
+        *out.entry(part.name.clone()).or_insert(0) += 1;
+        count_parts(part, out);
+    }
+}
+
+fn kind_name(kind: &CellKind) -> &str {
+    match kind {
+        CellKind::Nand => "nand",
+        CellKind::Dff => "dff",
+        CellKind::Builtin { chip, .. } => chip
+    }
+}
+
+impl ChipStats {
+    pub fn new(gate: &Gate) -> Result<ChipStats, GateValidationError> {
+        let netlist = Netlist::flatten(gate);
+        let mut parts = BTreeMap::new();
+        count_parts(gate, &mut parts);
+        let mut primitives = BTreeMap::new();
+        for cell in &netlist.cells {
+            *primitives.entry(kind_name(&cell.kind).to_string()).or_insert(0) += 1;
+        }
+
+        // levels of the nets and the input of the cell each one came from,
+        // the constants start no path
+        let mut levels: Vec<Option<usize>> = vec![None; netlist.net_count()];
+        let mut from: Vec<Option<Net>> = vec![None; netlist.net_count()];
+        for port in &netlist.inputs {
+            for net in &port.nets {
+                levels[*net] = Some(0);
+            }
+        }
+        for i in netlist.order()? {
+            let cell = &netlist.cells[i];
+            let cost = if cell.kind == CellKind::Nand { 1 } else { 0 };
+            for (j, out) in cell.outputs.iter().enumerate() {
+                let mut best: Option<(usize, Net)> = None;
+                for k in cell.dependencies(j) {
+                    let net = cell.inputs[k];
+                    if let Some(level) = levels[net] {
+                        if best.is_none_or(|(x, _)| level > x) {
+                            best = Some((level, net));
+                        }
+                    }
+                }
+                levels[*out] = Some(best.map_or(0, |(x, _)| x) + cost);
+                from[*out] = best.map(|(_, x)| x);
+            }
+        }
+
+        let mut ends: Vec<Net> = netlist.outputs.iter().flat_map(|x| x.nets.iter().copied()).collect();
+        for cell in netlist.cells.iter().filter(|x| x.is_stateful()) {
+            ends.extend(cell.inputs.iter().copied());
+        }
+        let mut end: Option<(usize, Net)> = None;
+        for net in ends {
+            if let Some(level) = levels[net] {
+                if end.is_none_or(|(x, _)| level > x) {
+                    end = Some((level, net));
+                }
+            }
+        }
+        let mut critical_path = Vec::new();
+        let mut next = end.map(|(_, x)| x);
+        while let Some(net) = next {
+            critical_path.push(netlist.net_name(net).to_string());
+            next = from[net];
+        }
+        critical_path.reverse();
+
+        Ok(ChipStats {
+            chip: gate.name.clone(),
+            primitives,
+            parts,
+            depth: end.map_or(0, |(x, _)| x),
+            critical_path
+        })
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.primitives.values().sum()
+    }
+}
+
+// chip alu
+// primitives 741
+//   nand 741
+// parts 1257
+//   add16 1
+//   ...
+// depth 82
+// critical path
+//   alu:zx
+//   ...
+impl fmt::Display for ChipStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "chip {}", self.chip)?;
+        writeln!(f, "primitives {}", self.primitive_count())?;
+        for (name, count) in &self.primitives {
+            writeln!(f, "  {} {}", name, count)?;
+        }
+        writeln!(f, "parts {}", self.parts.values().sum::<usize>())?;
+        for (name, count) in &self.parts {
+            writeln!(f, "  {} {}", name, count)?;
+        }
+        writeln!(f, "depth {}", self.depth)?;
+        writeln!(f, "critical path")?;
+        for name in &self.critical_path {
+            writeln!(f, "  {}", name)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+// Event-driven timing simulation of a netlist
+//
+// Unlike the gate simulator every cell takes time: a nand changes its output
+// `delay` after one of its inputs changed, a dff `delay` after the clock
+// edge. Delays are transport delays, so a pulse shorter than a delay still
+// goes through and the hazards of a chip show up as outputs changing more
+// than once before they settle. Only nands and dffs are supported.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+use crate::gates::gate::{Gate, GateValidationError};
+use crate::gates::netlist::{CellKind, Net, Netlist, TRUE};
+use crate::gates::utils::PinValues;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delays {
+    pub nand: u64,
+    // from the clock edge to the output
+    pub dff: u64,
+    // time the input of a dff has to be stable before the clock edge
+    pub setup: u64
+}
+
+impl Default for Delays {
+    fn default() -> Delays {
+        Delays { nand: 1, dff: 1, setup: 0 }
+    }
+}
+
+// An output bit that changed more than once in a step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glitch {
+    pub pin: String,
+    pub index: usize,
+    // (time from the start of the step, new value) of every change
+    pub changes: Vec<(u64, bool)>
+}
+
+impl Glitch {
+    // the output went back to its value, like 1 -> 0 -> 1
+    pub fn is_static(&self) -> bool {
+        self.changes.len().is_multiple_of(2)
+    }
+}
+
+// out[0]: 0 at 2, 1 at 4 (static)
+impl fmt::Display for Glitch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let changes: Vec<String> = self.changes.iter().map(|(time, value)| format!("{} at {}", *value as u8, time)).collect();
+        write!(f, "{}[{}]: {} ({})", self.pin, self.index, changes.join(", "), if self.is_static() { "static" } else { "dynamic" })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingReport {
+    // time the step started at
+    pub start: u64,
+    // from the start to the last change of an output, 0 if none changed
+    pub settling_time: u64,
+    // from the start to the last change of any net
+    pub quiet_time: u64,
+    pub events: usize,
+    pub glitches: Vec<Glitch>
+}
+
+#[derive(Debug, Clone)]
+pub struct TimingSimulator {
+    netlist: Netlist,
+    delays: Vec<u64>,
+    setup: u64,
+    // nand cells reading each net
+    readers: Vec<Vec<usize>>,
+    dffs: Vec<usize>,
+    values: Vec<bool>,
+    // value of each net once the events scheduled so far happened
+    projected: Vec<bool>,
+    // (time, order of scheduling, net, value)
+    events: BinaryHeap<Reverse<(u64, usize, Net, bool)>>,
+    scheduled: usize,
+    time: u64
+}
+
+impl TimingSimulator {
+    pub fn new(netlist: Netlist, delays: Delays) -> Result<TimingSimulator, GateValidationError> {
+        let order = netlist.order()?;
+        let mut readers = vec![Vec::new(); netlist.net_count()];
+        let mut dffs = Vec::new();
+        let mut cell_delays = Vec::new();
+        for (i, cell) in netlist.cells.iter().enumerate() {
+            match &cell.kind {
+                CellKind::Nand => {
+                    readers[cell.inputs[0]].push(i);
+                    if cell.inputs[1] != cell.inputs[0] {
+                        readers[cell.inputs[1]].push(i);
+                    }
+                    cell_delays.push(delays.nand);
+                },
+                CellKind::Dff => {
+                    dffs.push(i);
+                    cell_delays.push(delays.dff);
+                },
+                CellKind::Builtin { chip, .. } => return Err(GateValidationError::UnsupportedPrimitive(chip.clone()))
+            }
+        }
+        // settled with every input false and every dff 0
+        let mut values = vec![false; netlist.net_count()];
+        values[TRUE] = true;
+        for i in order {
+            let cell = &netlist.cells[i];
+            if cell.kind == CellKind::Nand {
+                values[cell.outputs[0]] = !(values[cell.inputs[0]] && values[cell.inputs[1]]);
+            }
+        }
+        Ok(TimingSimulator {
+            netlist,
+            delays: cell_delays,
+            setup: delays.setup,
+            readers,
+            dffs,
+            projected: values.clone(),
+            values,
+            events: BinaryHeap::new(),
+            scheduled: 0,
+            time: 0
+        })
+    }
+
+    pub fn from_gate(gate: &Gate, delays: Delays) -> Result<TimingSimulator, GateValidationError> {
+        TimingSimulator::new(Netlist::flatten(gate), delays)
+    }
+
+    pub fn netlist(&self) -> &Netlist {
+        &self.netlist
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    // delay of a cell of the netlist
+    pub fn delay(&self, cell: usize) -> u64 {
+        self.delays[cell]
+    }
+
+    pub fn set_delay(&mut self, cell: usize, delay: u64) {
+        self.delays[cell] = delay;
+    }
+
+    fn schedule(&mut self, time: u64, net: Net, value: bool) {
+        if self.projected[net] != value {
+            self.projected[net] = value;
+            self.events.push(Reverse((time, self.scheduled, net, value)));
+            self.scheduled += 1;
+        }
+    }
+
+    // Changes an input bit at the current time, it takes effect in the next
+    // step
+    pub fn set(&mut self, name: &str, index: i64, value: bool) {
+        if let Some(net) = self.netlist.input(name).and_then(|x| x.nets.get(index as usize)).copied() {
+            self.schedule(self.time, net, value);
+        }
+    }
+
+    pub fn set_number(&mut self, name: &str, number: u64) {
+        let size = self.netlist.input(name).map_or(0, |x| x.nets.len());
+        for i in 0..size.min(64) {
+            self.set(name, i as i64, (number >> i) & 1 == 1);
+        }
+    }
+
+    pub fn set_inputs(&mut self, inputs: &PinValues) {
+        for (key, value) in inputs.iter() {
+            self.set(&key.name, key.index, *value);
+        }
+    }
+
+    // A bit of an input or output at the current time
+    pub fn get(&self, name: &str, index: i64) -> Option<bool> {
+        let port = self.netlist.input(name).or_else(|| self.netlist.output(name))?;
+        port.nets.get(index as usize).map(|x| self.values[*x])
+    }
+
+    pub fn number(&self, name: &str) -> u64 {
+        let port = match self.netlist.input(name).or_else(|| self.netlist.output(name)) {
+            Some(x) => x,
+            None => return 0
+        };
+        port.nets.iter().take(64).enumerate().fold(0, |out, (i, net)| out | (self.values[*net] as u64) << i)
+    }
+
+    pub fn outputs(&self) -> PinValues {
+        let mut out = PinValues::new();
+        for port in &self.netlist.outputs {
+            for (i, net) in port.nets.iter().enumerate() {
+                out.set(&port.name, i as i64, self.values[*net]);
+            }
+        }
+        out
+    }
+
+    // Runs the events until nothing changes anymore
+    pub fn settle(&mut self) -> TimingReport {
+        let start = self.time;
+        // changes of the output bits in the order of the ports
+        let mut watched: Vec<Vec<usize>> = vec![Vec::new(); self.netlist.net_count()];
+        let mut bits = Vec::new();
+        for port in &self.netlist.outputs {
+            for (i, net) in port.nets.iter().enumerate() {
+                watched[*net].push(bits.len());
+                bits.push((port.name.clone(), i));
+            }
+        }
+        let mut changes: Vec<Vec<(u64, bool)>> = vec![Vec::new(); bits.len()];
+        let mut report = TimingReport { start, settling_time: 0, quiet_time: 0, events: 0, glitches: Vec::new() };
+
+        let mut dirty = Vec::new();
+        let mut seen = vec![false; self.netlist.cells.len()];
+        while let Some(Reverse((time, _, _, _))) = self.events.peek().copied() {
+            // every event of the same time before the cells react
+            while let Some(Reverse((next, _, net, value))) = self.events.peek().copied() {
+                if next != time {
+                    break;
+                }
+                self.events.pop();
+                report.events += 1;
+                if self.values[net] == value {
+                    continue;
+                }
+                self.values[net] = value;
+                report.quiet_time = time - start;
+                for bit in &watched[net] {
+                    changes[*bit].push((time - start, value));
+                    report.settling_time = time - start;
+                }
+                for cell in &self.readers[net] {
+                    if !seen[*cell] {
+                        seen[*cell] = true;
+                        dirty.push(*cell);
+                    }
+                }
+            }
+            for i in dirty.drain(..) {
+                seen[i] = false;
+                let cell = &self.netlist.cells[i];
+                let (a, b, out) = (cell.inputs[0], cell.inputs[1], cell.outputs[0]);
+                let value = !(self.values[a] && self.values[b]);
+                self.schedule(time + self.delays[i], out, value);
+            }
+            self.time = time;
+        }
+
+        for ((pin, index), changes) in bits.into_iter().zip(changes) {
+            if changes.len() > 1 {
+                report.glitches.push(Glitch { pin, index, changes });
+            }
+        }
+        report
+    }
+
+    // Rising edge of the clock at the current time: every dff takes its input
+    // and the netlist settles again
+    pub fn clock(&mut self) -> TimingReport {
+        for k in 0..self.dffs.len() {
+            let cell = &self.netlist.cells[self.dffs[k]];
+            let (input, out) = (cell.inputs[0], cell.outputs[0]);
+            let value = self.values[input];
+            self.schedule(self.time + self.delays[self.dffs[k]], out, value);
+        }
+        self.settle()
+    }
+
+    // Latest time each net can change after the inputs change and the clock
+    // rises together
+    fn arrival(&self) -> Vec<u64> {
+        let mut out = vec![0; self.netlist.net_count()];
+        // the order is known to exist from new
+        for i in self.netlist.order().unwrap() {
+            let cell = &self.netlist.cells[i];
+            out[cell.outputs[0]] = match cell.kind {
+                CellKind::Nand => out[cell.inputs[0]].max(out[cell.inputs[1]]) + self.delays[i],
+                _ => self.delays[i]
+            };
+        }
+        out
+    }
+
+    // Worst case time for the outputs to settle after the inputs change
+    pub fn max_delay(&self) -> u64 {
+        let arrival = self.arrival();
+        self.netlist.outputs.iter().flat_map(|x| x.nets.iter()).map(|x| arrival[*x]).max().unwrap_or(0)
+    }
+
+    // Shortest clock period for which the inputs of every dff settle before
+    // the next edge and the outputs settle within a cycle, assuming the inputs
+    // of the chip change with the clock. None for a combinational chip.
+    pub fn min_clock_period(&self) -> Option<u64> {
+        if self.dffs.is_empty() {
+            return None;
+        }
+        let arrival = self.arrival();
+        let dffs = self.dffs.iter().map(|x| arrival[self.netlist.cells[*x].inputs[0]] + self.setup).max().unwrap_or(0);
+        Some(dffs.max(self.max_delay()))
+    }
+}
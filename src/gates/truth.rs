@@ -0,0 +1,285 @@
+// Exhaustive truth tables of combinational gates
+//
+// Row r assigns the inputs in declaration order with the first input in the
+// most significant bits of r, like the compare files of nand2tetris: a=0 b=0,
+// a=0 b=1, a=1 b=0, a=1 b=1. Within a bus bit 0 is the least significant.
+// Gates made of nands are evaluated 64 rows at a time with the parallel
+// simulator, the others one row at a time.
+
+use std::fmt;
+
+use crate::gates::gate::{Gate, PinKind};
+use crate::gates::parallel::{ParallelSimulator, LANES};
+use crate::gates::script::{center, format_value, Format};
+use crate::gates::utils::PinValues;
+
+// 16M rows
+pub const MAX_TRUTH_TABLE_INPUTS: i64 = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthTableError {
+    pub chip: String,
+    pub message: String
+}
+
+impl fmt::Display for TruthTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.chip, self.message)
+    }
+}
+
+impl std::error::Error for TruthTableError {}
+
+// (name, size) of the pins of a kind in declaration order
+fn pins(gate: &Gate, kind: PinKind) -> Vec<(String, i64)> {
+    let mut out: Vec<(String, i64)> = Vec::new();
+    for name in gate.pin_names() {
+        if out.iter().any(|(x, _)| x == name) {
+            continue;
+        }
+        if let Some(pin) = gate.get_pin(name, 0) {
+            if pin.kind == kind {
+                out.push((name.clone(), pin.size));
+            }
+        }
+    }
+    out
+}
+
+fn width(pins: &[(String, i64)]) -> i64 {
+    pins.iter().map(|(_, size)| size).sum()
+}
+
+// Splits a packed number into the numbers of the pins, the first pin in the
+// most significant bits
+fn unpack(pins: &[(String, i64)], mut packed: u64) -> Vec<u64> {
+    let mut out = vec![0; pins.len()];
+    for (i, (_, size)) in pins.iter().enumerate().rev() {
+        out[i] = packed & (!0 >> (64 - size));
+        packed = packed.checked_shr(*size as u32).unwrap_or(0);
+    }
+    out
+}
+
+fn pack(pins: &[(String, i64)], numbers: impl Iterator<Item = u64>) -> u64 {
+    let mut out = 0u64;
+    for ((_, size), number) in pins.iter().zip(numbers) {
+        out = out.checked_shl(*size as u32).unwrap_or(0) | number;
+    }
+    out
+}
+
+fn to_values(pins: &[(String, i64)], packed: u64) -> PinValues {
+    let mut out = PinValues::new();
+    for ((name, size), number) in pins.iter().zip(unpack(pins, packed)) {
+        out.set_number(name, *size, number);
+    }
+    out
+}
+
+fn from_values(pins: &[(String, i64)], values: &PinValues) -> u64 {
+    pack(pins, pins.iter().map(|(name, size)| values.get_number(name, *size)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthTable {
+    chip: String,
+    inputs: Vec<(String, i64)>,
+    outputs: Vec<(String, i64)>,
+    // packed outputs of each row, the first output in the most significant bits
+    rows: Vec<u64>
+}
+
+impl TruthTable {
+    pub fn new(gate: &mut Gate) -> Result<TruthTable, TruthTableError> {
+        let error = |message: String| TruthTableError { chip: gate.name.clone(), message };
+        if gate.is_stateful() {
+            return Err(error("is not combinational".to_string()));
+        }
+        let inputs = pins(gate, PinKind::Input);
+        let outputs = pins(gate, PinKind::Output);
+        if width(&inputs) > MAX_TRUTH_TABLE_INPUTS {
+            return Err(error(format!("has {} input bits, at most {} are supported", width(&inputs), MAX_TRUTH_TABLE_INPUTS)));
+        }
+        if width(&outputs) > 63 {
+            return Err(error(format!("has {} output bits, at most 63 are supported", width(&outputs))));
+        }
+
+        let count = 1u64 << width(&inputs);
+        let mut rows = Vec::with_capacity(count as usize);
+        match ParallelSimulator::from_gate(gate) {
+            Ok(mut simulator) => {
+                for start in (0..count).step_by(LANES) {
+                    let end = (start + LANES as u64).min(count);
+                    let numbers: Vec<Vec<u64>> = (start..end).map(|x| unpack(&inputs, x)).collect();
+                    for (i, (name, _)) in inputs.iter().enumerate() {
+                        let lanes: Vec<u64> = numbers.iter().map(|x| x[i]).collect();
+                        simulator.set_numbers(name, &lanes);
+                    }
+                    simulator.evaluate();
+                    let results: Vec<Vec<u64>> = outputs.iter().map(|(name, _)| simulator.numbers(name)).collect();
+                    for lane in 0..(end - start) as usize {
+                        rows.push(pack(&outputs, results.iter().map(|x| x[lane])));
+                    }
+                }
+            },
+            Err(_) => {
+                for row in 0..count {
+                    let out = gate.eval(to_values(&inputs, row));
+                    rows.push(from_values(&outputs, &out));
+                }
+            }
+        }
+        Ok(TruthTable { chip: gate.name.clone(), inputs, outputs, rows })
+    }
+
+    pub fn chip(&self) -> &str {
+        &self.chip
+    }
+
+    // (name, size) of the inputs in declaration order
+    pub fn inputs(&self) -> &[(String, i64)] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[(String, i64)] {
+        &self.outputs
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // inputs of a row
+    pub fn assignment(&self, row: usize) -> PinValues {
+        to_values(&self.inputs, row as u64)
+    }
+
+    // outputs of a row
+    pub fn row(&self, row: usize) -> PinValues {
+        to_values(&self.outputs, self.rows[row])
+    }
+
+    // output numbers of a row in declaration order
+    pub fn row_numbers(&self, row: usize) -> Vec<u64> {
+        unpack(&self.outputs, self.rows[row])
+    }
+
+    // index of the row of an assignment
+    pub fn find(&self, inputs: &PinValues) -> usize {
+        from_values(&self.inputs, inputs) as usize
+    }
+
+    // First row where the outputs differ, the pins must have the same names
+    // and sizes
+    pub fn compare(&self, other: &TruthTable) -> Result<Option<Counterexample>, TruthTableError> {
+        let error = |message: String| TruthTableError { chip: self.chip.clone(), message };
+        let mut inputs = self.inputs.clone();
+        let mut other_inputs = other.inputs.clone();
+        inputs.sort();
+        other_inputs.sort();
+        if inputs != other_inputs {
+            return Err(error(format!("inputs differ from those of {}", other.chip)));
+        }
+        let mut outputs = self.outputs.clone();
+        let mut other_outputs = other.outputs.clone();
+        outputs.sort();
+        other_outputs.sort();
+        if outputs != other_outputs {
+            return Err(error(format!("outputs differ from those of {}", other.chip)));
+        }
+        let same_order = self.inputs == other.inputs && self.outputs == other.outputs;
+        for row in 0..self.rows.len() {
+            if same_order && self.rows[row] == other.rows[row] {
+                continue;
+            }
+            // pins may be declared in another order by the other chip
+            let assignment = self.assignment(row);
+            let expected = self.row(row);
+            let actual = other.row(other.find(&assignment));
+            if from_values(&self.outputs, &actual) != self.rows[row] {
+                return Ok(Some(Counterexample {
+                    chip: self.chip.clone(),
+                    other: other.chip.clone(),
+                    inputs: assignment,
+                    expected,
+                    actual,
+                    input_pins: self.inputs.clone(),
+                    output_pins: self.outputs.clone()
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn describe(pins: &[(String, i64)], values: &PinValues) -> String {
+    pins.iter()
+        .map(|(name, size)| format!("{}={}", name, format_value(values.get_number(name, *size), *size, Format::Binary, *size as usize)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Rows in the format of the output files of the test scripts, every pin in
+// binary with a space on each side
+impl fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let columns: Vec<&(String, i64)> = self.inputs.iter().chain(self.outputs.iter()).collect();
+        let header: Vec<String> = columns.iter().map(|(name, size)| center(name, *size as usize + 2)).collect();
+        writeln!(f, "|{}|", header.join("|"))?;
+        for row in 0..self.rows.len() {
+            let numbers = unpack(&self.inputs, row as u64).into_iter().chain(self.row_numbers(row));
+            let cells: Vec<String> = columns.iter().zip(numbers)
+                .map(|((_, size), number)| format!(" {} ", format_value(number, *size, Format::Binary, *size as usize)))
+                .collect();
+            writeln!(f, "|{}|", cells.join("|"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    pub chip: String,
+    pub other: String,
+    pub inputs: PinValues,
+    // outputs of the first chip
+    pub expected: PinValues,
+    pub actual: PinValues,
+    input_pins: Vec<(String, i64)>,
+    output_pins: Vec<(String, i64)>
+}
+
+impl Counterexample {
+    pub(crate) fn new(chip: &Gate, other: &Gate, inputs: PinValues, expected: PinValues, actual: PinValues) -> Counterexample {
+        Counterexample {
+            chip: chip.name.clone(),
+            other: other.name.clone(),
+            inputs,
+            expected,
+            actual,
+            input_pins: pins(chip, PinKind::Input),
+            output_pins: pins(chip, PinKind::Output)
+        }
+    }
+}
+
+// a=1 b=0: out=1 in xor but out=0 in myxor
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} in {} but {} in {}", describe(&self.input_pins, &self.inputs),
+            describe(&self.output_pins, &self.expected), self.chip, describe(&self.output_pins, &self.actual), self.other)
+    }
+}
+
+// Compares two combinational gates on every input, None if they are
+// equivalent
+pub fn check_equivalence(a: &mut Gate, b: &mut Gate) -> Result<Option<Counterexample>, TruthTableError> {
+    let table = TruthTable::new(a)?;
+    let other = TruthTable::new(b)?;
+    table.compare(&other)
+}
@@ -1,28 +1,27 @@
-use crate::gates::gate::PinKind;
+use crate::gates::gate::{Pin, PinKind};
 use std::collections::BTreeSet;
-use crate::gates::gate::{Pin};
 use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Clone)]
 pub struct PinKey {
-    name: String,
-    index: i64
+    pub name: String,
+    pub index: i64
 }
 
 impl PinKey {
     pub fn new(name: &str, index: i64) -> PinKey {
-        PinKey { name: name.to_string(), index: index } 
+        PinKey { name: name.to_string(), index }
     }
 }
 
 #[derive(Debug)]
 pub struct PinMap {
-    internal: BTreeMap<PinKey, Pin>,
+    pub(crate) internal: BTreeMap<PinKey, Pin>,
     names: Vec<String>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PinValues {
     map: BTreeMap<PinKey, bool>,
     names: BTreeSet<String>
@@ -38,21 +37,44 @@ impl PinValues {
 
     pub fn set_binary(&mut self, name: &str, value: &str)  {
         for (i, c) in value.chars().enumerate() {
-            if c == '0' {
-                self.set(name, i as i64, false);
-            } else {
-                self.set(name, i as i64, true);
-            }
-        };
+            self.set(name, i as i64, c != '0');
+        }
     }
 
     pub fn get(&self, name: &str, index: i64) -> bool {
         *self.map.get(&PinKey::new(name, index)).unwrap()
     }
 
+    // bit i of value goes to pin index i
+    pub fn set_number(&mut self, name: &str, size: i64, value: u64) {
+        for i in 0..size {
+            self.set(name, i, (value >> i) & 1 == 1);
+        }
+    }
+
+    pub fn get_number(&self, name: &str, size: i64) -> u64 {
+        let mut out = 0;
+        for i in 0..size {
+            if self.get(name, i) {
+                out |= 1 << i;
+            }
+        }
+        out
+    }
+
+    pub fn contains(&self, name: &str, index: i64) -> bool {
+        self.map.contains_key(&PinKey::new(name, index))
+    }
+
     pub fn set(&mut self, name: &str, index: i64, value: bool) {
         self.map.insert(PinKey::new(name, index), value);
-        self.names.insert(name.to_string());
+        if !self.names.contains(name) {
+            self.names.insert(name.to_string());
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PinKey, &bool)> {
+        self.map.iter()
     }
 }
 
@@ -63,16 +85,8 @@ impl fmt::Display for PinValues {
         for name in &self.names {
             let mut out = Vec::new();
             let mut i = 0;
-            while true {
-                let a = self.map.get(&PinKey::new(&name, i));
-                if a.is_none() {
-                    break;
-                }
-                if *a.unwrap() == true {
-                    out.push("1");
-                } else {
-                    out.push("0");
-                }
+            while let Some(a) = self.map.get(&PinKey::new(name, i)) {
+                out.push(if *a { "1" } else { "0" });
                 i += 1;
             }
             res.push([name.clone(), out.join("")].join(":"));
@@ -82,38 +96,46 @@ impl fmt::Display for PinValues {
 }
 
 impl PinMap {
-    fn new() -> PinMap {
+    pub fn new() -> PinMap {
         PinMap {
             internal: BTreeMap::new(),
             names: Vec::new()
-        }    
+        }
     }
 
-    fn insert(&mut self, id: &str, kind: PinKind, name: &str, size: i64, index: i64) {
+    pub fn insert(&mut self, kind: PinKind, name: &str, size: i64, index: i64) {
+        let slot = match self.internal.get(&PinKey::new(name, index)) {
+            Some(x) => x.slot,
+            None => self.internal.len()
+        };
         let pin = Pin {
             name: name.to_string(),
-            index: index,
-            kind: kind,
-            size: size,
-            connection: Connection::None
+            index,
+            kind,
+            size,
+            clocked: false,
+            slot,
+            connections: Vec::new()
         };
-        let name2 = name.to_string();
-        self.names.push(name2);
-        self.internal.insert(PinKey::new(&self.names[self.names.len()-1], index), pin);
-        
+        if index == 0 {
+            self.names.push(name.to_string());
+        }
+        self.internal.insert(PinKey::new(name, index), pin);
     }
 
-    fn get(&self, name: &str, index: i64) -> Option<&Pin> {
-        match self.internal.get(&PinKey::new(name, index)) {
-            None => None,
-            Some(x) => Some(x)
-        }
+    pub fn get(&self, name: &str, index: i64) -> Option<&Pin> {
+        self.internal.get(&PinKey::new(name, index))
     }
 
-    fn get_mut(&mut self, name: &str, index: i64) -> Option<&mut Pin> {
-        match self.internal.get_mut(&PinKey::new(name, index)) {
-            None => None,
-            Some(x) => Some(x)
-        }
+    pub fn get_mut(&mut self, name: &str, index: i64) -> Option<&mut Pin> {
+        self.internal.get_mut(&PinKey::new(name, index))
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
     }
-}
\ No newline at end of file
+
+    pub(crate) fn len(&self) -> usize {
+        self.internal.len()
+    }
+}
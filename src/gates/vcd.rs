@@ -0,0 +1,244 @@
+// Value Change Dump of pins for waveform viewers like GTKWave
+//
+// Pins are selected with paths relative to the recorded gate:
+//
+// out          pin of the gate itself
+// cpu.areg     pin of a part, internal pins included
+// cpu.*        every pin of a part
+// cpu.**       every pin of a part and of all the parts inside it
+//
+// Parts are named after their chip, parts of the same chip get the number of
+// their occurrence, like register_0 and register_1. Each part is a scope of
+// the dump.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::gates::gate::Gate;
+use crate::gates::utils::PinValues;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VcdError {
+    pub pattern: String,
+    pub message: String
+}
+
+impl fmt::Display for VcdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.pattern, self.message)
+    }
+}
+
+impl std::error::Error for VcdError {}
+
+// names of the parts of a gate as used in paths and scopes
+pub fn part_names(gate: &Gate) -> Vec<String> {
+    let mut out = Vec::new();
+    for (i, part) in gate.gates.iter().enumerate() {
+        let same = gate.gates.iter().filter(|x| x.name == part.name).count();
+        if same == 1 {
+            out.push(part.name.clone());
+        } else {
+            let occurrence = gate.gates[..i].iter().filter(|x| x.name == part.name).count();
+            out.push(format!("{}_{}", part.name, occurrence));
+        }
+    }
+    out
+}
+
+struct Signal {
+    // part indices from the recorded gate
+    path: Vec<usize>,
+    scope: Vec<String>,
+    name: String,
+    // slots of the bits, the least significant first
+    slots: Vec<usize>,
+    id: String
+}
+
+pub struct VcdRecorder {
+    signals: Vec<Signal>,
+    last: Vec<Option<Vec<bool>>>,
+    out: String,
+    time: u64
+}
+
+// short identifier made of printable characters
+fn identifier(mut n: usize) -> String {
+    let mut out = String::new();
+    loop {
+        out.push((33 + n % 94) as u8 as char);
+        n /= 94;
+        if n == 0 {
+            return out;
+        }
+        n -= 1;
+    }
+}
+
+// pins of a gate as (name, slots), in declaration order
+fn pins(gate: &Gate) -> Vec<(String, Vec<usize>)> {
+    let mut out: Vec<(String, Vec<usize>)> = Vec::new();
+    for name in gate.pin_names() {
+        if out.iter().any(|(x, _)| x == name) {
+            continue;
+        }
+        let mut slots = Vec::new();
+        while let Some(pin) = gate.get_pin(name, slots.len() as i64) {
+            slots.push(pin.slot);
+        }
+        out.push((name.clone(), slots));
+    }
+    out
+}
+
+impl VcdRecorder {
+    pub fn new(gate: &Gate, patterns: &[&str]) -> Result<VcdRecorder, VcdError> {
+        let mut signals: Vec<Signal> = Vec::new();
+        for pattern in patterns {
+            let error = |message: String| VcdError { pattern: pattern.to_string(), message };
+            let mut parts: Vec<&str> = pattern.split('.').collect();
+            let last = parts.pop().unwrap_or("");
+            let mut current = gate;
+            let mut path = Vec::new();
+            let mut scope = vec![gate.name.clone()];
+            for part in parts {
+                let names = part_names(current);
+                let index = match names.iter().position(|x| x == part) {
+                    Some(x) => x,
+                    None => return Err(error(format!("{} has no part {}", scope.join("."), part)))
+                };
+                path.push(index);
+                scope.push(part.to_string());
+                current = &current.gates[index];
+            }
+
+            let mut found = Vec::new();
+            match last {
+                "*" => {
+                    for (name, slots) in pins(current) {
+                        found.push((path.clone(), scope.clone(), name, slots));
+                    }
+                },
+                "**" => {
+                    let mut stack = vec![(current, path.clone(), scope.clone())];
+                    while let Some((gate, path, scope)) = stack.pop() {
+                        for (name, slots) in pins(gate) {
+                            found.push((path.clone(), scope.clone(), name, slots));
+                        }
+                        let names = part_names(gate);
+                        for (i, part) in gate.gates.iter().enumerate().rev() {
+                            let mut path = path.clone();
+                            path.push(i);
+                            let mut scope = scope.clone();
+                            scope.push(names[i].clone());
+                            stack.push((part, path, scope));
+                        }
+                    }
+                },
+                name => {
+                    let slots = match pins(current).into_iter().find(|(x, _)| x == name) {
+                        Some((_, slots)) => slots,
+                        None => return Err(error(format!("{} has no pin {}", scope.join("."), name)))
+                    };
+                    found.push((path, scope, name.to_string(), slots));
+                }
+            }
+            for (path, scope, name, slots) in found {
+                if signals.iter().any(|x| x.path == path && x.name == name) {
+                    continue;
+                }
+                signals.push(Signal { path, scope, name, slots, id: String::new() });
+            }
+        }
+        // signals of a scope must be declared together, parts in order
+        signals.sort_by(|a, b| a.path.cmp(&b.path));
+        for (i, signal) in signals.iter_mut().enumerate() {
+            signal.id = identifier(i);
+        }
+
+        let mut out = String::new();
+        out.push_str("$version sunho-computer $end\n");
+        out.push_str("$timescale 1ns $end\n");
+        let mut open: Vec<String> = Vec::new();
+        for signal in &signals {
+            let common = open.iter().zip(signal.scope.iter()).take_while(|(a, b)| a == b).count();
+            while open.len() > common {
+                out.push_str("$upscope $end\n");
+                open.pop();
+            }
+            for name in &signal.scope[common..] {
+                out.push_str(&format!("$scope module {} $end\n", name));
+                open.push(name.clone());
+            }
+            let size = signal.slots.len();
+            if size == 1 {
+                out.push_str(&format!("$var wire 1 {} {} $end\n", signal.id, signal.name));
+            } else {
+                out.push_str(&format!("$var wire {} {} {} [{}:0] $end\n", size, signal.id, signal.name, size - 1));
+            }
+        }
+        for _ in open {
+            out.push_str("$upscope $end\n");
+        }
+        out.push_str("$enddefinitions $end\n");
+
+        let last = vec![None; signals.len()];
+        Ok(VcdRecorder { signals, last, out, time: 0 })
+    }
+
+    // names of the recorded pins like computer.cpu.areg
+    pub fn signal_names(&self) -> Vec<String> {
+        self.signals.iter().map(|x| format!("{}.{}", x.scope.join("."), x.name)).collect()
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    // Writes the pins that changed at the current time and moves on by one
+    pub fn sample(&mut self, gate: &Gate) {
+        let mut changes = String::new();
+        for (i, signal) in self.signals.iter().enumerate() {
+            let mut part = gate;
+            for index in &signal.path {
+                part = &part.gates[*index];
+            }
+            let values: Vec<bool> = signal.slots.iter().map(|x| part.values()[*x]).collect();
+            if self.last[i].as_ref() == Some(&values) {
+                continue;
+            }
+            if values.len() == 1 {
+                changes.push_str(&format!("{}{}\n", if values[0] { 1 } else { 0 }, signal.id));
+            } else {
+                let bits: String = values.iter().rev().map(|x| if *x { '1' } else { '0' }).collect();
+                changes.push_str(&format!("b{} {}\n", bits, signal.id));
+            }
+            self.last[i] = Some(values);
+        }
+        if !changes.is_empty() || self.time == 0 {
+            self.out.push_str(&format!("#{}\n", self.time));
+            self.out.push_str(&changes);
+        }
+        self.time += 1;
+    }
+
+    // Runs a clock cycle and samples after the tick and after the tock
+    pub fn cycle(&mut self, gate: &mut Gate, inputs: PinValues) -> PinValues {
+        gate.tick(inputs);
+        self.sample(gate);
+        let out = gate.tock();
+        self.sample(gate);
+        out
+    }
+
+    pub fn contents(&self) -> String {
+        format!("{}#{}\n", self.out, self.time)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.contents())
+    }
+}
@@ -0,0 +1,260 @@
+// Instruction level emulator of the Hack computer
+//
+// Behaves like the computer chip of gates/computer.rs, including the
+// computations that have no assembly mnemonic since the ALU bits are decoded
+// the same way. Lockstep runs both and stops at the first difference.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::assembler::{assemble_file, parse_hack, AsmError};
+use crate::gates::{Gate, GateFactory, PinValues};
+
+pub const ROM_SIZE: usize = 32768;
+pub const SCREEN: usize = 0x4000;
+pub const SCREEN_WORDS: usize = 8192;
+pub const KEYBOARD: usize = 0x6000;
+
+// .asm files are assembled, anything else is read as .hack text
+pub fn load_program<P: AsRef<Path>>(path: P) -> Result<Vec<u16>, AsmError> {
+    let path = path.as_ref();
+    let file = path.display().to_string();
+    let program = if path.extension().map(|x| x == "asm").unwrap_or(false) {
+        assemble_file(path)?
+    } else {
+        match std::fs::read_to_string(path) {
+            Ok(source) => parse_hack(&file, &source)?,
+            Err(e) => return Err(AsmError { file, line: 0, message: e.to_string() })
+        }
+    };
+    if program.len() > ROM_SIZE {
+        let message = format!("program of {} words does not fit in the ROM of {} words", program.len(), ROM_SIZE);
+        return Err(AsmError { file, line: 0, message });
+    }
+    Ok(program)
+}
+
+// zx nx zy ny f no are bits 11 to 6 of the instruction
+fn alu(x: u16, y: u16, instruction: u16) -> u16 {
+    let bit = |i: u16| (instruction >> i) & 1 == 1;
+    let x = if bit(11) { 0 } else { x };
+    let x = if bit(10) { !x } else { x };
+    let y = if bit(9) { 0 } else { y };
+    let y = if bit(8) { !y } else { y };
+    let out = if bit(7) { x.wrapping_add(y) } else { x & y };
+    if bit(6) { !out } else { out }
+}
+
+pub struct HackEmulator {
+    rom: Vec<u16>,
+    // RAM, screen and keyboard
+    ram: Vec<u16>,
+    a: u16,
+    d: u16,
+    pc: u16,
+    cycles: u64
+}
+
+impl HackEmulator {
+    // programs from load_program always fit in the ROM
+    pub fn new(program: &[u16]) -> HackEmulator {
+        assert!(program.len() <= ROM_SIZE, "program of {} words does not fit in the ROM of {} words", program.len(), ROM_SIZE);
+        let mut rom = vec![0; ROM_SIZE];
+        rom[..program.len()].copy_from_slice(program);
+        HackEmulator { rom, ram: vec![0; KEYBOARD + 1], a: 0, d: 0, pc: 0, cycles: 0 }
+    }
+
+    pub fn a(&self) -> u16 {
+        self.a
+    }
+
+    pub fn d(&self) -> u16 {
+        self.d
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc & 0x7fff
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn rom(&self) -> &[u16] {
+        &self.rom
+    }
+
+    pub fn ram(&self) -> &[u16] {
+        &self.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u16] {
+        &mut self.ram
+    }
+
+    pub fn screen(&self) -> &[u16] {
+        &self.ram[SCREEN..SCREEN + SCREEN_WORDS]
+    }
+
+    // scan code of the key held down, 0 for none
+    pub fn set_key(&mut self, key: u16) {
+        self.ram[KEYBOARD] = key;
+    }
+
+    // A cycle with reset set, the instruction still runs like in the CPU chip
+    // but the PC becomes 0
+    pub fn reset(&mut self) {
+        self.step();
+        self.pc = 0;
+    }
+
+    // every address from the keyboard up reads the keyboard like the memory
+    // chip, writes there are lost
+    fn read(&self, address: u16) -> u16 {
+        let address = (address & 0x7fff) as usize;
+        self.ram[address.min(KEYBOARD)]
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        let address = (address & 0x7fff) as usize;
+        if address < KEYBOARD {
+            self.ram[address] = value;
+        }
+    }
+
+    pub fn step(&mut self) {
+        let instruction = self.rom[self.pc() as usize];
+        self.cycles += 1;
+        if instruction & 0x8000 == 0 {
+            self.a = instruction;
+            self.pc = self.pc.wrapping_add(1);
+            return;
+        }
+        let y = if instruction & 0x1000 != 0 { self.read(self.a) } else { self.a };
+        let out = alu(self.d, y, instruction);
+        let address = self.a;
+        if instruction & 0x08 != 0 {
+            self.write(address, out);
+        }
+        if instruction & 0x10 != 0 {
+            self.d = out;
+        }
+        if instruction & 0x20 != 0 {
+            self.a = out;
+        }
+        let negative = (out as i16) < 0;
+        let jump = (instruction & 0x04 != 0 && negative)
+            || (instruction & 0x02 != 0 && out == 0)
+            || (instruction & 0x01 != 0 && !negative && out != 0);
+        self.pc = if jump { address } else { self.pc.wrapping_add(1) };
+    }
+
+    pub fn run(&mut self, cycles: u64) {
+        for _ in 0..cycles {
+            self.step();
+        }
+    }
+}
+
+// First difference between the emulator and the gates, `location` is PC, A,
+// D or RAM[address]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub cycle: u64,
+    pub location: String,
+    pub emulator: u16,
+    pub gates: u16
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cycle {}: {} is {} in the emulator but {} in the gates", self.cycle, self.location, self.emulator, self.gates)
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+fn read_register(gate: &Gate, name: &str, size: i64) -> u16 {
+    let mut out = 0;
+    for i in 0..size {
+        if gate.value(name, i).unwrap_or(false) {
+            out |= 1 << i;
+        }
+    }
+    out
+}
+
+// Runs the emulator and the gate level computer chip cycle by cycle
+pub struct Lockstep {
+    pub emulator: HackEmulator,
+    pub computer: Gate
+}
+
+impl Lockstep {
+    pub fn new(program: &[u16]) -> Lockstep {
+        let emulator = HackEmulator::new(program);
+        let mut computer = GateFactory::with_builtin_memory().build("computer");
+        computer.find_mut("rom32k").unwrap().memory_mut().unwrap().copy_from_slice(emulator.rom());
+        Lockstep { emulator, computer }
+    }
+
+    pub fn set_key(&mut self, key: u16) {
+        self.emulator.set_key(key);
+        self.computer.find_mut("keyboard").unwrap().memory_mut().unwrap()[0] = key;
+    }
+
+    fn cycle(&mut self, reset: bool) -> Result<(), Divergence> {
+        if reset {
+            self.emulator.reset();
+        } else {
+            self.emulator.step();
+        }
+        let mut inputs = PinValues::new();
+        inputs.set("reset", 0, reset);
+        self.computer.run(inputs);
+        self.compare()
+    }
+
+    pub fn step(&mut self) -> Result<(), Divergence> {
+        self.cycle(false)
+    }
+
+    pub fn reset(&mut self) -> Result<(), Divergence> {
+        self.cycle(true)
+    }
+
+    pub fn run(&mut self, cycles: u64) -> Result<(), Divergence> {
+        for _ in 0..cycles {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    pub fn compare(&self) -> Result<(), Divergence> {
+        let cpu = self.computer.find("cpu").unwrap();
+        let emulator = &self.emulator;
+        let divergence = |location: String, emulator: u16, gates: u16| {
+            Divergence { cycle: self.emulator.cycles(), location, emulator, gates }
+        };
+        let registers = [
+            ("PC", emulator.pc(), read_register(cpu, "pc", 15)),
+            ("A", emulator.a(), read_register(cpu, "areg", 16)),
+            ("D", emulator.d(), read_register(cpu, "dreg", 16))
+        ];
+        for (name, x, y) in registers.iter() {
+            if x != y {
+                return Err(divergence(name.to_string(), *x, *y));
+            }
+        }
+        let parts = [("ram16k", 0), ("screen", SCREEN)];
+        for (name, base) in parts.iter() {
+            let words = self.computer.find(name).unwrap().memory().unwrap();
+            let ram = &emulator.ram()[*base..*base + words.len()];
+            if ram != words {
+                let i = (0..words.len()).find(|i| ram[*i] != words[*i]).unwrap();
+                return Err(divergence(format!("RAM[{}]", base + i), ram[i], words[i]));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Char,
+    Boolean,
+    Class(String)
+}
+
+impl Type {
+    pub fn name(&self) -> &str {
+        match self {
+            Type::Int => "int",
+            Type::Char => "char",
+            Type::Boolean => "boolean",
+            Type::Class(x) => x
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassVarKind {
+    Static,
+    Field
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassVarDec {
+    pub kind: ClassVarKind,
+    pub ty: Type,
+    pub names: Vec<(String, Location)>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubroutineKind {
+    Constructor,
+    Function,
+    Method
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subroutine {
+    pub kind: SubroutineKind,
+    // None for void
+    pub return_type: Option<Type>,
+    pub name: String,
+    pub parameters: Vec<(Type, String, Location)>,
+    pub locals: Vec<(Type, String, Location)>,
+    pub body: Vec<Statement>,
+    pub location: Location
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub name: String,
+    pub vars: Vec<ClassVarDec>,
+    pub subroutines: Vec<Subroutine>,
+    pub location: Location
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let { name: String, index: Option<Expression>, value: Expression, location: Location },
+    If { condition: Expression, then: Vec<Statement>, otherwise: Option<Vec<Statement>> },
+    While { condition: Expression, body: Vec<Statement> },
+    Do(SubroutineCall),
+    Return(Option<Expression>, Location)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Lt,
+    Gt,
+    Eq
+}
+
+impl BinaryOp {
+    pub fn from_symbol(c: char) -> Option<BinaryOp> {
+        let op = match c {
+            '+' => BinaryOp::Add,
+            '-' => BinaryOp::Sub,
+            '*' => BinaryOp::Mul,
+            '/' => BinaryOp::Div,
+            '&' => BinaryOp::And,
+            '|' => BinaryOp::Or,
+            '<' => BinaryOp::Lt,
+            '>' => BinaryOp::Gt,
+            '=' => BinaryOp::Eq,
+            _ => return None
+        };
+        Some(op)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeywordConstant {
+    True,
+    False,
+    Null,
+    This
+}
+
+// Jack has no operator precedence, operators apply from left to right
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub term: Term,
+    pub ops: Vec<(BinaryOp, Term)>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Int(u16),
+    Str(String),
+    Keyword(KeywordConstant, Location),
+    Var(String, Location),
+    Index(String, Box<Expression>, Location),
+    Call(SubroutineCall),
+    Paren(Box<Expression>),
+    Unary(UnaryOp, Box<Term>)
+}
+
+// name(args) or target.name(args), target is a variable or a class
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineCall {
+    pub target: Option<String>,
+    pub name: String,
+    pub args: Vec<Expression>,
+    pub location: Location
+}
@@ -0,0 +1,354 @@
+// Jack to VM code
+//
+// static -> static, field -> this, parameter -> argument, var -> local.
+// Methods get the object as argument 0 and constructors allocate the fields
+// with Memory.alloc. Labels follow the reference compiler: IF_TRUE0,
+// IF_FALSE0, IF_END0, WHILE_EXP0 and WHILE_END0 numbered per subroutine.
+
+use std::collections::BTreeMap;
+
+use super::ast::*;
+use super::JackError;
+use crate::vm::{ArithmeticOp, Segment, VmCommand};
+
+#[derive(Debug, Clone)]
+struct Symbol {
+    ty: Type,
+    segment: Segment,
+    index: u16
+}
+
+struct Compiler<'a> {
+    class: &'a Class,
+    class_symbols: BTreeMap<String, Symbol>,
+    symbols: BTreeMap<String, Symbol>,
+    subroutines: BTreeMap<String, SubroutineKind>,
+    fields: u16,
+    kind: SubroutineKind,
+    returns_value: bool,
+    if_count: usize,
+    while_count: usize,
+    out: Vec<VmCommand>
+}
+
+impl<'a> Compiler<'a> {
+    fn error<T>(&self, location: Location, message: String) -> Result<T, JackError> {
+        Err(JackError { class: self.class.name.clone(), line: location.line, column: location.column, message })
+    }
+
+    fn emit(&mut self, command: VmCommand) {
+        self.out.push(command);
+    }
+
+    fn push(&mut self, segment: Segment, index: u16) {
+        self.emit(VmCommand::Push(segment, index));
+    }
+
+    fn pop(&mut self, segment: Segment, index: u16) {
+        self.emit(VmCommand::Pop(segment, index));
+    }
+
+    fn arithmetic(&mut self, op: ArithmeticOp) {
+        self.emit(VmCommand::Arithmetic(op));
+    }
+
+    fn call(&mut self, name: String, args: u16) {
+        self.emit(VmCommand::Call(name, args));
+    }
+
+    fn label(&mut self, label: String) {
+        self.emit(VmCommand::Label(label));
+    }
+
+    fn declare_class_vars(&mut self) -> Result<(), JackError> {
+        let mut statics = 0;
+        for dec in &self.class.vars {
+            for (name, location) in &dec.names {
+                if self.class_symbols.contains_key(name) {
+                    return self.error(*location, format!("'{}' is already declared", name));
+                }
+                let (segment, index) = match dec.kind {
+                    ClassVarKind::Static => {
+                        statics += 1;
+                        (Segment::Static, statics - 1)
+                    },
+                    ClassVarKind::Field => {
+                        self.fields += 1;
+                        (Segment::This, self.fields - 1)
+                    }
+                };
+                self.class_symbols.insert(name.clone(), Symbol { ty: dec.ty.clone(), segment, index });
+            }
+        }
+        for subroutine in &self.class.subroutines {
+            if self.subroutines.insert(subroutine.name.clone(), subroutine.kind).is_some() {
+                return self.error(subroutine.location, format!("subroutine '{}' is already declared", subroutine.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str, location: Location) -> Result<Symbol, JackError> {
+        if let Some(x) = self.symbols.get(name) {
+            return Ok(x.clone());
+        }
+        if let Some(x) = self.class_symbols.get(name) {
+            if x.segment == Segment::This && self.kind == SubroutineKind::Function {
+                return self.error(location, format!("field '{}' cannot be used in a function", name));
+            }
+            return Ok(x.clone());
+        }
+        self.error(location, format!("undefined variable '{}'", name))
+    }
+
+    fn declare(&mut self, name: &str, ty: &Type, segment: Segment, index: u16, location: Location) -> Result<(), JackError> {
+        if self.symbols.contains_key(name) {
+            return self.error(location, format!("'{}' is already declared", name));
+        }
+        self.symbols.insert(name.to_string(), Symbol { ty: ty.clone(), segment, index });
+        Ok(())
+    }
+
+    fn compile_subroutine(&mut self, subroutine: &Subroutine) -> Result<(), JackError> {
+        self.symbols = BTreeMap::new();
+        self.kind = subroutine.kind;
+        self.returns_value = subroutine.return_type.is_some();
+        self.if_count = 0;
+        self.while_count = 0;
+
+        let offset = if subroutine.kind == SubroutineKind::Method { 1 } else { 0 };
+        for (i, (ty, name, location)) in subroutine.parameters.iter().enumerate() {
+            self.declare(name, ty, Segment::Argument, i as u16 + offset, *location)?;
+        }
+        for (i, (ty, name, location)) in subroutine.locals.iter().enumerate() {
+            self.declare(name, ty, Segment::Local, i as u16, *location)?;
+        }
+
+        let name = format!("{}.{}", self.class.name, subroutine.name);
+        self.emit(VmCommand::Function(name, subroutine.locals.len() as u16));
+        match subroutine.kind {
+            SubroutineKind::Constructor => {
+                let fields = self.fields;
+                self.push(Segment::Constant, fields);
+                self.call("Memory.alloc".to_string(), 1);
+                self.pop(Segment::Pointer, 0);
+            },
+            SubroutineKind::Method => {
+                self.push(Segment::Argument, 0);
+                self.pop(Segment::Pointer, 0);
+            },
+            SubroutineKind::Function => {}
+        }
+        self.compile_statements(&subroutine.body)
+    }
+
+    fn compile_statements(&mut self, statements: &[Statement]) -> Result<(), JackError> {
+        for statement in statements {
+            self.compile_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<(), JackError> {
+        match statement {
+            Statement::Let { name, index: None, value, location } => {
+                let symbol = self.lookup(name, *location)?;
+                self.compile_expression(value)?;
+                self.pop(symbol.segment, symbol.index);
+            },
+            Statement::Let { name, index: Some(index), value, location } => {
+                let symbol = self.lookup(name, *location)?;
+                self.push(symbol.segment, symbol.index);
+                self.compile_expression(index)?;
+                self.arithmetic(ArithmeticOp::Add);
+                self.compile_expression(value)?;
+                self.pop(Segment::Temp, 0);
+                self.pop(Segment::Pointer, 1);
+                self.push(Segment::Temp, 0);
+                self.pop(Segment::That, 0);
+            },
+            Statement::If { condition, then, otherwise } => {
+                let n = self.if_count;
+                self.if_count += 1;
+                self.compile_expression(condition)?;
+                self.emit(VmCommand::IfGoto(format!("IF_TRUE{}", n)));
+                self.emit(VmCommand::Goto(format!("IF_FALSE{}", n)));
+                self.label(format!("IF_TRUE{}", n));
+                self.compile_statements(then)?;
+                match otherwise {
+                    Some(otherwise) => {
+                        self.emit(VmCommand::Goto(format!("IF_END{}", n)));
+                        self.label(format!("IF_FALSE{}", n));
+                        self.compile_statements(otherwise)?;
+                        self.label(format!("IF_END{}", n));
+                    },
+                    None => self.label(format!("IF_FALSE{}", n))
+                }
+            },
+            Statement::While { condition, body } => {
+                let n = self.while_count;
+                self.while_count += 1;
+                self.label(format!("WHILE_EXP{}", n));
+                self.compile_expression(condition)?;
+                self.arithmetic(ArithmeticOp::Not);
+                self.emit(VmCommand::IfGoto(format!("WHILE_END{}", n)));
+                self.compile_statements(body)?;
+                self.emit(VmCommand::Goto(format!("WHILE_EXP{}", n)));
+                self.label(format!("WHILE_END{}", n));
+            },
+            Statement::Do(call) => {
+                self.compile_call(call)?;
+                self.pop(Segment::Temp, 0);
+            },
+            Statement::Return(value, location) => {
+                match value {
+                    Some(value) => {
+                        if !self.returns_value {
+                            return self.error(*location, "a void subroutine cannot return a value".to_string());
+                        }
+                        self.compile_expression(value)?;
+                    },
+                    None => {
+                        if self.returns_value {
+                            return self.error(*location, "missing return value".to_string());
+                        }
+                        self.push(Segment::Constant, 0);
+                    }
+                }
+                self.emit(VmCommand::Return);
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<(), JackError> {
+        self.compile_term(&expression.term)?;
+        for (op, term) in &expression.ops {
+            self.compile_term(term)?;
+            match op {
+                BinaryOp::Add => self.arithmetic(ArithmeticOp::Add),
+                BinaryOp::Sub => self.arithmetic(ArithmeticOp::Sub),
+                BinaryOp::And => self.arithmetic(ArithmeticOp::And),
+                BinaryOp::Or => self.arithmetic(ArithmeticOp::Or),
+                BinaryOp::Lt => self.arithmetic(ArithmeticOp::Lt),
+                BinaryOp::Gt => self.arithmetic(ArithmeticOp::Gt),
+                BinaryOp::Eq => self.arithmetic(ArithmeticOp::Eq),
+                BinaryOp::Mul => self.call("Math.multiply".to_string(), 2),
+                BinaryOp::Div => self.call("Math.divide".to_string(), 2)
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_term(&mut self, term: &Term) -> Result<(), JackError> {
+        match term {
+            Term::Int(x) => self.push(Segment::Constant, *x),
+            Term::Str(x) => {
+                self.push(Segment::Constant, x.chars().count() as u16);
+                self.call("String.new".to_string(), 1);
+                for c in x.chars() {
+                    self.push(Segment::Constant, c as u16);
+                    self.call("String.appendChar".to_string(), 2);
+                }
+            },
+            Term::Keyword(constant, location) => {
+                match constant {
+                    KeywordConstant::True => {
+                        self.push(Segment::Constant, 0);
+                        self.arithmetic(ArithmeticOp::Not);
+                    },
+                    KeywordConstant::False | KeywordConstant::Null => self.push(Segment::Constant, 0),
+                    KeywordConstant::This => {
+                        if self.kind == SubroutineKind::Function {
+                            return self.error(*location, "'this' cannot be used in a function".to_string());
+                        }
+                        self.push(Segment::Pointer, 0);
+                    }
+                }
+            },
+            Term::Var(name, location) => {
+                let symbol = self.lookup(name, *location)?;
+                self.push(symbol.segment, symbol.index);
+            },
+            Term::Index(name, index, location) => {
+                let symbol = self.lookup(name, *location)?;
+                self.push(symbol.segment, symbol.index);
+                self.compile_expression(index)?;
+                self.arithmetic(ArithmeticOp::Add);
+                self.pop(Segment::Pointer, 1);
+                self.push(Segment::That, 0);
+            },
+            Term::Call(call) => self.compile_call(call)?,
+            Term::Paren(expression) => self.compile_expression(expression)?,
+            Term::Unary(op, term) => {
+                self.compile_term(term)?;
+                match op {
+                    UnaryOp::Neg => self.arithmetic(ArithmeticOp::Neg),
+                    UnaryOp::Not => self.arithmetic(ArithmeticOp::Not)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_call(&mut self, call: &SubroutineCall) -> Result<(), JackError> {
+        let args = call.args.len() as u16;
+        let (name, args) = match &call.target {
+            None => {
+                match self.subroutines.get(&call.name) {
+                    Some(SubroutineKind::Method) => {
+                        if self.kind == SubroutineKind::Function {
+                            return self.error(call.location, format!("method '{}' cannot be called from a function", call.name));
+                        }
+                        self.push(Segment::Pointer, 0);
+                        (format!("{}.{}", self.class.name, call.name), args + 1)
+                    },
+                    Some(_) => (format!("{}.{}", self.class.name, call.name), args),
+                    None => return self.error(call.location, format!("undefined subroutine '{}' in class {}", call.name, self.class.name))
+                }
+            },
+            Some(target) => {
+                let symbol = match self.symbols.get(target).or_else(|| self.class_symbols.get(target)) {
+                    Some(_) => Some(self.lookup(target, call.location)?),
+                    None => None
+                };
+                match symbol {
+                    Some(symbol) => {
+                        let class = match &symbol.ty {
+                            Type::Class(x) => x.clone(),
+                            ty => return self.error(call.location, format!("'{}' of type {} has no methods", target, ty.name()))
+                        };
+                        self.push(symbol.segment, symbol.index);
+                        (format!("{}.{}", class, call.name), args + 1)
+                    },
+                    None => (format!("{}.{}", target, call.name), args)
+                }
+            }
+        };
+        for arg in &call.args {
+            self.compile_expression(arg)?;
+        }
+        self.call(name, args);
+        Ok(())
+    }
+}
+
+pub fn compile_class(class: &Class) -> Result<Vec<VmCommand>, JackError> {
+    let mut compiler = Compiler {
+        class,
+        class_symbols: BTreeMap::new(),
+        symbols: BTreeMap::new(),
+        subroutines: BTreeMap::new(),
+        fields: 0,
+        kind: SubroutineKind::Function,
+        returns_value: false,
+        if_count: 0,
+        while_count: 0,
+        out: Vec::new()
+    };
+    compiler.declare_class_vars()?;
+    for subroutine in &class.subroutines {
+        compiler.compile_subroutine(subroutine)?;
+    }
+    Ok(compiler.out)
+}
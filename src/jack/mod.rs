@@ -0,0 +1,93 @@
+// Jack compiler: tokenizer, recursive descent parser and a code generator
+// that emits one .vm file per class
+
+mod tokenizer;
+mod parser;
+mod compiler;
+pub mod ast;
+
+pub use tokenizer::{tokenize, Token, TokenKind};
+pub use parser::parse_class;
+pub use compiler::compile_class;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::vm::VmCommand;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JackError {
+    // name of the class, or the path of a file or directory that can not be read
+    pub class: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for JackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.class, self.message)
+        } else {
+            write!(f, "{}:{}:{}: {}", self.class, self.line, self.column, self.message)
+        }
+    }
+}
+
+impl std::error::Error for JackError {}
+
+pub fn to_vm(commands: &[VmCommand]) -> String {
+    let mut out = String::new();
+    for command in commands {
+        out.push_str(&command.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+// Compiles the source of `class`, which must declare a class of that name
+pub fn compile(class: &str, source: &str) -> Result<Vec<VmCommand>, JackError> {
+    let parsed = parse_class(class, source)?;
+    if parsed.name != class {
+        return Err(JackError {
+            class: class.to_string(),
+            line: parsed.location.line,
+            column: parsed.location.column,
+            message: format!("class {} must be declared in {}.jack", parsed.name, parsed.name)
+        });
+    }
+    compile_class(&parsed)
+}
+
+fn compile_file(path: &Path) -> Result<(PathBuf, String), JackError> {
+    let class = match path.file_stem() {
+        Some(x) => x.to_string_lossy().to_string(),
+        None => path.display().to_string()
+    };
+    let source = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => return Err(JackError { class: path.display().to_string(), line: 0, column: 0, message: e.to_string() })
+    };
+    let commands = compile(&class, &source)?;
+    Ok((path.with_extension("vm"), to_vm(&commands)))
+}
+
+// Compiles a .jack file or every .jack file of a directory, returning the
+// path and contents of every .vm file
+pub fn compile_path<P: AsRef<Path>>(path: P) -> Result<Vec<(PathBuf, String)>, JackError> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(vec![compile_file(path)?]);
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(x) => x,
+        Err(e) => return Err(JackError { class: path.display().to_string(), line: 0, column: 0, message: e.to_string() })
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map(|x| x == "jack").unwrap_or(false))
+        .collect();
+    paths.sort();
+    paths.iter().map(|x| compile_file(x)).collect()
+}
@@ -0,0 +1,364 @@
+use super::ast::*;
+use super::tokenizer::{tokenize, Token, TokenKind};
+use super::JackError;
+
+struct Parser<'a> {
+    class: &'a str,
+    tokens: Vec<Token>,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|x| &x.kind)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.pos + offset).map(|x| &x.kind)
+    }
+
+    fn location(&self) -> Location {
+        match self.tokens.get(self.pos).or_else(|| self.tokens.last()) {
+            Some(x) => Location { line: x.line, column: x.column },
+            None => Location { line: 1, column: 1 }
+        }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, JackError> {
+        let location = self.location();
+        Err(JackError { class: self.class.to_string(), line: location.line, column: location.column, message })
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, JackError> {
+        match self.peek() {
+            Some(x) => self.error(format!("expected {}, found {}", expected, x.describe())),
+            None => self.error(format!("expected {}, found end of file", expected))
+        }
+    }
+
+    fn is_symbol(&self, c: char) -> bool {
+        self.peek() == Some(&TokenKind::Symbol(c))
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(TokenKind::Keyword(x)) if x == keyword)
+    }
+
+    fn symbol(&mut self, c: char) -> Result<(), JackError> {
+        if !self.is_symbol(c) {
+            return self.unexpected(&format!("'{}'", c));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), JackError> {
+        if !self.is_keyword(keyword) {
+            return self.unexpected(&format!("'{}'", keyword));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn identifier(&mut self) -> Result<String, JackError> {
+        match self.peek() {
+            Some(TokenKind::Identifier(x)) => {
+                let x = x.clone();
+                self.pos += 1;
+                Ok(x)
+            },
+            _ => self.unexpected("an identifier")
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<Type, JackError> {
+        let ty = match self.peek() {
+            Some(TokenKind::Keyword(x)) if x == "int" => Type::Int,
+            Some(TokenKind::Keyword(x)) if x == "char" => Type::Char,
+            Some(TokenKind::Keyword(x)) if x == "boolean" => Type::Boolean,
+            Some(TokenKind::Identifier(x)) => Type::Class(x.clone()),
+            _ => return self.unexpected("a type")
+        };
+        self.pos += 1;
+        Ok(ty)
+    }
+
+    // type name (, name)* ;
+    fn parse_names(&mut self) -> Result<(Type, Vec<(String, Location)>), JackError> {
+        let ty = self.parse_type()?;
+        let mut names = Vec::new();
+        loop {
+            let location = self.location();
+            names.push((self.identifier()?, location));
+            if !self.is_symbol(',') {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.symbol(';')?;
+        Ok((ty, names))
+    }
+
+    fn parse_class(&mut self) -> Result<Class, JackError> {
+        self.keyword("class")?;
+        let location = self.location();
+        let name = self.identifier()?;
+        self.symbol('{')?;
+        let mut vars = Vec::new();
+        loop {
+            let kind = if self.is_keyword("static") {
+                ClassVarKind::Static
+            } else if self.is_keyword("field") {
+                ClassVarKind::Field
+            } else {
+                break;
+            };
+            self.pos += 1;
+            let (ty, names) = self.parse_names()?;
+            vars.push(ClassVarDec { kind, ty, names });
+        }
+        let mut subroutines = Vec::new();
+        while !self.is_symbol('}') {
+            subroutines.push(self.parse_subroutine()?);
+        }
+        self.symbol('}')?;
+        if self.peek().is_some() {
+            return self.unexpected("end of file");
+        }
+        Ok(Class { name, vars, subroutines, location })
+    }
+
+    fn parse_subroutine(&mut self) -> Result<Subroutine, JackError> {
+        let location = self.location();
+        let kind = if self.is_keyword("constructor") {
+            SubroutineKind::Constructor
+        } else if self.is_keyword("function") {
+            SubroutineKind::Function
+        } else if self.is_keyword("method") {
+            SubroutineKind::Method
+        } else {
+            return self.unexpected("a subroutine declaration");
+        };
+        self.pos += 1;
+        let return_type = if self.is_keyword("void") {
+            self.pos += 1;
+            None
+        } else {
+            Some(self.parse_type()?)
+        };
+        let name = self.identifier()?;
+        self.symbol('(')?;
+        let mut parameters = Vec::new();
+        if !self.is_symbol(')') {
+            loop {
+                let ty = self.parse_type()?;
+                let location = self.location();
+                parameters.push((ty, self.identifier()?, location));
+                if !self.is_symbol(',') {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+        self.symbol(')')?;
+        self.symbol('{')?;
+        let mut locals = Vec::new();
+        while self.is_keyword("var") {
+            self.pos += 1;
+            let (ty, names) = self.parse_names()?;
+            for (name, location) in names {
+                locals.push((ty.clone(), name, location));
+            }
+        }
+        let body = self.parse_statements()?;
+        self.symbol('}')?;
+        Ok(Subroutine { kind, return_type, name, parameters, locals, body, location })
+    }
+
+    fn parse_statements(&mut self) -> Result<Vec<Statement>, JackError> {
+        let mut out = Vec::new();
+        while !self.is_symbol('}') {
+            out.push(self.parse_statement()?);
+        }
+        Ok(out)
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<Statement>, JackError> {
+        self.symbol('{')?;
+        let out = self.parse_statements()?;
+        self.symbol('}')?;
+        Ok(out)
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, JackError> {
+        let location = self.location();
+        let keyword = match self.peek() {
+            Some(TokenKind::Keyword(x)) => x.clone(),
+            _ => return self.unexpected("a statement")
+        };
+        match keyword.as_str() {
+            "let" => {
+                self.pos += 1;
+                let location = self.location();
+                let name = self.identifier()?;
+                let index = if self.is_symbol('[') {
+                    self.pos += 1;
+                    let index = self.parse_expression()?;
+                    self.symbol(']')?;
+                    Some(index)
+                } else {
+                    None
+                };
+                self.symbol('=')?;
+                let value = self.parse_expression()?;
+                self.symbol(';')?;
+                Ok(Statement::Let { name, index, value, location })
+            },
+            "if" => {
+                self.pos += 1;
+                self.symbol('(')?;
+                let condition = self.parse_expression()?;
+                self.symbol(')')?;
+                let then = self.parse_block()?;
+                let otherwise = if self.is_keyword("else") {
+                    self.pos += 1;
+                    Some(self.parse_block()?)
+                } else {
+                    None
+                };
+                Ok(Statement::If { condition, then, otherwise })
+            },
+            "while" => {
+                self.pos += 1;
+                self.symbol('(')?;
+                let condition = self.parse_expression()?;
+                self.symbol(')')?;
+                let body = self.parse_block()?;
+                Ok(Statement::While { condition, body })
+            },
+            "do" => {
+                self.pos += 1;
+                let call = self.parse_call()?;
+                self.symbol(';')?;
+                Ok(Statement::Do(call))
+            },
+            "return" => {
+                self.pos += 1;
+                let value = if self.is_symbol(';') {
+                    None
+                } else {
+                    Some(self.parse_expression()?)
+                };
+                self.symbol(';')?;
+                Ok(Statement::Return(value, location))
+            },
+            _ => self.unexpected("a statement")
+        }
+    }
+
+    fn parse_call(&mut self) -> Result<SubroutineCall, JackError> {
+        let location = self.location();
+        let first = self.identifier()?;
+        let (target, name) = if self.is_symbol('.') {
+            self.pos += 1;
+            (Some(first), self.identifier()?)
+        } else {
+            (None, first)
+        };
+        self.symbol('(')?;
+        let mut args = Vec::new();
+        if !self.is_symbol(')') {
+            loop {
+                args.push(self.parse_expression()?);
+                if !self.is_symbol(',') {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+        self.symbol(')')?;
+        Ok(SubroutineCall { target, name, args, location })
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, JackError> {
+        let term = self.parse_term()?;
+        let mut ops = Vec::new();
+        while let Some(TokenKind::Symbol(c)) = self.peek() {
+            let op = match BinaryOp::from_symbol(*c) {
+                Some(x) => x,
+                None => break
+            };
+            self.pos += 1;
+            ops.push((op, self.parse_term()?));
+        }
+        Ok(Expression { term, ops })
+    }
+
+    fn parse_term(&mut self) -> Result<Term, JackError> {
+        let location = self.location();
+        let token = match self.peek() {
+            Some(x) => x.clone(),
+            None => return self.unexpected("an expression")
+        };
+        match token {
+            TokenKind::IntConst(x) => {
+                self.pos += 1;
+                Ok(Term::Int(x))
+            },
+            TokenKind::StringConst(x) => {
+                self.pos += 1;
+                Ok(Term::Str(x))
+            },
+            TokenKind::Keyword(x) => {
+                let constant = match x.as_str() {
+                    "true" => KeywordConstant::True,
+                    "false" => KeywordConstant::False,
+                    "null" => KeywordConstant::Null,
+                    "this" => KeywordConstant::This,
+                    _ => return self.unexpected("an expression")
+                };
+                self.pos += 1;
+                Ok(Term::Keyword(constant, location))
+            },
+            TokenKind::Symbol('(') => {
+                self.pos += 1;
+                let expression = self.parse_expression()?;
+                self.symbol(')')?;
+                Ok(Term::Paren(Box::new(expression)))
+            },
+            TokenKind::Symbol('-') => {
+                self.pos += 1;
+                Ok(Term::Unary(UnaryOp::Neg, Box::new(self.parse_term()?)))
+            },
+            TokenKind::Symbol('~') => {
+                self.pos += 1;
+                Ok(Term::Unary(UnaryOp::Not, Box::new(self.parse_term()?)))
+            },
+            TokenKind::Identifier(name) => {
+                match self.peek_at(1) {
+                    Some(TokenKind::Symbol('(')) | Some(TokenKind::Symbol('.')) => {
+                        Ok(Term::Call(self.parse_call()?))
+                    },
+                    Some(TokenKind::Symbol('[')) => {
+                        self.pos += 2;
+                        let index = self.parse_expression()?;
+                        self.symbol(']')?;
+                        Ok(Term::Index(name, Box::new(index), location))
+                    },
+                    _ => {
+                        self.pos += 1;
+                        Ok(Term::Var(name, location))
+                    }
+                }
+            },
+            _ => self.unexpected("an expression")
+        }
+    }
+}
+
+// `class` names the errors, normally the file stem
+pub fn parse_class(class: &str, source: &str) -> Result<Class, JackError> {
+    let tokens = tokenize(class, source)?;
+    let mut parser = Parser { class, tokens, pos: 0 };
+    parser.parse_class()
+}
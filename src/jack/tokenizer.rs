@@ -0,0 +1,140 @@
+use super::JackError;
+
+pub const KEYWORDS: [&str; 21] = [
+    "class", "constructor", "function", "method", "field", "static", "var",
+    "int", "char", "boolean", "void", "true", "false", "null", "this",
+    "let", "do", "if", "else", "while", "return"
+];
+
+pub const SYMBOLS: &str = "{}()[].,;+-*/&|<>=~";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Keyword(String),
+    Symbol(char),
+    Identifier(String),
+    IntConst(u16),
+    StringConst(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize
+}
+
+impl TokenKind {
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Keyword(x) => format!("keyword '{}'", x),
+            TokenKind::Symbol(x) => format!("'{}'", x),
+            TokenKind::Identifier(x) => format!("identifier '{}'", x),
+            TokenKind::IntConst(x) => format!("integer {}", x),
+            TokenKind::StringConst(x) => format!("string \"{}\"", x)
+        }
+    }
+}
+
+pub fn tokenize(class: &str, source: &str) -> Result<Vec<Token>, JackError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut column = 1;
+    let error = |line: usize, column: usize, message: String| JackError { class: class.to_string(), line, column, message };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            column = 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            column += 1;
+            continue;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let (start_line, start_column) = (line, column);
+            i += 2;
+            column += 2;
+            loop {
+                if i >= chars.len() {
+                    return Err(error(start_line, start_column, "unterminated comment".to_string()));
+                }
+                if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    i += 2;
+                    column += 2;
+                    break;
+                }
+                if chars[i] == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+                i += 1;
+            }
+            continue;
+        }
+
+        let start = column;
+        let kind = if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            column += 1;
+            loop {
+                match chars.get(i) {
+                    None | Some('\n') => return Err(error(line, start, "unterminated string".to_string())),
+                    Some('"') => break,
+                    Some(x) => value.push(*x)
+                }
+                i += 1;
+                column += 1;
+            }
+            i += 1;
+            column += 1;
+            TokenKind::StringConst(value)
+        } else if c.is_ascii_digit() {
+            let mut value = String::new();
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                value.push(chars[i]);
+                i += 1;
+                column += 1;
+            }
+            match value.parse::<u16>() {
+                Ok(x) if x <= 32767 => TokenKind::IntConst(x),
+                _ => return Err(error(line, start, format!("integer constant {} is too large", value)))
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut value = String::new();
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                value.push(chars[i]);
+                i += 1;
+                column += 1;
+            }
+            if KEYWORDS.contains(&value.as_str()) {
+                TokenKind::Keyword(value)
+            } else {
+                TokenKind::Identifier(value)
+            }
+        } else if SYMBOLS.contains(c) {
+            i += 1;
+            column += 1;
+            TokenKind::Symbol(c)
+        } else {
+            return Err(error(line, start, format!("unexpected character '{}'", c)));
+        };
+        out.push(Token { kind, line, column: start });
+    }
+    Ok(out)
+}
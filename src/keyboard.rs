@@ -0,0 +1,257 @@
+// Scripted input for the memory mapped keyboard
+//
+// # cycle  action   key or text
+// 100      press    A
+// 400      release  A
+// 500      press    newline
+// 600      release  newline
+// 1000     type     2000 "42\n"
+//
+// Keys are a single character, a name like newline, backspace, left, up,
+// right, down, home, end, pageup, pagedown, insert, delete, esc or f1 to
+// f12, or a number. `type` presses every character of the text for the given
+// number of cycles and then releases it for as long, \n is newline and \b is
+// backspace. The register holds the most recently pressed key that is still
+// down, 0 when none is.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::gates::Gate;
+use crate::hack::{HackEmulator, Lockstep};
+use crate::vm::VmEmulator;
+
+pub const NEW_LINE: u16 = 128;
+pub const BACKSPACE: u16 = 129;
+
+const KEY_NAMES: [(&str, u16); 14] = [
+    ("newline", 128), ("backspace", 129), ("left", 130), ("up", 131),
+    ("right", 132), ("down", 133), ("home", 134), ("end", 135),
+    ("pageup", 136), ("pagedown", 137), ("insert", 138), ("delete", 139),
+    ("esc", 140), ("space", 32)
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyScriptError {
+    pub file: String,
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for KeyScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for KeyScriptError {}
+
+pub fn key_code(name: &str) -> Option<u16> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if (' '..='~').contains(&c) {
+            return Some(c as u16);
+        }
+    }
+    let lower = name.to_ascii_lowercase();
+    if let Some((_, code)) = KEY_NAMES.iter().find(|(x, _)| *x == lower) {
+        return Some(*code);
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|x| x.parse::<u16>().ok()) {
+        if (1..=12).contains(&n) {
+            return Some(140 + n);
+        }
+    }
+    name.parse::<u16>().ok()
+}
+
+// Anything with a keyboard register
+pub trait KeyboardDevice {
+    fn set_key(&mut self, key: u16);
+}
+
+// the memory or computer chip with the builtin keyboard
+impl KeyboardDevice for Gate {
+    fn set_key(&mut self, key: u16) {
+        if let Some(words) = self.find_mut("keyboard").and_then(|x| x.memory_mut()) {
+            words[0] = key;
+        }
+    }
+}
+
+impl KeyboardDevice for HackEmulator {
+    fn set_key(&mut self, key: u16) {
+        HackEmulator::set_key(self, key);
+    }
+}
+
+impl KeyboardDevice for VmEmulator {
+    fn set_key(&mut self, key: u16) {
+        VmEmulator::set_key(self, key);
+    }
+}
+
+impl KeyboardDevice for Lockstep {
+    fn set_key(&mut self, key: u16) {
+        Lockstep::set_key(self, key);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyEvent {
+    pub cycle: u64,
+    pub key: u16,
+    pub pressed: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyScript {
+    events: Vec<KeyEvent>,
+    next: usize,
+    held: Vec<u16>
+}
+
+// # starts a comment outside of quotes, a # key is written as 35
+fn strip_comment(text: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '#' if !quoted => return &text[..i],
+            '"' if !escaped => quoted = !quoted,
+            _ => {}
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    text
+}
+
+fn parse_text(text: &str) -> Option<Vec<u16>> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let code = match c {
+            '\\' => match chars.next()? {
+                'n' => NEW_LINE,
+                'b' => BACKSPACE,
+                '\\' => '\\' as u16,
+                '"' => '"' as u16,
+                _ => return None
+            },
+            ' '..='~' => c as u16,
+            _ => return None
+        };
+        out.push(code);
+    }
+    Some(out)
+}
+
+impl KeyScript {
+    pub fn new(mut events: Vec<KeyEvent>) -> KeyScript {
+        events.sort_by_key(|x| x.cycle);
+        KeyScript { events, next: 0, held: Vec::new() }
+    }
+
+    pub fn parse(file: &str, source: &str) -> Result<KeyScript, KeyScriptError> {
+        let mut events = Vec::new();
+        for (i, text) in source.lines().enumerate() {
+            let error = |message: String| KeyScriptError { file: file.to_string(), line: i + 1, message };
+            let text = strip_comment(text);
+            let words: Vec<&str> = text.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let cycle = match words[0].parse::<u64>() {
+                Ok(x) => x,
+                Err(_) => return Err(error(format!("invalid cycle '{}'", words[0])))
+            };
+            match words.get(1).copied() {
+                Some(action @ ("press" | "release")) => {
+                    if words.len() != 3 {
+                        return Err(error(format!("'{}' expects a key", action)));
+                    }
+                    let key = match key_code(words[2]) {
+                        Some(x) if x != 0 => x,
+                        _ => return Err(error(format!("unknown key '{}'", words[2])))
+                    };
+                    events.push(KeyEvent { cycle, key, pressed: action == "press" });
+                },
+                Some("type") => {
+                    let hold = match words.get(2).and_then(|x| x.parse::<u64>().ok()) {
+                        Some(x) if x > 0 => x,
+                        _ => return Err(error("'type' expects a number of cycles and a text".to_string()))
+                    };
+                    // the text may contain spaces
+                    let start = text.find('"').unwrap_or(text.len());
+                    let keys = match parse_text(text[start..].trim()) {
+                        Some(x) => x,
+                        None => return Err(error("invalid text, expected \"...\"".to_string()))
+                    };
+                    for (j, key) in keys.iter().enumerate() {
+                        let at = cycle + 2 * hold * j as u64;
+                        events.push(KeyEvent { cycle: at, key: *key, pressed: true });
+                        events.push(KeyEvent { cycle: at + hold, key: *key, pressed: false });
+                    }
+                },
+                Some(x) => return Err(error(format!("unknown action '{}'", x))),
+                None => return Err(error("expected an action".to_string()))
+            }
+        }
+        Ok(KeyScript::new(events))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<KeyScript, KeyScriptError> {
+        let path = path.as_ref();
+        let file = path.display().to_string();
+        match fs::read_to_string(path) {
+            Ok(source) => KeyScript::parse(&file, &source),
+            Err(e) => Err(KeyScriptError { file, line: 0, message: e.to_string() })
+        }
+    }
+
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next == self.events.len()
+    }
+
+    // value of the keyboard register
+    pub fn key(&self) -> u16 {
+        self.held.last().copied().unwrap_or(0)
+    }
+
+    // Applies the events up to `cycle`, returns the new register value if
+    // any event happened
+    pub fn advance(&mut self, cycle: u64) -> Option<u16> {
+        let start = self.next;
+        while let Some(event) = self.events.get(self.next) {
+            if event.cycle > cycle {
+                break;
+            }
+            self.held.retain(|x| *x != event.key);
+            if event.pressed {
+                self.held.push(event.key);
+            }
+            self.next += 1;
+        }
+        if self.next == start {
+            return None;
+        }
+        Some(self.key())
+    }
+
+    // Call before running cycle `cycle` + 1
+    pub fn drive<D: KeyboardDevice + ?Sized>(&mut self, cycle: u64, device: &mut D) {
+        if let Some(key) = self.advance(cycle) {
+            device.set_key(key);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod gates;
+pub mod assembler;
+pub mod hack;
+pub mod keyboard;
+pub mod screen;
+pub mod vm;
+pub mod jack;
@@ -1,14 +1,74 @@
-use gates::{GateFactory, PinValues};
+use std::env;
+use std::path::Path;
+use std::process;
+use sunho_computer::gates::{load_script, ChipStats, GateFactory, ScriptError, ScriptRunner};
 
-mod gates;
+// Runs a test script with the files it names next to it, printing what it
+// echoes
+fn run_script(path: &str) -> Result<(), ScriptError> {
+    let script = load_script(path)?;
+    let dir = Path::new(path).parent().map(|x| x.to_path_buf()).unwrap_or_default();
+    let mut factory = GateFactory::with_builtin_memory();
+    let mut runner = ScriptRunner::new(&mut factory, dir);
+    let result = runner.run(&script);
+    for text in runner.echoes() {
+        println!("{}", text);
+    }
+    result.map(|_| ())
+}
 
+// Prints the cost and depth of registered chips
+fn print_stats(chips: &[String]) -> bool {
+    let factory = GateFactory::with_builtin_memory();
+    let mut failed = false;
+    for chip in chips {
+        let name = chip.to_lowercase();
+        if !factory.contains(&name) {
+            eprintln!("{}: no such chip", chip);
+            failed = true;
+            continue;
+        }
+        match ChipStats::new(&factory.build(&name)) {
+            Ok(stats) => print!("{}", stats),
+            Err(e) => {
+                eprintln!("{}: {}", chip, e);
+                failed = true;
+            }
+        }
+    }
+    failed
+}
 
+// usage: sunho-computer [file.tst ...]
+//        sunho-computer --stats [chip ...]
 fn main() {
-    let factory = GateFactory::new();
-    let mut i = factory.build("or");
-    let mut inputs = PinValues::new();
-    inputs.set_binary("a", "0");
-    inputs.set_binary("b", "0");
-    let o = i.run(inputs);
-    println!("{}", o);
-}   
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let stats = args.first().map(|x| x == "--stats").unwrap_or(false);
+    if stats {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        eprintln!("usage: sunho-computer <file.tst>...");
+        eprintln!("       sunho-computer --stats <chip>...");
+        process::exit(2);
+    }
+    if stats {
+        if print_stats(&args) {
+            process::exit(1);
+        }
+        return;
+    }
+    let mut failed = false;
+    for script in &args {
+        match run_script(script) {
+            Ok(_) => println!("{}: End of script - Comparison ended successfully", script),
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,233 @@
+// Images of the 512x256 Hack screen
+//
+// Word i of the screen holds pixels 16 * (i % 32) to 16 * (i % 32) + 15 of
+// row i / 32, bit 0 is the leftmost pixel and 1 is black. Frames are written
+// as binary PBM or as 1 bit grayscale PNG without compression.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::gates::Gate;
+use crate::hack::HackEmulator;
+use crate::vm::VmEmulator;
+
+pub const WIDTH: usize = 512;
+pub const HEIGHT: usize = 256;
+pub const WORDS: usize = WIDTH * HEIGHT / 16;
+
+// Anything with a screen: the memory or computer chip with the builtin
+// screen, or one of the emulators
+pub trait ScreenSource {
+    fn screen_words(&self) -> Option<&[u16]>;
+}
+
+impl ScreenSource for Gate {
+    fn screen_words(&self) -> Option<&[u16]> {
+        self.find("screen").and_then(|x| x.memory())
+    }
+}
+
+impl ScreenSource for HackEmulator {
+    fn screen_words(&self) -> Option<&[u16]> {
+        Some(self.screen())
+    }
+}
+
+impl ScreenSource for VmEmulator {
+    fn screen_words(&self) -> Option<&[u16]> {
+        Some(self.screen())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    words: Vec<u16>
+}
+
+impl Frame {
+    // missing words are white
+    pub fn new(words: &[u16]) -> Frame {
+        let mut out = vec![0; WORDS];
+        let size = words.len().min(WORDS);
+        out[..size].copy_from_slice(&words[..size]);
+        Frame { words: out }
+    }
+
+    pub fn capture<S: ScreenSource + ?Sized>(source: &S) -> Option<Frame> {
+        source.screen_words().map(Frame::new)
+    }
+
+    pub fn words(&self) -> &[u16] {
+        &self.words
+    }
+
+    // true for black
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        (self.words[y * 32 + x / 16] >> (x % 16)) & 1 == 1
+    }
+
+    pub fn count_black(&self) -> usize {
+        self.words.iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    // rows of 64 bytes, the leftmost pixel in the high bit
+    fn packed_rows(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        self.words.chunks(32).map(|row| {
+            row.iter().flat_map(|word| [(*word as u8).reverse_bits(), ((*word >> 8) as u8).reverse_bits()]).collect()
+        })
+    }
+
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut out = format!("P4\n{} {}\n", WIDTH, HEIGHT).into_bytes();
+        for row in self.packed_rows() {
+            out.extend_from_slice(&row);
+        }
+        out
+    }
+
+    // Reads a binary PBM of the size of the screen
+    pub fn from_pbm(data: &[u8]) -> Option<Frame> {
+        // magic, width and height separated by whitespace, comments start
+        // with # and run to the end of the line
+        let mut fields = Vec::new();
+        let mut i = 0;
+        while fields.len() < 3 {
+            while i < data.len() && (data[i].is_ascii_whitespace() || data[i] == b'#') {
+                if data[i] == b'#' {
+                    while i < data.len() && data[i] != b'\n' {
+                        i += 1;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+            let start = i;
+            while i < data.len() && !data[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            if start == i {
+                return None;
+            }
+            fields.push(String::from_utf8_lossy(&data[start..i]).to_string());
+        }
+        if fields[0] != "P4" || fields[1] != WIDTH.to_string() || fields[2] != HEIGHT.to_string() {
+            return None;
+        }
+        let pixels = data.get(i + 1..i + 1 + WIDTH * HEIGHT / 8)?;
+        let words = pixels
+            .chunks(2)
+            .map(|x| x[0].reverse_bits() as u16 | (x[1].reverse_bits() as u16) << 8)
+            .collect();
+        Some(Frame { words })
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        // a filter byte before every row, 0 is black in grayscale
+        let mut raw = Vec::with_capacity(HEIGHT * (WIDTH / 8 + 1));
+        for row in self.packed_rows() {
+            raw.push(0);
+            raw.extend(row.iter().map(|x| !x));
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&(WIDTH as u32).to_be_bytes());
+        header.extend_from_slice(&(HEIGHT as u32).to_be_bytes());
+        // bit depth 1, grayscale, deflate, no filtering, no interlace
+        header.extend_from_slice(&[1, 0, 0, 0, 0]);
+
+        let mut out = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        png_chunk(&mut out, b"IHDR", &header);
+        png_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    // PNG if the extension is .png, PBM otherwise
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let data = if path.extension().map(|x| x == "png").unwrap_or(false) {
+            self.to_png()
+        } else {
+            self.to_pbm()
+        };
+        fs::write(path, data)
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+// zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = data.chunks(65535).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        out.push(if i + 1 == blocks.len() { 1 } else { 0 });
+        let size = block.len() as u16;
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&(!size).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Saves a frame every `every` cycles as directory/frame_<cycle>.<extension>
+pub struct FrameDumper {
+    directory: PathBuf,
+    extension: String,
+    every: u64
+}
+
+impl FrameDumper {
+    // `extension` is png or pbm
+    pub fn new<P: AsRef<Path>>(directory: P, extension: &str, every: u64) -> io::Result<FrameDumper> {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(FrameDumper { directory: directory.as_ref().to_path_buf(), extension: extension.to_string(), every: every.max(1) })
+    }
+
+    pub fn path(&self, cycle: u64) -> PathBuf {
+        self.directory.join(format!("frame_{:08}.{}", cycle, self.extension))
+    }
+
+    // Call after every cycle, returns the path if a frame was saved
+    pub fn cycle<S: ScreenSource + ?Sized>(&mut self, cycle: u64, source: &S) -> io::Result<Option<PathBuf>> {
+        if !cycle.is_multiple_of(self.every) {
+            return Ok(None);
+        }
+        let frame = match Frame::capture(source) {
+            Some(x) => x,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "no screen to capture"))
+        };
+        let path = self.path(cycle);
+        frame.save(&path)?;
+        Ok(Some(path))
+    }
+}
@@ -0,0 +1,534 @@
+// Interpreter for VM code
+//
+// Memory is laid out like on the Hack computer: SP, LCL, ARG, THIS, THAT in
+// RAM[0..5], temp in RAM[5..13], statics from 16, the stack from 256, the
+// heap from 2048, the screen at 16384 and the keyboard at 24576. Classes of
+// the Jack OS that are not loaded from .vm files are provided by os.rs.
+//
+// A native function can ask to be called again on the next step instead of
+// returning (Keyboard.readChar waiting for a key), so the emulator never
+// blocks and keyboard input can be changed between steps.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use super::os::{self, NativeFunction, NativeResult, OsState};
+use super::{load_vm_path, ArithmeticOp, Segment, VmCommand, VmError, VmFile};
+use crate::jack;
+
+pub const STACK_BASE: u16 = 256;
+pub const STATIC_BASE: u16 = 16;
+pub const HEAP_BASE: u16 = 2048;
+pub const SCREEN: usize = 16384;
+pub const SCREEN_WORDS: usize = 8192;
+pub const KEYBOARD: usize = 24576;
+
+const SP: usize = 0;
+const LCL: usize = 1;
+const ARG: usize = 2;
+const THIS: usize = 3;
+const THAT: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Constant(u16),
+    // temp, pointer and static
+    Fixed(u16),
+    // RAM[register] + offset
+    Based(usize, u16)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Instruction {
+    Push(Operand),
+    Pop(Operand),
+    Arithmetic(ArithmeticOp),
+    Goto(usize),
+    IfGoto(usize),
+    Function(u16),
+    Call(usize, u16),
+    Return
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+    Vm(usize),
+    Native(NativeFunction, u16),
+    Undefined
+}
+
+struct Function {
+    name: String,
+    target: Target
+}
+
+struct Frame {
+    function: usize,
+    return_pc: usize
+}
+
+pub struct VmEmulator {
+    ram: Vec<u16>,
+    program: Vec<Instruction>,
+    // file and line of every instruction
+    origins: Vec<(String, usize)>,
+    functions: Vec<Function>,
+    function_ids: BTreeMap<String, usize>,
+    frames: Vec<Frame>,
+    pc: usize,
+    steps: u64,
+    halted: bool,
+    exit_code: Option<i16>,
+    pub(crate) os: OsState
+}
+
+fn class_of(function: &str) -> &str {
+    match function.find('.') {
+        Some(i) => &function[..i],
+        None => function
+    }
+}
+
+impl VmEmulator {
+    // Loads the files and every OS class they do not define. Execution starts
+    // at the first command of the first file, call boot to start at Sys.init.
+    pub fn new(files: &[VmFile]) -> Result<VmEmulator, VmError> {
+        let mut classes = BTreeSet::new();
+        for file in files {
+            classes.insert(file.name.clone());
+            for (_, command) in &file.commands {
+                if let VmCommand::Function(name, _) = command {
+                    classes.insert(class_of(name).to_string());
+                }
+            }
+        }
+
+        // OS code goes first so that execution can start at the user code
+        let mut all: Vec<VmFile> = Vec::new();
+        for (class, source) in os::JACK_SOURCES.iter() {
+            if classes.contains(*class) {
+                continue;
+            }
+            let commands = match jack::compile(class, source) {
+                Ok(x) => x,
+                Err(e) => return Err(VmError { file: format!("{}.jack", class), line: e.line, message: e.message })
+            };
+            let commands = commands.into_iter().enumerate().map(|(i, x)| (i + 1, x)).collect();
+            all.push(VmFile { name: class.to_string(), commands });
+        }
+
+        let mut out = VmEmulator {
+            ram: vec![0; KEYBOARD + 1],
+            program: Vec::new(),
+            origins: Vec::new(),
+            functions: Vec::new(),
+            function_ids: BTreeMap::new(),
+            frames: Vec::new(),
+            pc: 0,
+            steps: 0,
+            halted: false,
+            exit_code: None,
+            os: OsState::default()
+        };
+        for (name, args, function) in os::natives() {
+            if !classes.contains(class_of(name)) {
+                out.function_ids.insert(name.to_string(), out.functions.len());
+                out.functions.push(Function { name: name.to_string(), target: Target::Native(function, args) });
+            }
+        }
+        let start = all.iter().map(|x| x.commands.iter().filter(|(_, x)| !matches!(x, VmCommand::Label(_))).count()).sum();
+        all.extend_from_slice(files);
+        out.load(&all)?;
+        out.pc = start;
+        Ok(out)
+    }
+
+    pub fn load_path<P: AsRef<Path>>(path: P) -> Result<VmEmulator, VmError> {
+        VmEmulator::new(&load_vm_path(path)?)
+    }
+
+    fn function_id(&mut self, name: &str) -> usize {
+        if let Some(x) = self.function_ids.get(name) {
+            return *x;
+        }
+        self.function_ids.insert(name.to_string(), self.functions.len());
+        self.functions.push(Function { name: name.to_string(), target: Target::Undefined });
+        self.functions.len() - 1
+    }
+
+    fn load(&mut self, files: &[VmFile]) -> Result<(), VmError> {
+        // labels are scoped by function like in the translator
+        let mut labels: BTreeMap<(String, String), usize> = BTreeMap::new();
+        let mut address = 0;
+        for file in files {
+            let mut scope = file.name.clone();
+            for (line, command) in &file.commands {
+                match command {
+                    VmCommand::Label(label) => {
+                        if labels.insert((scope.clone(), label.clone()), address).is_some() {
+                            return Err(VmError { file: file.name.clone(), line: *line, message: format!("label '{}' is already defined", label) });
+                        }
+                        continue;
+                    },
+                    VmCommand::Function(name, _) => {
+                        scope = name.clone();
+                        let id = self.function_id(name);
+                        if let Target::Vm(_) = self.functions[id].target {
+                            return Err(VmError { file: file.name.clone(), line: *line, message: format!("function '{}' is already defined", name) });
+                        }
+                        self.functions[id].target = Target::Vm(address);
+                    },
+                    _ => {}
+                }
+                address += 1;
+            }
+        }
+
+        let mut static_base = STATIC_BASE;
+        for file in files {
+            let mut scope = file.name.clone();
+            let mut statics = 0;
+            for (line, command) in &file.commands {
+                let error = |message: String| VmError { file: file.name.clone(), line: *line, message };
+                let operand = |segment: Segment, index: u16| -> Operand {
+                    match segment {
+                        Segment::Constant => Operand::Constant(index),
+                        Segment::Local => Operand::Based(LCL, index),
+                        Segment::Argument => Operand::Based(ARG, index),
+                        Segment::This => Operand::Based(THIS, index),
+                        Segment::That => Operand::Based(THAT, index),
+                        Segment::Pointer => Operand::Fixed(3 + index),
+                        Segment::Temp => Operand::Fixed(5 + index),
+                        Segment::Static => Operand::Fixed(static_base + index)
+                    }
+                };
+                let label = |label: &str| -> Result<usize, VmError> {
+                    match labels.get(&(scope.clone(), label.to_string())) {
+                        Some(x) => Ok(*x),
+                        None => Err(error(format!("undefined label '{}'", label)))
+                    }
+                };
+                let instruction = match command {
+                    VmCommand::Label(_) => continue,
+                    VmCommand::Arithmetic(op) => Instruction::Arithmetic(*op),
+                    VmCommand::Push(segment, index) | VmCommand::Pop(segment, index) => {
+                        if *segment == Segment::Static {
+                            statics = statics.max(index + 1);
+                            if static_base as usize + *index as usize >= STACK_BASE as usize {
+                                return Err(error("too many static variables".to_string()));
+                            }
+                        }
+                        match command {
+                            VmCommand::Push(..) => Instruction::Push(operand(*segment, *index)),
+                            _ => Instruction::Pop(operand(*segment, *index))
+                        }
+                    },
+                    VmCommand::Goto(x) => Instruction::Goto(label(x)?),
+                    VmCommand::IfGoto(x) => Instruction::IfGoto(label(x)?),
+                    VmCommand::Function(name, locals) => {
+                        scope = name.clone();
+                        Instruction::Function(*locals)
+                    },
+                    VmCommand::Call(name, args) => Instruction::Call(self.function_id(name), *args),
+                    VmCommand::Return => Instruction::Return
+                };
+                self.program.push(instruction);
+                self.origins.push((file.name.clone(), *line));
+            }
+            static_base += statics;
+        }
+        Ok(())
+    }
+
+    // SP = 256 and calls Sys.init
+    pub fn boot(&mut self) -> Result<(), VmError> {
+        self.ram[SP] = STACK_BASE;
+        let id = self.function_id("Sys.init");
+        match self.functions[id].target {
+            Target::Vm(address) => {
+                self.enter(id, 0, self.program.len());
+                self.pc = address;
+                Ok(())
+            },
+            _ => Err(VmError { file: String::new(), line: 0, message: "Sys.init is not defined".to_string() })
+        }
+    }
+
+    pub fn ram(&self) -> &[u16] {
+        &self.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u16] {
+        &mut self.ram
+    }
+
+    pub fn screen(&self) -> &[u16] {
+        &self.ram[SCREEN..SCREEN + SCREEN_WORDS]
+    }
+
+    // scan code of the key held down, 0 for none
+    pub fn set_key(&mut self, key: u16) {
+        self.ram[KEYBOARD] = key;
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    // code passed to Sys.error
+    pub fn exit_code(&self) -> Option<i16> {
+        self.exit_code
+    }
+
+    // names of the functions being executed, the innermost last
+    pub fn call_stack(&self) -> Vec<&str> {
+        self.frames.iter().map(|frame| self.functions[frame.function].name.as_str()).collect()
+    }
+
+    pub(crate) fn halt(&mut self, code: Option<i16>) {
+        self.halted = true;
+        if code.is_some() {
+            self.exit_code = code;
+        }
+    }
+
+    pub(crate) fn read(&self, address: u16) -> Result<u16, VmError> {
+        match self.ram.get(address as usize) {
+            Some(x) => Ok(*x),
+            None => Err(self.error(format!("illegal memory access at {}", address)))
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, value: u16) -> Result<(), VmError> {
+        if address as usize >= KEYBOARD {
+            return Err(self.error(format!("illegal memory access at {}", address)));
+        }
+        self.ram[address as usize] = value;
+        Ok(())
+    }
+
+    pub(crate) fn error(&self, message: String) -> VmError {
+        let (file, line) = match self.origins.get(self.pc) {
+            Some((file, line)) => (file.clone(), *line),
+            None => (String::new(), 0)
+        };
+        let stack = self.call_stack();
+        let message = if stack.is_empty() {
+            message
+        } else {
+            format!("{} (in {})", message, stack.join(" > "))
+        };
+        VmError { file, line, message }
+    }
+
+    fn push(&mut self, value: u16) -> Result<(), VmError> {
+        let sp = self.ram[SP];
+        if sp >= HEAP_BASE {
+            return Err(self.error("stack overflow".to_string()));
+        }
+        self.ram[sp as usize] = value;
+        self.ram[SP] = sp + 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u16, VmError> {
+        let sp = self.ram[SP];
+        if sp == 0 {
+            return Err(self.error("stack underflow".to_string()));
+        }
+        self.ram[SP] = sp - 1;
+        self.read(sp - 1)
+    }
+
+    fn address(&self, operand: Operand) -> u16 {
+        match operand {
+            Operand::Constant(x) => x,
+            Operand::Fixed(x) => x,
+            Operand::Based(register, offset) => self.ram[register].wrapping_add(offset)
+        }
+    }
+
+    // pushes the frame of the caller, the arguments are already on the stack
+    fn enter(&mut self, function: usize, args: u16, return_pc: usize) {
+        let sp = self.ram[SP];
+        let saved = [return_pc as u16, self.ram[LCL], self.ram[ARG], self.ram[THIS], self.ram[THAT]];
+        for (i, value) in saved.iter().enumerate() {
+            self.ram[sp as usize + i] = *value;
+        }
+        self.ram[ARG] = sp.wrapping_sub(args);
+        self.ram[LCL] = sp + 5;
+        self.ram[SP] = sp + 5;
+        self.frames.push(Frame { function, return_pc });
+    }
+
+    // Calls a function from native code and runs it to completion
+    pub fn call_function(&mut self, name: &str, args: &[u16]) -> Result<u16, VmError> {
+        let id = self.function_id(name);
+        match self.functions[id].target {
+            Target::Native(function, count) => {
+                if count as usize != args.len() {
+                    return Err(self.error(format!("{} expects {} arguments, got {}", name, count, args.len())));
+                }
+                match function(self, args)? {
+                    NativeResult::Return(x) => Ok(x),
+                    NativeResult::Wait => Err(self.error(format!("{} cannot wait when called from a native function", name)))
+                }
+            },
+            Target::Vm(address) => {
+                for arg in args {
+                    self.push(*arg)?;
+                }
+                if self.ram[SP] as usize + 5 > HEAP_BASE as usize {
+                    return Err(self.error("stack overflow".to_string()));
+                }
+                let depth = self.frames.len();
+                self.enter(id, args.len() as u16, self.pc);
+                self.pc = address;
+                while self.frames.len() > depth && !self.halted {
+                    self.step()?;
+                }
+                if self.halted {
+                    return Ok(0);
+                }
+                self.pop()
+            },
+            Target::Undefined => Err(self.error(format!("undefined function '{}'", name)))
+        }
+    }
+
+    // Runs until the program halts or `steps` commands were executed,
+    // returns the number of steps taken
+    pub fn run(&mut self, steps: u64) -> Result<u64, VmError> {
+        let start = self.steps;
+        while !self.halted && self.steps - start < steps {
+            self.step()?;
+        }
+        Ok(self.steps - start)
+    }
+
+    pub fn step(&mut self) -> Result<(), VmError> {
+        if self.halted {
+            return Ok(());
+        }
+        let instruction = match self.program.get(self.pc) {
+            Some(x) => *x,
+            None => {
+                self.halted = true;
+                return Ok(());
+            }
+        };
+        self.steps += 1;
+        match instruction {
+            Instruction::Push(operand) => {
+                let value = match operand {
+                    Operand::Constant(x) => x,
+                    _ => self.read(self.address(operand))?
+                };
+                self.push(value)?;
+            },
+            Instruction::Pop(operand) => {
+                let value = self.pop()?;
+                self.write(self.address(operand), value)?;
+            },
+            Instruction::Arithmetic(op) => self.arithmetic(op)?,
+            Instruction::Goto(address) => {
+                self.pc = address;
+                return Ok(());
+            },
+            Instruction::IfGoto(address) => {
+                if self.pop()? != 0 {
+                    self.pc = address;
+                    return Ok(());
+                }
+            },
+            Instruction::Function(locals) => {
+                for _ in 0..locals {
+                    self.push(0)?;
+                }
+            },
+            Instruction::Call(function, args) => return self.call(function, args),
+            Instruction::Return => return self.ret()
+        }
+        self.pc += 1;
+        Ok(())
+    }
+
+    fn arithmetic(&mut self, op: ArithmeticOp) -> Result<(), VmError> {
+        let boolean = |x: bool| if x { 0xffff } else { 0 };
+        let value = match op {
+            ArithmeticOp::Neg => (self.pop()? as i16).wrapping_neg() as u16,
+            ArithmeticOp::Not => !self.pop()?,
+            _ => {
+                let y = self.pop()?;
+                let x = self.pop()?;
+                match op {
+                    ArithmeticOp::Add => x.wrapping_add(y),
+                    ArithmeticOp::Sub => x.wrapping_sub(y),
+                    ArithmeticOp::And => x & y,
+                    ArithmeticOp::Or => x | y,
+                    ArithmeticOp::Eq => boolean(x == y),
+                    ArithmeticOp::Gt => boolean((x as i16) > (y as i16)),
+                    _ => boolean((x as i16) < (y as i16))
+                }
+            }
+        };
+        self.push(value)
+    }
+
+    fn call(&mut self, function: usize, args: u16) -> Result<(), VmError> {
+        match self.functions[function].target {
+            Target::Vm(address) => {
+                if self.ram[SP] as usize + 5 > HEAP_BASE as usize {
+                    return Err(self.error("stack overflow".to_string()));
+                }
+                self.enter(function, args, self.pc + 1);
+                self.pc = address;
+            },
+            Target::Native(native, count) => {
+                if count != args {
+                    let name = self.functions[function].name.clone();
+                    return Err(self.error(format!("{} expects {} arguments, got {}", name, count, args)));
+                }
+                let sp = self.ram[SP];
+                if sp < args {
+                    return Err(self.error("stack underflow".to_string()));
+                }
+                let values: Vec<u16> = self.ram[(sp - args) as usize..sp as usize].to_vec();
+                if let NativeResult::Return(x) = native(self, &values)? {
+                    self.ram[SP] = sp - args;
+                    self.push(x)?;
+                    self.pc += 1;
+                }
+            },
+            Target::Undefined => {
+                let name = self.functions[function].name.clone();
+                return Err(self.error(format!("undefined function '{}'", name)));
+            }
+        }
+        Ok(())
+    }
+
+    fn ret(&mut self) -> Result<(), VmError> {
+        let frame = self.ram[LCL];
+        if frame < 5 {
+            return Err(self.error("return outside of a function".to_string()));
+        }
+        let value = self.pop()?;
+        let arg = self.ram[ARG];
+        self.write(arg, value)?;
+        self.ram[SP] = arg + 1;
+        self.ram[THAT] = self.read(frame - 1)?;
+        self.ram[THIS] = self.read(frame - 2)?;
+        self.ram[ARG] = self.read(frame - 3)?;
+        self.ram[LCL] = self.read(frame - 4)?;
+        match self.frames.pop() {
+            Some(x) => self.pc = x.return_pc,
+            None => self.halted = true
+        }
+        Ok(())
+    }
+}
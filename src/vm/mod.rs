@@ -0,0 +1,303 @@
+// Stack VM of nand2tetris
+//
+// push constant 7
+// push local 0
+// add
+// pop static 1
+// label LOOP, goto LOOP, if-goto LOOP
+// function Main.main 2, call Math.multiply 2, return
+
+mod translator;
+mod emulator;
+mod os;
+
+pub use translator::{translate, translate_path, Translator};
+pub use emulator::VmEmulator;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.file.is_empty() {
+            write!(f, "{}", self.message)
+        } else if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Argument,
+    Local,
+    Static,
+    Constant,
+    This,
+    That,
+    Pointer,
+    Temp
+}
+
+impl Segment {
+    pub fn from_name(name: &str) -> Option<Segment> {
+        let segment = match name {
+            "argument" => Segment::Argument,
+            "local" => Segment::Local,
+            "static" => Segment::Static,
+            "constant" => Segment::Constant,
+            "this" => Segment::This,
+            "that" => Segment::That,
+            "pointer" => Segment::Pointer,
+            "temp" => Segment::Temp,
+            _ => return None
+        };
+        Some(segment)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Segment::Argument => "argument",
+            Segment::Local => "local",
+            Segment::Static => "static",
+            Segment::Constant => "constant",
+            Segment::This => "this",
+            Segment::That => "that",
+            Segment::Pointer => "pointer",
+            Segment::Temp => "temp"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not
+}
+
+impl ArithmeticOp {
+    pub fn from_name(name: &str) -> Option<ArithmeticOp> {
+        let op = match name {
+            "add" => ArithmeticOp::Add,
+            "sub" => ArithmeticOp::Sub,
+            "neg" => ArithmeticOp::Neg,
+            "eq" => ArithmeticOp::Eq,
+            "gt" => ArithmeticOp::Gt,
+            "lt" => ArithmeticOp::Lt,
+            "and" => ArithmeticOp::And,
+            "or" => ArithmeticOp::Or,
+            "not" => ArithmeticOp::Not,
+            _ => return None
+        };
+        Some(op)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArithmeticOp::Add => "add",
+            ArithmeticOp::Sub => "sub",
+            ArithmeticOp::Neg => "neg",
+            ArithmeticOp::Eq => "eq",
+            ArithmeticOp::Gt => "gt",
+            ArithmeticOp::Lt => "lt",
+            ArithmeticOp::And => "and",
+            ArithmeticOp::Or => "or",
+            ArithmeticOp::Not => "not"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmCommand {
+    Arithmetic(ArithmeticOp),
+    Push(Segment, u16),
+    Pop(Segment, u16),
+    Label(String),
+    Goto(String),
+    IfGoto(String),
+    Function(String, u16),
+    Call(String, u16),
+    Return
+}
+
+impl fmt::Display for VmCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmCommand::Arithmetic(op) => write!(f, "{}", op.name()),
+            VmCommand::Push(segment, index) => write!(f, "push {} {}", segment.name(), index),
+            VmCommand::Pop(segment, index) => write!(f, "pop {} {}", segment.name(), index),
+            VmCommand::Label(label) => write!(f, "label {}", label),
+            VmCommand::Goto(label) => write!(f, "goto {}", label),
+            VmCommand::IfGoto(label) => write!(f, "if-goto {}", label),
+            VmCommand::Function(name, locals) => write!(f, "function {} {}", name, locals),
+            VmCommand::Call(name, args) => write!(f, "call {} {}", name, args),
+            VmCommand::Return => write!(f, "return")
+        }
+    }
+}
+
+// One .vm file, `name` is the file stem which names its static variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmFile {
+    pub name: String,
+    pub commands: Vec<(usize, VmCommand)>
+}
+
+fn is_identifier(name: &str) -> bool {
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => {},
+        _ => return false
+    }
+    name.chars().all(|c| c.is_ascii_alphanumeric() || "_.$:".contains(c))
+}
+
+pub fn parse_command(file: &str, line: usize, text: &str) -> Result<Option<VmCommand>, VmError> {
+    let error = |message: String| VmError { file: file.to_string(), line, message };
+    let text = match text.find("//") {
+        Some(i) => &text[..i],
+        None => text
+    };
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Ok(None);
+    }
+    let expect = |count: usize| -> Result<(), VmError> {
+        if words.len() != count {
+            return Err(error(format!("'{}' expects {} arguments", words[0], count - 1)));
+        }
+        Ok(())
+    };
+    let number = |word: &str| -> Result<u16, VmError> {
+        match word.parse::<u16>() {
+            Ok(x) => Ok(x),
+            Err(_) => Err(error(format!("invalid number '{}'", word)))
+        }
+    };
+    let identifier = |word: &str| -> Result<String, VmError> {
+        if !is_identifier(word) {
+            return Err(error(format!("invalid name '{}'", word)));
+        }
+        Ok(word.to_string())
+    };
+
+    if let Some(op) = ArithmeticOp::from_name(words[0]) {
+        expect(1)?;
+        return Ok(Some(VmCommand::Arithmetic(op)));
+    }
+    let command = match words[0] {
+        "push" | "pop" => {
+            expect(3)?;
+            let segment = match Segment::from_name(words[1]) {
+                Some(x) => x,
+                None => return Err(error(format!("unknown segment '{}'", words[1])))
+            };
+            let index = number(words[2])?;
+            let limit = match segment {
+                Segment::Pointer => Some(2),
+                Segment::Temp => Some(8),
+                Segment::Constant => Some(0x8000),
+                _ => None
+            };
+            if let Some(limit) = limit {
+                if index >= limit {
+                    return Err(error(format!("index {} is out of range for {}", index, segment.name())));
+                }
+            }
+            if words[0] == "push" {
+                VmCommand::Push(segment, index)
+            } else {
+                if segment == Segment::Constant {
+                    return Err(error("cannot pop to constant".to_string()));
+                }
+                VmCommand::Pop(segment, index)
+            }
+        },
+        "label" => {
+            expect(2)?;
+            VmCommand::Label(identifier(words[1])?)
+        },
+        "goto" => {
+            expect(2)?;
+            VmCommand::Goto(identifier(words[1])?)
+        },
+        "if-goto" => {
+            expect(2)?;
+            VmCommand::IfGoto(identifier(words[1])?)
+        },
+        "function" => {
+            expect(3)?;
+            VmCommand::Function(identifier(words[1])?, number(words[2])?)
+        },
+        "call" => {
+            expect(3)?;
+            VmCommand::Call(identifier(words[1])?, number(words[2])?)
+        },
+        "return" => {
+            expect(1)?;
+            VmCommand::Return
+        },
+        x => return Err(error(format!("unknown command '{}'", x)))
+    };
+    Ok(Some(command))
+}
+
+pub fn parse_vm(file: &str, source: &str) -> Result<VmFile, VmError> {
+    let mut commands = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        if let Some(x) = parse_command(file, i + 1, text)? {
+            commands.push((i + 1, x));
+        }
+    }
+    let name = match Path::new(file).file_stem() {
+        Some(x) => x.to_string_lossy().to_string(),
+        None => file.to_string()
+    };
+    Ok(VmFile { name, commands })
+}
+
+pub fn parse_vm_file<P: AsRef<Path>>(path: P) -> Result<VmFile, VmError> {
+    let path = path.as_ref();
+    let file = path.display().to_string();
+    let source = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => return Err(VmError { file, line: 0, message: e.to_string() })
+    };
+    parse_vm(&file, &source)
+}
+
+// A .vm file or every .vm file of a directory in name order
+pub fn load_vm_path<P: AsRef<Path>>(path: P) -> Result<Vec<VmFile>, VmError> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(vec![parse_vm_file(path)?]);
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(x) => x,
+        Err(e) => return Err(VmError { file: path.display().to_string(), line: 0, message: e.to_string() })
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map(|x| x == "vm").unwrap_or(false))
+        .collect();
+    paths.sort();
+    paths.iter().map(parse_vm_file).collect()
+}
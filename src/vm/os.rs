@@ -0,0 +1,670 @@
+// Jack OS classes for the VM emulator
+//
+// Most functions are native. Sys.init and Keyboard.readLine/readInt are Jack
+// code compiled when the emulator is created, so that they can wait for keys
+// through Keyboard.readChar without blocking the emulator.
+//
+// Errors call Sys.error with the codes of the reference OS.
+
+use std::collections::BTreeMap;
+
+use super::emulator::{VmEmulator, HEAP_BASE, KEYBOARD, SCREEN, SCREEN_WORDS};
+use super::VmError;
+
+pub(crate) enum NativeResult {
+    Return(u16),
+    // call again on the next step
+    Wait
+}
+
+pub(crate) type NativeFunction = fn(&mut VmEmulator, &[u16]) -> Result<NativeResult, VmError>;
+
+const HEAP_END: u16 = SCREEN as u16;
+const ROWS: u16 = 23;
+const COLUMNS: u16 = 64;
+const NEW_LINE: u16 = 128;
+const BACKSPACE: u16 = 129;
+const DOUBLE_QUOTE: u16 = 34;
+
+pub(crate) const JACK_SOURCES: [(&str, &str); 2] = [
+    ("Sys", "
+class Sys {
+    function void init() {
+        do Memory.init();
+        do Math.init();
+        do Screen.init();
+        do Output.init();
+        do Keyboard.init();
+        do Main.main();
+        do Sys.halt();
+        return;
+    }
+}
+"),
+    ("Keyboard", "
+class Keyboard {
+    function String readLine(String message) {
+        var String line;
+        var char c;
+        do Output.printString(message);
+        let line = String.new(64);
+        while (true) {
+            let c = Keyboard.readChar();
+            if (c = String.newLine()) {
+                do Output.println();
+                return line;
+            }
+            if (c = String.backSpace()) {
+                if (line.length() > 0) {
+                    do line.eraseLastChar();
+                    do Output.backSpace();
+                }
+            } else {
+                if (line.length() < 64) {
+                    do line.appendChar(c);
+                }
+            }
+        }
+        return line;
+    }
+
+    function int readInt(String message) {
+        var String line;
+        var int value;
+        let line = Keyboard.readLine(message);
+        let value = line.intValue();
+        do line.dispose();
+        return value;
+    }
+}
+")
+];
+
+#[derive(Default)]
+pub(crate) struct OsState {
+    heap_ready: bool,
+    // free blocks of the heap as (address, size)
+    free: Vec<(u16, u16)>,
+    blocks: BTreeMap<u16, u16>,
+    row: u16,
+    column: u16,
+    white: bool,
+    // key seen by Keyboard.readChar, waiting for it to be released
+    key: Option<u16>
+}
+
+// Glyphs of the characters 32 to 126, 11 rows of 8 pixels each, bit 0 is the
+// leftmost pixel
+const FONT: [[u8; 11]; 95] = [
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [12, 30, 30, 30, 12, 12, 0, 12, 12, 0, 0],
+    [54, 54, 20, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 18, 18, 63, 18, 18, 63, 18, 18, 0, 0],
+    [12, 30, 51, 3, 30, 48, 51, 30, 12, 12, 0],
+    [0, 0, 35, 51, 24, 12, 6, 51, 49, 0, 0],
+    [12, 30, 30, 12, 54, 27, 27, 27, 54, 0, 0],
+    [12, 12, 6, 0, 0, 0, 0, 0, 0, 0, 0],
+    [24, 12, 6, 6, 6, 6, 6, 12, 24, 0, 0],
+    [6, 12, 24, 24, 24, 24, 24, 12, 6, 0, 0],
+    [0, 0, 0, 51, 30, 63, 30, 51, 0, 0, 0],
+    [0, 0, 0, 12, 12, 63, 12, 12, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 6, 0],
+    [0, 0, 0, 0, 0, 63, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 0, 0],
+    [0, 0, 32, 48, 24, 12, 6, 3, 1, 0, 0],
+    [12, 30, 51, 51, 51, 51, 51, 30, 12, 0, 0],
+    [12, 14, 15, 12, 12, 12, 12, 12, 63, 0, 0],
+    [30, 51, 48, 24, 12, 6, 3, 51, 63, 0, 0],
+    [30, 51, 48, 48, 28, 48, 48, 51, 30, 0, 0],
+    [16, 24, 28, 26, 25, 63, 24, 24, 60, 0, 0],
+    [63, 3, 3, 31, 48, 48, 48, 51, 30, 0, 0],
+    [28, 6, 3, 3, 31, 51, 51, 51, 30, 0, 0],
+    [63, 49, 48, 48, 24, 12, 12, 12, 12, 0, 0],
+    [30, 51, 51, 51, 30, 51, 51, 51, 30, 0, 0],
+    [30, 51, 51, 51, 62, 48, 48, 24, 14, 0, 0],
+    [0, 0, 12, 12, 0, 0, 12, 12, 0, 0, 0],
+    [0, 0, 12, 12, 0, 0, 12, 12, 6, 0, 0],
+    [0, 0, 24, 12, 6, 3, 6, 12, 24, 0, 0],
+    [0, 0, 0, 63, 0, 0, 63, 0, 0, 0, 0],
+    [0, 0, 3, 6, 12, 24, 12, 6, 3, 0, 0],
+    [30, 51, 51, 24, 12, 12, 0, 12, 12, 0, 0],
+    [30, 51, 51, 59, 59, 59, 27, 3, 30, 0, 0],
+    [12, 30, 51, 51, 63, 51, 51, 51, 51, 0, 0],
+    [31, 51, 51, 51, 31, 51, 51, 51, 31, 0, 0],
+    [28, 54, 35, 3, 3, 3, 35, 54, 28, 0, 0],
+    [15, 27, 51, 51, 51, 51, 51, 27, 15, 0, 0],
+    [63, 51, 35, 11, 15, 11, 35, 51, 63, 0, 0],
+    [63, 51, 35, 11, 15, 11, 3, 3, 3, 0, 0],
+    [28, 54, 35, 3, 59, 51, 51, 54, 44, 0, 0],
+    [51, 51, 51, 51, 63, 51, 51, 51, 51, 0, 0],
+    [30, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],
+    [60, 24, 24, 24, 24, 24, 27, 27, 14, 0, 0],
+    [51, 51, 51, 27, 15, 27, 51, 51, 51, 0, 0],
+    [3, 3, 3, 3, 3, 3, 35, 51, 63, 0, 0],
+    [33, 51, 63, 63, 51, 51, 51, 51, 51, 0, 0],
+    [51, 51, 55, 55, 63, 59, 59, 51, 51, 0, 0],
+    [30, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],
+    [31, 51, 51, 51, 31, 3, 3, 3, 3, 0, 0],
+    [30, 51, 51, 51, 51, 51, 63, 59, 30, 48, 0],
+    [31, 51, 51, 51, 31, 27, 51, 51, 51, 0, 0],
+    [30, 51, 51, 6, 28, 48, 51, 51, 30, 0, 0],
+    [63, 63, 45, 12, 12, 12, 12, 12, 30, 0, 0],
+    [51, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],
+    [51, 51, 51, 51, 51, 30, 30, 12, 12, 0, 0],
+    [51, 51, 51, 51, 51, 63, 63, 63, 18, 0, 0],
+    [51, 51, 30, 30, 12, 30, 30, 51, 51, 0, 0],
+    [51, 51, 51, 51, 30, 12, 12, 12, 30, 0, 0],
+    [63, 51, 49, 24, 12, 6, 35, 51, 63, 0, 0],
+    [30, 6, 6, 6, 6, 6, 6, 6, 30, 0, 0],
+    [0, 0, 1, 3, 6, 12, 24, 48, 32, 0, 0],
+    [30, 24, 24, 24, 24, 24, 24, 24, 30, 0, 0],
+    [8, 28, 54, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 0],
+    [6, 12, 24, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 14, 24, 30, 27, 27, 54, 0, 0],
+    [3, 3, 3, 15, 27, 51, 51, 51, 30, 0, 0],
+    [0, 0, 0, 30, 51, 3, 3, 51, 30, 0, 0],
+    [48, 48, 48, 60, 54, 51, 51, 51, 30, 0, 0],
+    [0, 0, 0, 30, 51, 63, 3, 51, 30, 0, 0],
+    [28, 54, 38, 6, 15, 6, 6, 6, 15, 0, 0],
+    [0, 0, 30, 51, 51, 51, 62, 48, 51, 30, 0],
+    [3, 3, 3, 27, 55, 51, 51, 51, 51, 0, 0],
+    [12, 12, 0, 14, 12, 12, 12, 12, 30, 0, 0],
+    [48, 48, 0, 56, 48, 48, 48, 48, 51, 30, 0],
+    [3, 3, 3, 51, 27, 15, 15, 27, 51, 0, 0],
+    [14, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],
+    [0, 0, 0, 29, 63, 43, 43, 43, 43, 0, 0],
+    [0, 0, 0, 29, 51, 51, 51, 51, 51, 0, 0],
+    [0, 0, 0, 30, 51, 51, 51, 51, 30, 0, 0],
+    [0, 0, 0, 30, 51, 51, 51, 31, 3, 3, 0],
+    [0, 0, 0, 30, 51, 51, 51, 62, 48, 48, 0],
+    [0, 0, 0, 29, 55, 51, 3, 3, 7, 0, 0],
+    [0, 0, 0, 30, 51, 6, 24, 51, 30, 0, 0],
+    [4, 6, 6, 15, 6, 6, 6, 54, 28, 0, 0],
+    [0, 0, 0, 27, 27, 27, 27, 27, 54, 0, 0],
+    [0, 0, 0, 51, 51, 51, 51, 30, 12, 0, 0],
+    [0, 0, 0, 51, 51, 51, 63, 63, 18, 0, 0],
+    [0, 0, 0, 51, 30, 12, 12, 30, 51, 0, 0],
+    [0, 0, 0, 51, 51, 51, 62, 48, 24, 15, 0],
+    [0, 0, 0, 63, 27, 12, 6, 51, 63, 0, 0],
+    [56, 12, 12, 12, 7, 12, 12, 12, 56, 0, 0],
+    [12, 12, 12, 12, 12, 12, 12, 12, 12, 0, 0],
+    [7, 12, 12, 12, 56, 12, 12, 12, 7, 0, 0],
+    [38, 45, 25, 0, 0, 0, 0, 0, 0, 0, 0]
+];
+
+// drawn for characters without a glyph
+const UNKNOWN_GLYPH: [u8; 11] = [63, 63, 63, 63, 63, 63, 63, 63, 63, 0, 0];
+
+fn glyph(c: u16) -> &'static [u8; 11] {
+    if (32..127).contains(&c) {
+        &FONT[c as usize - 32]
+    } else {
+        &UNKNOWN_GLYPH
+    }
+}
+
+pub(crate) fn natives() -> Vec<(&'static str, u16, NativeFunction)> {
+    vec![
+        ("Math.init", 0, |_, _| done()),
+        ("Math.multiply", 2, |_, a| value(a[0].wrapping_mul(a[1]))),
+        ("Math.divide", 2, math_divide),
+        ("Math.sqrt", 1, math_sqrt),
+        ("Math.abs", 1, |_, a| value((a[0] as i16).wrapping_abs() as u16)),
+        ("Math.min", 2, |_, a| value((a[0] as i16).min(a[1] as i16) as u16)),
+        ("Math.max", 2, |_, a| value((a[0] as i16).max(a[1] as i16) as u16)),
+        ("Memory.init", 0, memory_init),
+        ("Memory.peek", 1, |emu, a| value(emu.read(a[0])?)),
+        ("Memory.poke", 2, |emu, a| {
+            emu.write(a[0], a[1])?;
+            done()
+        }),
+        ("Memory.alloc", 1, memory_alloc),
+        ("Memory.deAlloc", 1, memory_dealloc),
+        ("Array.new", 1, |emu, a| {
+            if a[0] as i16 <= 0 {
+                return fail(emu, 2);
+            }
+            value(emu.call_function("Memory.alloc", a)?)
+        }),
+        ("Array.dispose", 1, |emu, a| {
+            emu.call_function("Memory.deAlloc", a)?;
+            done()
+        }),
+        ("String.new", 1, string_new),
+        ("String.dispose", 1, string_dispose),
+        ("String.length", 1, |emu, a| value(emu.read(a[0].wrapping_add(1))?)),
+        ("String.charAt", 2, string_char_at),
+        ("String.setCharAt", 3, string_set_char_at),
+        ("String.appendChar", 2, string_append_char),
+        ("String.eraseLastChar", 1, string_erase_last_char),
+        ("String.intValue", 1, string_int_value),
+        ("String.setInt", 2, string_set_int),
+        ("String.newLine", 0, |_, _| value(NEW_LINE)),
+        ("String.backSpace", 0, |_, _| value(BACKSPACE)),
+        ("String.doubleQuote", 0, |_, _| value(DOUBLE_QUOTE)),
+        ("Screen.init", 0, |emu, _| {
+            emu.os.white = false;
+            done()
+        }),
+        ("Screen.clearScreen", 0, |emu, _| {
+            for x in &mut emu.ram_mut()[SCREEN..SCREEN + SCREEN_WORDS] {
+                *x = 0;
+            }
+            done()
+        }),
+        ("Screen.setColor", 1, |emu, a| {
+            emu.os.white = a[0] == 0;
+            done()
+        }),
+        ("Screen.drawPixel", 2, screen_draw_pixel),
+        ("Screen.drawLine", 4, screen_draw_line),
+        ("Screen.drawRectangle", 4, screen_draw_rectangle),
+        ("Screen.drawCircle", 3, screen_draw_circle),
+        ("Output.init", 0, |emu, _| {
+            emu.os.row = 0;
+            emu.os.column = 0;
+            done()
+        }),
+        ("Output.moveCursor", 2, |emu, a| {
+            if a[0] >= ROWS || a[1] >= COLUMNS {
+                return fail(emu, 20);
+            }
+            emu.os.row = a[0];
+            emu.os.column = a[1];
+            done()
+        }),
+        ("Output.printChar", 1, |emu, a| {
+            print_char(emu, a[0]);
+            done()
+        }),
+        ("Output.printString", 1, output_print_string),
+        ("Output.printInt", 1, |emu, a| {
+            for c in (a[0] as i16).to_string().chars() {
+                print_char(emu, c as u16);
+            }
+            done()
+        }),
+        ("Output.println", 0, |emu, _| {
+            println(emu);
+            done()
+        }),
+        ("Output.backSpace", 0, |emu, _| {
+            backspace(emu);
+            done()
+        }),
+        ("Keyboard.init", 0, |emu, _| {
+            emu.os.key = None;
+            done()
+        }),
+        ("Keyboard.keyPressed", 0, |emu, _| value(emu.ram()[KEYBOARD])),
+        ("Keyboard.readChar", 0, keyboard_read_char),
+        ("Sys.halt", 0, |emu, _| {
+            emu.halt(None);
+            done()
+        }),
+        ("Sys.error", 1, sys_error),
+        ("Sys.wait", 1, |emu, a| {
+            if (a[0] as i16) < 0 {
+                return fail(emu, 1);
+            }
+            done()
+        })
+    ]
+}
+
+fn value(x: u16) -> Result<NativeResult, VmError> {
+    Ok(NativeResult::Return(x))
+}
+
+fn done() -> Result<NativeResult, VmError> {
+    value(0)
+}
+
+fn fail(emu: &mut VmEmulator, code: u16) -> Result<NativeResult, VmError> {
+    emu.call_function("Sys.error", &[code])?;
+    done()
+}
+
+fn math_divide(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    if args[1] == 0 {
+        return fail(emu, 3);
+    }
+    value((args[0] as i16).wrapping_div(args[1] as i16) as u16)
+}
+
+fn math_sqrt(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let x = args[0] as i16;
+    if x < 0 {
+        return fail(emu, 4);
+    }
+    let mut y = 0i32;
+    while (y + 1) * (y + 1) <= x as i32 {
+        y += 1;
+    }
+    value(y as u16)
+}
+
+fn memory_init(emu: &mut VmEmulator, _: &[u16]) -> Result<NativeResult, VmError> {
+    emu.os.free = vec![(HEAP_BASE, HEAP_END - HEAP_BASE)];
+    emu.os.blocks.clear();
+    emu.os.heap_ready = true;
+    done()
+}
+
+fn memory_alloc(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let size = args[0];
+    if size as i16 <= 0 {
+        return fail(emu, 5);
+    }
+    if !emu.os.heap_ready {
+        memory_init(emu, &[])?;
+    }
+    let found = emu.os.free.iter().position(|(_, free)| *free >= size);
+    let index = match found {
+        Some(x) => x,
+        None => return fail(emu, 6)
+    };
+    let (address, free) = emu.os.free[index];
+    if free == size {
+        emu.os.free.remove(index);
+    } else {
+        emu.os.free[index] = (address + size, free - size);
+    }
+    emu.os.blocks.insert(address, size);
+    value(address)
+}
+
+fn memory_dealloc(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let size = match emu.os.blocks.remove(&args[0]) {
+        Some(x) => x,
+        None => return done()
+    };
+    let free = &mut emu.os.free;
+    let index = free.iter().position(|(address, _)| *address > args[0]).unwrap_or(free.len());
+    free.insert(index, (args[0], size));
+    // merge with the following and the preceding block
+    if index + 1 < free.len() && free[index].0 + free[index].1 == free[index + 1].0 {
+        free[index].1 += free[index + 1].1;
+        free.remove(index + 1);
+    }
+    if index > 0 && free[index - 1].0 + free[index - 1].1 == free[index].0 {
+        free[index - 1].1 += free[index].1;
+        free.remove(index);
+    }
+    done()
+}
+
+// Strings are 3 words: address of the characters, length and capacity
+
+fn string_new(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let capacity = args[0];
+    if (capacity as i16) < 0 {
+        return fail(emu, 14);
+    }
+    let this = emu.call_function("Memory.alloc", &[3])?;
+    let chars = if capacity > 0 {
+        emu.call_function("Memory.alloc", &[capacity])?
+    } else {
+        0
+    };
+    if emu.is_halted() {
+        return done();
+    }
+    emu.write(this, chars)?;
+    emu.write(this + 1, 0)?;
+    emu.write(this + 2, capacity)?;
+    value(this)
+}
+
+fn string_dispose(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let chars = emu.read(args[0])?;
+    if chars != 0 {
+        emu.call_function("Memory.deAlloc", &[chars])?;
+    }
+    emu.call_function("Memory.deAlloc", &[args[0]])?;
+    done()
+}
+
+fn string_char_at(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let (this, index) = (args[0], args[1]);
+    if index >= emu.read(this.wrapping_add(1))? {
+        return fail(emu, 15);
+    }
+    let chars = emu.read(this)?;
+    value(emu.read(chars.wrapping_add(index))?)
+}
+
+fn string_set_char_at(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let (this, index) = (args[0], args[1]);
+    if index >= emu.read(this.wrapping_add(1))? {
+        return fail(emu, 16);
+    }
+    let chars = emu.read(this)?;
+    emu.write(chars.wrapping_add(index), args[2])?;
+    done()
+}
+
+fn string_append_char(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let this = args[0];
+    let length = emu.read(this.wrapping_add(1))?;
+    if length >= emu.read(this.wrapping_add(2))? {
+        return fail(emu, 17);
+    }
+    let chars = emu.read(this)?;
+    emu.write(chars.wrapping_add(length), args[1])?;
+    emu.write(this + 1, length + 1)?;
+    value(this)
+}
+
+fn string_erase_last_char(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let this = args[0];
+    let length = emu.read(this.wrapping_add(1))?;
+    if length == 0 {
+        return fail(emu, 18);
+    }
+    emu.write(this + 1, length - 1)?;
+    done()
+}
+
+fn string_int_value(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let this = args[0];
+    let length = emu.read(this.wrapping_add(1))?;
+    let chars = emu.read(this)?;
+    let mut out: u16 = 0;
+    let mut negative = false;
+    for i in 0..length {
+        let c = emu.read(chars.wrapping_add(i))?;
+        if i == 0 && c == '-' as u16 {
+            negative = true;
+        } else if (48..58).contains(&c) {
+            out = out.wrapping_mul(10).wrapping_add(c - 48);
+        } else {
+            break;
+        }
+    }
+    value(if negative { out.wrapping_neg() } else { out })
+}
+
+fn string_set_int(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let this = args[0];
+    let text = (args[1] as i16).to_string();
+    if text.len() as u16 > emu.read(this.wrapping_add(2))? {
+        return fail(emu, 19);
+    }
+    let chars = emu.read(this)?;
+    for (i, c) in text.chars().enumerate() {
+        emu.write(chars + i as u16, c as u16)?;
+    }
+    emu.write(this + 1, text.len() as u16)?;
+    done()
+}
+
+// Screen
+
+fn set_pixel(emu: &mut VmEmulator, x: i32, y: i32) {
+    let address = SCREEN + y as usize * 32 + x as usize / 16;
+    let mask = 1u16 << (x % 16);
+    let white = emu.os.white;
+    let word = &mut emu.ram_mut()[address];
+    if white {
+        *word &= !mask;
+    } else {
+        *word |= mask;
+    }
+}
+
+fn on_screen(x: i32, y: i32) -> bool {
+    (0..512).contains(&x) && (0..256).contains(&y)
+}
+
+fn screen_draw_pixel(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let (x, y) = (args[0] as i16 as i32, args[1] as i16 as i32);
+    if !on_screen(x, y) {
+        return fail(emu, 7);
+    }
+    set_pixel(emu, x, y);
+    done()
+}
+
+fn screen_draw_line(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let a: Vec<i32> = args.iter().map(|x| *x as i16 as i32).collect();
+    if !on_screen(a[0], a[1]) || !on_screen(a[2], a[3]) {
+        return fail(emu, 8);
+    }
+    let (mut x, mut y) = (a[0], a[1]);
+    let (dx, dy) = ((a[2] - x).abs(), -(a[3] - y).abs());
+    let (sx, sy) = ((a[2] - x).signum(), (a[3] - y).signum());
+    let mut error = dx + dy;
+    loop {
+        set_pixel(emu, x, y);
+        if x == a[2] && y == a[3] {
+            break;
+        }
+        if 2 * error >= dy {
+            error += dy;
+            x += sx;
+        }
+        if 2 * error <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+    done()
+}
+
+fn screen_draw_rectangle(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let a: Vec<i32> = args.iter().map(|x| *x as i16 as i32).collect();
+    if !on_screen(a[0], a[1]) || !on_screen(a[2], a[3]) || a[0] > a[2] || a[1] > a[3] {
+        return fail(emu, 9);
+    }
+    for y in a[1]..=a[3] {
+        for x in a[0]..=a[2] {
+            set_pixel(emu, x, y);
+        }
+    }
+    done()
+}
+
+fn screen_draw_circle(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let (cx, cy, r) = (args[0] as i16 as i32, args[1] as i16 as i32, args[2] as i16 as i32);
+    if !on_screen(cx, cy) {
+        return fail(emu, 12);
+    }
+    if !(0..=181).contains(&r) {
+        return fail(emu, 13);
+    }
+    for dy in -r..=r {
+        let mut dx = 0;
+        while (dx + 1) * (dx + 1) + dy * dy <= r * r {
+            dx += 1;
+        }
+        for x in cx - dx..=cx + dx {
+            if on_screen(x, cy + dy) {
+                set_pixel(emu, x, cy + dy);
+            }
+        }
+    }
+    done()
+}
+
+// Output, 23 rows of 64 characters
+
+fn draw_char(emu: &mut VmEmulator, c: u16) {
+    let (row, column) = (emu.os.row as usize, emu.os.column as usize);
+    for (i, bits) in glyph(c).iter().enumerate() {
+        let word = &mut emu.ram_mut()[SCREEN + (row * 11 + i) * 32 + column / 2];
+        if column % 2 == 0 {
+            *word = (*word & 0xff00) | *bits as u16;
+        } else {
+            *word = (*word & 0x00ff) | (*bits as u16) << 8;
+        }
+    }
+}
+
+fn println(emu: &mut VmEmulator) {
+    emu.os.column = 0;
+    emu.os.row = (emu.os.row + 1) % ROWS;
+}
+
+fn backspace(emu: &mut VmEmulator) {
+    if emu.os.column > 0 {
+        emu.os.column -= 1;
+    } else if emu.os.row > 0 {
+        emu.os.row -= 1;
+        emu.os.column = COLUMNS - 1;
+    }
+    draw_char(emu, ' ' as u16);
+}
+
+fn print_char(emu: &mut VmEmulator, c: u16) {
+    match c {
+        NEW_LINE => println(emu),
+        BACKSPACE => backspace(emu),
+        _ => {
+            draw_char(emu, c);
+            emu.os.column += 1;
+            if emu.os.column == COLUMNS {
+                println(emu);
+            }
+        }
+    }
+}
+
+fn output_print_string(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let length = emu.call_function("String.length", args)?;
+    for i in 0..length {
+        let c = emu.call_function("String.charAt", &[args[0], i])?;
+        if emu.is_halted() {
+            break;
+        }
+        print_char(emu, c);
+    }
+    done()
+}
+
+// Waits for a key to be pressed and released, then prints and returns it
+fn keyboard_read_char(emu: &mut VmEmulator, _: &[u16]) -> Result<NativeResult, VmError> {
+    let pressed = emu.ram()[KEYBOARD];
+    match emu.os.key {
+        None if pressed != 0 => emu.os.key = Some(pressed),
+        Some(key) if pressed == 0 => {
+            emu.os.key = None;
+            if key != NEW_LINE && key != BACKSPACE {
+                print_char(emu, key);
+            }
+            return value(key);
+        },
+        _ => {}
+    }
+    Ok(NativeResult::Wait)
+}
+
+fn sys_error(emu: &mut VmEmulator, args: &[u16]) -> Result<NativeResult, VmError> {
+    let code = args[0] as i16;
+    for c in format!("ERR{}", code).chars() {
+        emu.call_function("Output.printChar", &[c as u16])?;
+    }
+    emu.halt(Some(code));
+    done()
+}
@@ -0,0 +1,329 @@
+// VM to Hack assembly
+//
+// SP, LCL, ARG, THIS, THAT live in RAM[0..5], temp in RAM[5..13], R13-R15
+// are scratch registers and static i of File.vm is the variable @File.i.
+// Labels are namespaced by the function they are in (Main.loop$WHILE0) and
+// every call gets its own return label (Main.loop$ret.3).
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use super::{load_vm_path, ArithmeticOp, Segment, VmCommand, VmError, VmFile};
+
+pub struct Translator {
+    lines: Vec<String>,
+    file: String,
+    function: String,
+    labels: usize
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Translator {
+    pub fn new() -> Translator {
+        Translator {
+            lines: Vec::new(),
+            file: String::new(),
+            function: String::new(),
+            labels: 0
+        }
+    }
+
+    fn emit(&mut self, lines: &[&str]) {
+        self.lines.extend(lines.iter().map(|x| x.to_string()));
+    }
+
+    fn emit_line(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    fn unique_label(&mut self, name: &str) -> String {
+        self.labels += 1;
+        if self.function.is_empty() {
+            format!("{}${}.{}", self.file, name, self.labels)
+        } else {
+            format!("{}${}.{}", self.function, name, self.labels)
+        }
+    }
+
+    fn label_name(&self, label: &str) -> String {
+        if self.function.is_empty() {
+            label.to_string()
+        } else {
+            format!("{}${}", self.function, label)
+        }
+    }
+
+    // SP = 256, call Sys.init
+    pub fn bootstrap(&mut self) {
+        self.emit(&["// bootstrap", "@256", "D=A", "@SP", "M=D"]);
+        self.call("Sys.init", 0);
+    }
+
+    pub fn file(&mut self, file: &VmFile) {
+        self.file = file.name.clone();
+        self.function = String::new();
+        for (_, command) in &file.commands {
+            self.command(command);
+        }
+    }
+
+    pub fn finish(self) -> String {
+        let mut out = self.lines.join("\n");
+        out.push('\n');
+        out
+    }
+
+    fn push_d(&mut self) {
+        self.emit(&["@SP", "AM=M+1", "A=A-1", "M=D"]);
+    }
+
+    fn pop_d(&mut self) {
+        self.emit(&["@SP", "AM=M-1", "D=M"]);
+    }
+
+    fn base_register(segment: Segment) -> &'static str {
+        match segment {
+            Segment::Local => "LCL",
+            Segment::Argument => "ARG",
+            Segment::This => "THIS",
+            Segment::That => "THAT",
+            _ => unreachable!()
+        }
+    }
+
+    // symbol of a segment that is at a fixed address
+    fn fixed_address(&self, segment: Segment, index: u16) -> String {
+        match segment {
+            Segment::Pointer => format!("@{}", 3 + index),
+            Segment::Temp => format!("@{}", 5 + index),
+            Segment::Static => format!("@{}.{}", self.file, index),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn command(&mut self, command: &VmCommand) {
+        self.emit_line(format!("// {}", command));
+        match command {
+            VmCommand::Arithmetic(op) => self.arithmetic(*op),
+            VmCommand::Push(segment, index) => self.push(*segment, *index),
+            VmCommand::Pop(segment, index) => self.pop(*segment, *index),
+            VmCommand::Label(label) => {
+                let label = self.label_name(label);
+                self.emit_line(format!("({})", label));
+            },
+            VmCommand::Goto(label) => {
+                let label = self.label_name(label);
+                self.emit_line(format!("@{}", label));
+                self.emit(&["0;JMP"]);
+            },
+            VmCommand::IfGoto(label) => {
+                let label = self.label_name(label);
+                self.pop_d();
+                self.emit_line(format!("@{}", label));
+                self.emit(&["D;JNE"]);
+            },
+            VmCommand::Function(name, locals) => {
+                self.function = name.clone();
+                self.emit_line(format!("({})", name));
+                for _ in 0..*locals {
+                    self.emit(&["@SP", "AM=M+1", "A=A-1", "M=0"]);
+                }
+            },
+            VmCommand::Call(name, args) => self.call(name, *args),
+            VmCommand::Return => self.ret()
+        }
+    }
+
+    fn arithmetic(&mut self, op: ArithmeticOp) {
+        match op {
+            ArithmeticOp::Neg => self.emit(&["@SP", "A=M-1", "M=-M"]),
+            ArithmeticOp::Not => self.emit(&["@SP", "A=M-1", "M=!M"]),
+            ArithmeticOp::Add | ArithmeticOp::Sub | ArithmeticOp::And | ArithmeticOp::Or => {
+                let compute = match op {
+                    ArithmeticOp::Add => "M=D+M",
+                    ArithmeticOp::Sub => "M=M-D",
+                    ArithmeticOp::And => "M=D&M",
+                    _ => "M=D|M"
+                };
+                self.emit(&["@SP", "AM=M-1", "D=M", "A=A-1", compute]);
+            },
+            ArithmeticOp::Eq => {
+                self.emit(&["@SP", "AM=M-1", "D=M", "A=A-1", "D=M-D"]);
+                self.compare("D;JEQ");
+            },
+            ArithmeticOp::Gt => {
+                self.signed_difference();
+                self.compare("D;JGT");
+            },
+            ArithmeticOp::Lt => {
+                self.signed_difference();
+                self.compare("D;JLT");
+            }
+        }
+    }
+
+    // Pops y and leaves D with the sign of x - y for the x on top of the
+    // stack. x - y overflows when the signs differ, like 32767 - (-1), so
+    // then D is 1 or -1 from the sign of x alone.
+    fn signed_difference(&mut self) {
+        let negative = self.unique_label("negative");
+        let subtract = self.unique_label("subtract");
+        let done = self.unique_label("signed");
+        self.emit(&["@SP", "AM=M-1", "D=M", "@R13", "M=D", "@SP", "A=M-1", "D=M"]);
+        self.emit_line(format!("@{}", negative));
+        self.emit(&["D;JLT", "@R13", "D=M"]);
+        self.emit_line(format!("@{}", subtract));
+        self.emit(&["D;JGE", "D=1"]);
+        self.emit_line(format!("@{}", done));
+        self.emit(&["0;JMP"]);
+        self.emit_line(format!("({})", negative));
+        self.emit(&["@R13", "D=M"]);
+        self.emit_line(format!("@{}", subtract));
+        self.emit(&["D;JLT", "D=-1"]);
+        self.emit_line(format!("@{}", done));
+        self.emit(&["0;JMP"]);
+        self.emit_line(format!("({})", subtract));
+        self.emit(&["@R13", "D=M", "@SP", "A=M-1", "D=M-D"]);
+        self.emit_line(format!("({})", done));
+    }
+
+    // Replaces x on top of the stack with true when `jump` on D is taken
+    fn compare(&mut self, jump: &str) {
+        let label = self.unique_label("true");
+        self.emit(&["@SP", "A=M-1", "M=-1"]);
+        self.emit_line(format!("@{}", label));
+        self.emit(&[jump, "@SP", "A=M-1", "M=0"]);
+        self.emit_line(format!("({})", label));
+    }
+
+    fn push(&mut self, segment: Segment, index: u16) {
+        match segment {
+            Segment::Constant => {
+                self.emit_line(format!("@{}", index));
+                self.emit(&["D=A"]);
+            },
+            Segment::Local | Segment::Argument | Segment::This | Segment::That => {
+                self.emit_line(format!("@{}", index));
+                self.emit(&["D=A"]);
+                self.emit_line(format!("@{}", Self::base_register(segment)));
+                self.emit(&["A=D+M", "D=M"]);
+            },
+            Segment::Pointer | Segment::Temp | Segment::Static => {
+                let address = self.fixed_address(segment, index);
+                self.emit_line(address);
+                self.emit(&["D=M"]);
+            }
+        }
+        self.push_d();
+    }
+
+    fn pop(&mut self, segment: Segment, index: u16) {
+        match segment {
+            Segment::Local | Segment::Argument | Segment::This | Segment::That => {
+                self.emit_line(format!("@{}", index));
+                self.emit(&["D=A"]);
+                self.emit_line(format!("@{}", Self::base_register(segment)));
+                self.emit(&["D=D+M", "@R13", "M=D"]);
+                self.pop_d();
+                self.emit(&["@R13", "A=M", "M=D"]);
+            },
+            Segment::Pointer | Segment::Temp | Segment::Static => {
+                self.pop_d();
+                let address = self.fixed_address(segment, index);
+                self.emit_line(address);
+                self.emit(&["M=D"]);
+            },
+            Segment::Constant => unreachable!()
+        }
+    }
+
+    // push return address, LCL, ARG, THIS, THAT; ARG = SP - 5 - args; LCL = SP
+    fn call(&mut self, name: &str, args: u16) {
+        let ret = self.unique_label("ret");
+        self.emit_line(format!("@{}", ret));
+        self.emit(&["D=A"]);
+        self.push_d();
+        for register in ["LCL", "ARG", "THIS", "THAT"].iter() {
+            self.emit_line(format!("@{}", register));
+            self.emit(&["D=M"]);
+            self.push_d();
+        }
+        self.emit(&["@SP", "D=M"]);
+        self.emit_line(format!("@{}", args + 5));
+        self.emit(&["D=D-A", "@ARG", "M=D", "@SP", "D=M", "@LCL", "M=D"]);
+        self.emit_line(format!("@{}", name));
+        self.emit(&["0;JMP"]);
+        self.emit_line(format!("({})", ret));
+    }
+
+    // R13 = LCL, R14 = return address, *ARG = pop(), SP = ARG + 1 and the
+    // registers of the caller are restored from the frame
+    fn ret(&mut self) {
+        self.emit(&["@LCL", "D=M", "@R13", "M=D", "@5", "A=D-A", "D=M", "@R14", "M=D"]);
+        self.pop_d();
+        self.emit(&["@ARG", "A=M", "M=D", "@ARG", "D=M+1", "@SP", "M=D"]);
+        for register in ["THAT", "THIS", "ARG", "LCL"].iter() {
+            self.emit(&["@R13", "AM=M-1", "D=M"]);
+            self.emit_line(format!("@{}", register));
+            self.emit(&["M=D"]);
+        }
+        self.emit(&["@R14", "A=M", "0;JMP"]);
+    }
+}
+
+// Every goto and if-goto must jump to a label of its own function, the
+// assembler would otherwise make a variable of the label
+fn check_labels(files: &[VmFile]) -> Result<(), VmError> {
+    let mut translator = Translator::new();
+    let mut labels = BTreeSet::new();
+    for file in files {
+        translator.function = String::new();
+        for (_, command) in &file.commands {
+            match command {
+                VmCommand::Function(name, _) => translator.function = name.clone(),
+                VmCommand::Label(label) => {
+                    labels.insert(translator.label_name(label));
+                },
+                _ => {}
+            }
+        }
+    }
+    for file in files {
+        translator.function = String::new();
+        for (line, command) in &file.commands {
+            match command {
+                VmCommand::Function(name, _) => translator.function = name.clone(),
+                VmCommand::Goto(label) | VmCommand::IfGoto(label) if !labels.contains(&translator.label_name(label)) => {
+                    return Err(VmError { file: file.name.clone(), line: *line, message: format!("undefined label '{}'", label) });
+                },
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+// Bootstrap code is only added when one of the files defines Sys.init
+pub fn translate(files: &[VmFile]) -> Result<String, VmError> {
+    check_labels(files)?;
+    let mut translator = Translator::new();
+    let has_sys_init = files.iter().any(|file| {
+        file.commands.iter().any(|(_, command)| matches!(command, VmCommand::Function(name, _) if name == "Sys.init"))
+    });
+    if has_sys_init {
+        translator.bootstrap();
+    }
+    for file in files {
+        translator.file(file);
+    }
+    Ok(translator.finish())
+}
+
+pub fn translate_path<P: AsRef<Path>>(path: P) -> Result<String, VmError> {
+    translate(&load_vm_path(path)?)
+}
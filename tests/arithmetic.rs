@@ -0,0 +1,113 @@
+use sunho_computer::gates::{Gate, GateFactory, PinValues};
+
+const WORDS: [u64; 7] = [0x0000, 0x0001, 0xffff, 0x0011, 0x0003, 0x8000, 0x7fff];
+
+fn run(gate: &mut Gate, inputs: &[(&str, i64, u64)]) -> PinValues {
+    let mut values = PinValues::new();
+    for (input, size, value) in inputs {
+        values.set_number(input, *size, *value);
+    }
+    gate.run(values)
+}
+
+#[test]
+fn halfadder() {
+    let mut gate = GateFactory::new().build("halfadder");
+    for a in 0..2 {
+        for b in 0..2 {
+            let res = run(&mut gate, &[("a", 1, a), ("b", 1, b)]);
+            assert_eq!(res.get_number("sum", 1), (a + b) & 1);
+            assert_eq!(res.get_number("carry", 1), (a + b) >> 1);
+        }
+    }
+}
+
+#[test]
+fn fulladder() {
+    let mut gate = GateFactory::new().build("fulladder");
+    for x in 0..8 {
+        let (a, b, c) = (x & 1, (x >> 1) & 1, x >> 2);
+        let res = run(&mut gate, &[("a", 1, a), ("b", 1, b), ("c", 1, c)]);
+        assert_eq!(res.get_number("sum", 1), (a + b + c) & 1);
+        assert_eq!(res.get_number("carry", 1), (a + b + c) >> 1);
+    }
+}
+
+#[test]
+fn add16() {
+    let mut gate = GateFactory::new().build("add16");
+    for a in WORDS.iter() {
+        for b in WORDS.iter() {
+            let res = run(&mut gate, &[("a", 16, *a), ("b", 16, *b)]);
+            assert_eq!(res.get_number("out", 16), (a + b) & 0xffff, "{:x} + {:x}", a, b);
+        }
+    }
+}
+
+#[test]
+fn inc16() {
+    let mut gate = GateFactory::new().build("inc16");
+    for a in WORDS.iter() {
+        let res = run(&mut gate, &[("in", 16, *a)]);
+        assert_eq!(res.get_number("out", 16), (a + 1) & 0xffff);
+    }
+}
+
+type Operation = fn(u64, u64) -> u64;
+
+// zx, nx, zy, ny, f, no
+fn reference_alu(x: u64, y: u64, control: [bool; 6]) -> u64 {
+    let [zx, nx, zy, ny, f, no] = control;
+    let mut x = if zx { 0 } else { x };
+    x = if nx { !x & 0xffff } else { x };
+    let mut y = if zy { 0 } else { y };
+    y = if ny { !y & 0xffff } else { y };
+    let out = if f { (x + y) & 0xffff } else { x & y };
+    if no { !out & 0xffff } else { out }
+}
+
+#[test]
+fn alu() {
+    let canonical: [(&str, Operation); 18] = [
+        ("101010", |_, _| 0),
+        ("111111", |_, _| 1),
+        ("111010", |_, _| 0xffff),
+        ("001100", |x, _| x),
+        ("110000", |_, y| y),
+        ("001101", |x, _| !x),
+        ("110001", |_, y| !y),
+        ("001111", |x, _| 0u64.wrapping_sub(x)),
+        ("110011", |_, y| 0u64.wrapping_sub(y)),
+        ("011111", |x, _| x + 1),
+        ("110111", |_, y| y + 1),
+        ("001110", |x, _| x.wrapping_sub(1)),
+        ("110010", |_, y| y.wrapping_sub(1)),
+        ("000010", |x, y| x + y),
+        ("010011", |x, y| x.wrapping_sub(y)),
+        ("000111", |x, y| y.wrapping_sub(x)),
+        ("000000", |x, y| x & y),
+        ("010101", |x, y| x | y),
+    ];
+    let mut gate = GateFactory::new().build("alu");
+    let names = ["zx", "nx", "zy", "ny", "f", "no"];
+    for (bits, expected) in canonical.iter() {
+        let mut control = [false; 6];
+        for (i, c) in bits.chars().enumerate() {
+            control[i] = c == '1';
+        }
+        for x in WORDS.iter() {
+            for y in WORDS.iter() {
+                let mut inputs = vec![("x", 16, *x), ("y", 16, *y)];
+                for (name, bit) in names.iter().zip(control.iter()) {
+                    inputs.push((name, 1, *bit as u64));
+                }
+                let res = run(&mut gate, &inputs);
+                let out = res.get_number("out", 16);
+                assert_eq!(out, reference_alu(*x, *y, control), "{} x={:x} y={:x}", bits, x, y);
+                assert_eq!(out, expected(*x, *y) & 0xffff, "{} x={:x} y={:x}", bits, x, y);
+                assert_eq!(res.get("zr", 0), out == 0);
+                assert_eq!(res.get("ng", 0), out & 0x8000 != 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use sunho_computer::assembler::{assemble, assemble_file, parse_hack, to_hack};
+
+const MAX: &str = "
+// Computes R2 = max(R0, R1)
+   @R0
+   D=M              // D = first number
+   @R1
+   D=D-M            // D = first number - second number
+   @OUTPUT_FIRST
+   D;JGT            // if D>0 (first is greater) goto output_first
+   @R1
+   D=M              // D = second number
+   @OUTPUT_D
+   0;JMP            // goto output_d
+(OUTPUT_FIRST)
+   @R0
+   D=M              // D = first number
+(OUTPUT_D)
+   @R2
+   M=D              // M[2] = D (greatest number)
+(INFINITE_LOOP)
+   @INFINITE_LOOP
+   0;JMP            // infinite loop
+";
+
+const MAX_HACK: &str = "0000000000000000
+1111110000010000
+0000000000000001
+1111010011010000
+0000000000001010
+1110001100000001
+0000000000000001
+1111110000010000
+0000000000001100
+1110101010000111
+0000000000000000
+1111110000010000
+0000000000000010
+1110001100001000
+0000000000001110
+1110101010000111
+";
+
+#[test]
+fn max() {
+    let words = assemble("Max.asm", MAX).unwrap();
+    assert_eq!(to_hack(&words), MAX_HACK);
+    assert_eq!(parse_hack("Max.hack", MAX_HACK).unwrap(), words);
+}
+
+#[test]
+fn variables_and_predefined_symbols() {
+    let source = "@i\nM=1\n@sum\nM=0\n@i\nD=M\n@SCREEN\n@KBD\n@THAT\n@R15\n@32767\n";
+    let words = assemble("Sum.asm", source).unwrap();
+    assert_eq!(words, vec![16, 0xefc8, 17, 0xea88, 16, 0xfc10, 16384, 24576, 4, 15, 32767]);
+}
+
+#[test]
+fn compute_instructions() {
+    let cases = [
+        ("AMD=D|M;JNE", 0b1111010101111101),
+        ("MD=M+1", 0b1111110111011000),
+        ("D;JLE", 0b1110001100000110),
+        ("A=-1", 0b1110111010100000),
+        ("M=!A", 0b1110110001001000),
+        ("D=A-D", 0b1110000111010000),
+        ("0;JMP", 0b1110101010000111),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(assemble("x.asm", source).unwrap(), vec![*expected], "{}", source);
+    }
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("@1\nD=X\n", 2, "unknown computation 'X'"),
+        ("\n\n  D=D;JUMP\n", 3, "unknown jump 'JUMP'"),
+        ("MM=D\n", 1, "unknown destination 'MM'"),
+        ("@32768\n", 1, "invalid constant '32768'"),
+        ("(LOOP)\n@LOOP\n(LOOP)\n", 3, "symbol 'LOOP' is already defined"),
+        ("(1abc)\n", 1, "invalid label '1abc'"),
+        ("(END\n", 1, "expected ')' after label 'END'"),
+        ("@a-b\n", 1, "invalid symbol 'a-b'"),
+    ];
+    for (source, line, message) in cases.iter() {
+        let e = assemble("Bad.asm", source).unwrap_err();
+        assert_eq!(e.line, *line, "{}", source);
+        assert_eq!(e.message, *message);
+        assert_eq!(e.to_string(), format!("Bad.asm:{}: {}", line, message));
+    }
+    let e = assemble_file("/nonexistent/x.asm").unwrap_err();
+    assert!(e.to_string().starts_with("/nonexistent/x.asm: "), "{}", e);
+}
@@ -0,0 +1,120 @@
+use sunho_computer::gates::{prove_equivalence, variable_order, Bdd, GateFactory, BDD_FALSE, BDD_TRUE};
+
+#[test]
+fn operations() {
+    let mut bdd = Bdd::new();
+    let (x, y) = (bdd.variable(0), bdd.variable(1));
+    let and = bdd.and(x, y);
+    let nand = bdd.nand(y, x);
+    assert_eq!(bdd.not(nand), and);
+    assert_eq!(bdd.xor(x, x), BDD_FALSE);
+    let not_x = bdd.not(x);
+    assert_eq!(bdd.or(x, not_x), BDD_TRUE);
+    assert_eq!(bdd.size(and), 4);
+    assert_eq!(bdd.top(and), Some(0));
+    assert!(bdd.eval(and, &[true, true]));
+    assert!(!bdd.eval(and, &[true]));
+
+    let xor = bdd.xor(x, y);
+    let assignment = bdd.satisfy(xor).unwrap();
+    assert_eq!(assignment.into_iter().collect::<Vec<_>>(), vec![(0, false), (1, true)]);
+    assert_eq!(bdd.satisfy(BDD_FALSE), None);
+}
+
+#[test]
+fn order() {
+    let gate = GateFactory::new().build("alu");
+    let order = variable_order(&gate);
+    assert_eq!(order.len(), 38);
+    assert_eq!(order[0], ("zx".to_string(), 0));
+    assert_eq!(order[6], ("x".to_string(), 0));
+    assert_eq!(order[7], ("y".to_string(), 0));
+    assert_eq!(order[37], ("y".to_string(), 15));
+}
+
+#[test]
+fn wide_chips() {
+    let mut factory = GateFactory::new();
+    factory.load_hdl("Swapped.hdl", "CHIP Swapped {
+        IN a[16], b[16];
+        OUT out[16];
+        PARTS:
+        Add16(a=b, b=a, out=out);
+    }").unwrap();
+    factory.load_hdl("BadAdd16.hdl", "CHIP BadAdd16 {
+        IN a[16], b[16];
+        OUT out[16];
+        PARTS:
+        Add16(a=a, b=b, out[0..14]=out[0..14], out[15]=sum);
+        And(a=a[14], b=b[14], out=both);
+        Mux(a=sum, b=false, sel=both, out=out[15]);
+    }").unwrap();
+    let mut add16 = factory.build("add16");
+    assert!(prove_equivalence(&add16, &factory.build("swapped")).unwrap().is_none());
+
+    let mut bad = factory.build("badadd16");
+    let counterexample = prove_equivalence(&add16, &bad).unwrap().unwrap();
+    let inputs = counterexample.inputs.clone();
+    let expected = add16.eval(inputs.clone());
+    let actual = bad.eval(inputs.clone());
+    assert_eq!(counterexample.expected.to_string(), expected.to_string());
+    assert_eq!(counterexample.actual.to_string(), actual.to_string());
+    assert_ne!(expected.get_number("out", 16), actual.get_number("out", 16));
+    assert!(inputs.get("a", 14) && inputs.get("b", 14));
+}
+
+// The alu with x and y negated before they are zeroed, both results negated
+// before the f and no muxes and zr from the output pins. `sign` is the pin
+// zr reads for the sign bit.
+fn alu_hdl(name: &str, sign: &str) -> String {
+    format!("CHIP {} {{
+        IN x[16], y[16], zx, nx, zy, ny, f, no;
+        OUT out[16], zr, ng;
+        PARTS:
+        Not16(in=x, out=notx);
+        Mux16(a=x, b=notx, sel=nx, out=negx);
+        Mux16(a=false, b=true, sel=nx, out=nxall);
+        Mux16(a=negx, b=nxall, sel=zx, out=finalx);
+        Not16(in=y, out=noty);
+        Mux16(a=y, b=noty, sel=ny, out=negy);
+        Mux16(a=false, b=true, sel=ny, out=nyall);
+        Mux16(a=negy, b=nyall, sel=zy, out=finaly);
+        And16(a=finalx, b=finaly, out=and);
+        Add16(a=finalx, b=finaly, out=add);
+        Not16(in=and, out=notand);
+        Not16(in=add, out=notadd);
+        Mux4Way16(a=and, b=add, c=notand, d=notadd, sel[0]=f, sel[1]=no, out=out, out[15]=ng, out[0..7]=low, out[8..14]=high, out[15]=sign);
+        Or8Way(in=low, out=orlow);
+        Or8Way(in[0..6]=high, in[7]={}, out=orhigh);
+        Or(a=orlow, b=orhigh, out=nonzero);
+        Not(in=nonzero, out=zr);
+    }}", name, sign)
+}
+
+#[test]
+fn alu() {
+    let mut factory = GateFactory::new();
+    factory.load_hdl("MyAlu.hdl", &alu_hdl("MyAlu", "sign")).unwrap();
+    // zr misses the sign bit
+    factory.load_hdl("BadAlu.hdl", &alu_hdl("BadAlu", "false")).unwrap();
+    let mut alu = factory.build("alu");
+    assert!(prove_equivalence(&alu, &factory.build("myalu")).unwrap().is_none());
+
+    let mut bad = factory.build("badalu");
+    let counterexample = prove_equivalence(&alu, &bad).unwrap().unwrap();
+    let expected = alu.eval(counterexample.inputs.clone());
+    let actual = bad.eval(counterexample.inputs.clone());
+    assert_eq!(expected.get_number("out", 16), 0x8000);
+    assert_eq!(actual.get_number("out", 16), 0x8000);
+    assert!(!expected.get("zr", 0) && actual.get("zr", 0));
+    assert_eq!(counterexample.actual.to_string(), actual.to_string());
+}
+
+#[test]
+fn errors() {
+    let factory = GateFactory::new();
+    let e = prove_equivalence(&factory.build("bit"), &factory.build("bit")).unwrap_err();
+    assert_eq!(e.to_string(), "bit: is not combinational");
+    let e = prove_equivalence(&factory.build("add16"), &factory.build("inc16")).unwrap_err();
+    assert_eq!(e.to_string(), "add16: inputs differ from those of inc16");
+}
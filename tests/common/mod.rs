@@ -0,0 +1,5 @@
+// Linear congruential generator shared by the randomized tests
+pub fn random(seed: &mut u32) -> u64 {
+    *seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+    (*seed >> 8) as u64
+}
@@ -0,0 +1,197 @@
+use sunho_computer::gates::{Gate, GateFactory, PinValues};
+
+const ADD: [&str; 6] = [
+    "0000000000000010", // @2
+    "1110110000010000", // D=A
+    "0000000000000011", // @3
+    "1110000010010000", // D=D+A
+    "0000000000000000", // @0
+    "1110001100001000", // M=D
+];
+
+// R2 = max(R0, R1)
+const MAX: [&str; 16] = [
+    "0000000000000000", // @0
+    "1111110000010000", // D=M
+    "0000000000000001", // @1
+    "1111010011010000", // D=D-M
+    "0000000000001010", // @10
+    "1110001100000001", // D;JGT
+    "0000000000000001", // @1
+    "1111110000010000", // D=M
+    "0000000000001100", // @12
+    "1110101010000111", // 0;JMP
+    "0000000000000000", // @0
+    "1111110000010000", // D=M
+    "0000000000000010", // @2
+    "1110001100001000", // M=D
+    "0000000000001110", // @14
+    "1110101010000111", // 0;JMP
+];
+
+// copies the keyboard to the first word of the screen forever
+const ECHO: [&str; 6] = [
+    "0110000000000000", // @KBD
+    "1111110000010000", // D=M
+    "0100000000000000", // @SCREEN
+    "1110001100001000", // M=D
+    "0000000000000000", // @0
+    "1110101010000111", // 0;JMP
+];
+
+fn computer(program: &[&str]) -> Gate {
+    let mut gate = GateFactory::with_builtin_memory().build("computer");
+    let rom = gate.find_mut("rom32k").unwrap().memory_mut().unwrap();
+    for (i, line) in program.iter().enumerate() {
+        rom[i] = u16::from_str_radix(line, 2).unwrap();
+    }
+    gate
+}
+
+fn run(gate: &mut Gate, cycles: usize, reset: bool) {
+    for _ in 0..cycles {
+        let mut inputs = PinValues::new();
+        inputs.set("reset", 0, reset);
+        gate.run(inputs);
+    }
+}
+
+fn ram(gate: &mut Gate) -> &mut [u16] {
+    gate.find_mut("ram16k").unwrap().memory_mut().unwrap()
+}
+
+#[test]
+fn add() {
+    let mut gate = computer(&ADD);
+    run(&mut gate, 6, false);
+    assert_eq!(ram(&mut gate)[0], 5);
+    // reset starts the program over
+    ram(&mut gate)[0] = 0;
+    run(&mut gate, 1, true);
+    run(&mut gate, 6, false);
+    assert_eq!(ram(&mut gate)[0], 5);
+}
+
+#[test]
+fn max() {
+    for (a, b) in [(3, 5), (23456, 12345), (0xffff, 1), (7, 7)].iter() {
+        let mut gate = computer(&MAX);
+        ram(&mut gate)[0] = *a;
+        ram(&mut gate)[1] = *b;
+        run(&mut gate, 20, false);
+        let expected = if (*a as i16) > (*b as i16) { *a } else { *b };
+        assert_eq!(ram(&mut gate)[2], expected, "max({}, {})", a, b);
+    }
+}
+
+#[test]
+fn screen_and_keyboard() {
+    let mut gate = computer(&ECHO);
+    gate.find_mut("keyboard").unwrap().memory_mut().unwrap()[0] = 75;
+    run(&mut gate, 6, false);
+    assert_eq!(gate.find("screen").unwrap().memory().unwrap()[0], 75);
+    gate.find_mut("keyboard").unwrap().memory_mut().unwrap()[0] = 0;
+    run(&mut gate, 6, false);
+    assert_eq!(gate.find("screen").unwrap().memory().unwrap()[0], 0);
+    assert_eq!(ram(&mut gate)[0], 0);
+}
+
+fn cpu_step(gate: &mut Gate, in_m: u64, instruction: &str, reset: u64) -> (PinValues, PinValues) {
+    let mut inputs = PinValues::new();
+    inputs.set_number("inM", 16, in_m);
+    inputs.set_number("instruction", 16, u64::from_str_radix(instruction, 2).unwrap());
+    inputs.set_number("reset", 1, reset);
+    let before = gate.tick(inputs);
+    let after = gate.tock();
+    (before, after)
+}
+
+#[test]
+fn cpu() {
+    let mut gate = GateFactory::new().build("cpu");
+    // @12345
+    let (_, out) = cpu_step(&mut gate, 0, "0011000000111001", 0);
+    assert_eq!(out.get_number("addressM", 15), 12345);
+    assert_eq!(out.get_number("pc", 15), 1);
+    assert_eq!(out.get_number("writeM", 1), 0);
+    // D=A
+    cpu_step(&mut gate, 0, "1110110000010000", 0);
+    // MD=D+M, writes D + inM to M
+    let (before, _) = cpu_step(&mut gate, 11111, "1111000010011000", 0);
+    assert_eq!(before.get_number("outM", 16), 23456);
+    assert_eq!(before.get_number("writeM", 1), 1);
+    assert_eq!(before.get_number("addressM", 15), 12345);
+    // D;JGT jumps to A
+    let (_, out) = cpu_step(&mut gate, 0, "1110001100000001", 0);
+    assert_eq!(out.get_number("pc", 15), 12345);
+    // D;JLT does not
+    let (_, out) = cpu_step(&mut gate, 0, "1110001100000100", 0);
+    assert_eq!(out.get_number("pc", 15), 12346);
+    // A=-1, the address only keeps 15 bits
+    let (_, out) = cpu_step(&mut gate, 0, "1110111010100000", 0);
+    assert_eq!(out.get_number("addressM", 15), 0x7fff);
+    // reset
+    let (_, out) = cpu_step(&mut gate, 0, "1110101010000111", 1);
+    assert_eq!(out.get_number("pc", 15), 0);
+}
+
+#[test]
+fn memory() {
+    let mut gate = GateFactory::with_builtin_memory().build("memory");
+    for (address, value) in [(0, 1), (0x3fff, 2), (0x4000, 3), (0x5fff, 4)].iter() {
+        let mut inputs = PinValues::new();
+        inputs.set_number("in", 16, *value);
+        inputs.set_number("load", 1, 1);
+        inputs.set_number("address", 15, *address);
+        gate.run(inputs);
+    }
+    assert_eq!(gate.find("ram16k").unwrap().memory().unwrap()[0x3fff], 2);
+    assert_eq!(gate.find("screen").unwrap().memory().unwrap()[0x1fff], 4);
+    gate.find_mut("keyboard").unwrap().memory_mut().unwrap()[0] = 65;
+    for (address, value) in [(0, 1), (0x3fff, 2), (0x4000, 3), (0x5fff, 4), (0x6000, 65)].iter() {
+        let mut inputs = PinValues::new();
+        inputs.set_number("in", 16, 0);
+        inputs.set_number("load", 1, 0);
+        inputs.set_number("address", 15, *address);
+        assert_eq!(gate.eval(inputs).get_number("out", 16), *value, "address {:x}", address);
+    }
+}
+
+// Words changed through memory_mut show up on the next evaluation even when
+// no input of the part changed
+#[test]
+fn words_changed_outside() {
+    let mut gate = GateFactory::with_builtin_memory().build("memory");
+    let mut inputs = PinValues::new();
+    inputs.set_number("address", 15, 0x6000);
+    assert_eq!(gate.eval(inputs.clone()).get_number("out", 16), 0);
+    gate.find_mut("keyboard").unwrap().memory_mut().unwrap()[0] = 65;
+    assert_eq!(gate.eval(inputs.clone()).get_number("out", 16), 65);
+
+    let mut factory = GateFactory::with_builtin_memory();
+    factory.load_hdl("Program.hdl", "CHIP Program {
+        IN address[15];
+        OUT out[16];
+        PARTS:
+        ROM32K(address=address, out=out);
+    }").unwrap();
+    let mut gate = factory.build("program");
+    let mut inputs = PinValues::new();
+    inputs.set_number("address", 15, 3);
+    assert_eq!(gate.eval(inputs.clone()).get_number("out", 16), 0);
+    gate.find_mut("rom32k").unwrap().memory_mut().unwrap()[3] = 1234;
+    assert_eq!(gate.eval(inputs.clone()).get_number("out", 16), 1234);
+    assert_eq!(gate.run(inputs).get_number("out", 16), 1234);
+}
+
+// memory uses the builtin ram16k in every factory
+#[test]
+fn gate_level_factory() {
+    let mut gate = GateFactory::new().build("computer");
+    let rom = gate.find_mut("rom32k").unwrap().memory_mut().unwrap();
+    for (i, line) in ADD.iter().enumerate() {
+        rom[i] = u16::from_str_radix(line, 2).unwrap();
+    }
+    run(&mut gate, ADD.len(), false);
+    assert_eq!(ram(&mut gate)[0], 5);
+}
@@ -0,0 +1,89 @@
+use sunho_computer::gates::{to_dot, DotOptions, GateFactory};
+
+// ids of the declared nodes and the edges of a graph
+fn parse(dot: &str) -> (Vec<String>, Vec<(String, String)>) {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for line in dot.lines().map(|x| x.trim()) {
+        if let Some((from, rest)) = line.split_once(" -> ") {
+            let to = rest.split([' ', ';']).next().unwrap();
+            edges.push((from.to_string(), to.to_string()));
+        } else if line.starts_with('"') {
+            nodes.push(line.split(' ').next().unwrap().to_string());
+        }
+    }
+    (nodes, edges)
+}
+
+#[test]
+fn not() {
+    let gate = GateFactory::new().build("not");
+    let expected = r#"digraph "not" {
+    rankdir=LR;
+    subgraph cluster_0 {
+        label="not (not)";
+        "not:in" [label="in", shape=invhouse];
+        "not:out" [label="out", shape=house];
+        "not.nand" [label="nand", shape=box];
+    }
+    "not:in" -> "not.nand";
+    "not.nand" -> "not:out";
+}
+"#;
+    assert_eq!(to_dot(&gate, &DotOptions::default()), expected);
+}
+
+#[test]
+fn flatten() {
+    let gate = GateFactory::new().build("and");
+    let expected = r#"digraph "and" {
+    rankdir=LR;
+    "and:a" [label="a", shape=invhouse];
+    "and:b" [label="b", shape=invhouse];
+    "and:out" [label="out", shape=house];
+    "and.nand" [label="nand", shape=box];
+    "and.not.nand" [label="nand", shape=box];
+    "and:a" -> "and.nand";
+    "and:b" -> "and.nand";
+    "and.nand" -> "and.not.nand";
+    "and.not.nand" -> "and:out";
+}
+"#;
+    assert_eq!(to_dot(&gate, &DotOptions { depth: None, flatten: true }), expected);
+
+    // one box per primitive of the computer
+    let gate = GateFactory::with_builtin_memory().build("computer");
+    let dot = to_dot(&gate, &DotOptions { depth: None, flatten: true });
+    let (nodes, edges) = parse(&dot);
+    assert!(!dot.contains("subgraph"));
+    assert!(dot.contains(r#""computer.cpu.pc.register.bit_15.dff" [label="dff", shape=box];"#));
+    assert!(dot.contains(r#""computer.memory.ram16k" [label="ram16k", shape=box];"#));
+    for (from, to) in edges {
+        assert!(nodes.contains(&from) && nodes.contains(&to), "{} -> {}", from, to);
+    }
+}
+
+#[test]
+fn depth() {
+    let gate = GateFactory::new().build("add16");
+    let dot = to_dot(&gate, &DotOptions { depth: Some(0), flatten: false });
+    assert!(!dot.contains("cluster_1"));
+    assert!(dot.contains(r#""add16.fulladder_14" [label="fulladder", shape=box];"#));
+    assert!(dot.contains(r#""add16:a" -> "add16.fulladder_2" [label="3"];"#));
+    assert!(dot.contains(r#""add16.fulladder_14" -> "add16:out" [label="15"];"#));
+
+    let dot = to_dot(&gate, &DotOptions { depth: Some(1), flatten: false });
+    assert!(dot.contains(r#"label="add16.fulladder_14 (fulladder)";"#));
+    assert!(dot.contains(r#""add16.fulladder_14.halfadder_0" [label="halfadder", shape=box];"#));
+    assert!(dot.contains(r#""add16:carry" -> "add16.fulladder_14:c" [label="14"];"#));
+
+    // everything down to the primitives
+    let gate = GateFactory::with_builtin_memory().build("computer");
+    let dot = to_dot(&gate, &DotOptions::default());
+    let (nodes, edges) = parse(&dot);
+    assert!(dot.contains(r#""computer.cpu.alu.not16_0.not_15.nand" [label="nand", shape=box];"#));
+    assert!(dot.contains(r#""computer.cpu.pc.inc16:true" [label="true", shape=plaintext];"#));
+    for (from, to) in edges {
+        assert!(nodes.contains(&from) && nodes.contains(&to), "{} -> {}", from, to);
+    }
+}
@@ -0,0 +1,86 @@
+use sunho_computer::gates::{Gate, GateFactory, GateValidationError, PinKey, PinKind, PinValues};
+
+// Connects pins of the parent to pins of a part, internal pins are created
+// on first use
+fn part(gate: &mut Gate, factory: &GateFactory, name: &str, pins: &[(&str, &str)]) {
+    let index = gate.add_gate(factory.build(name));
+    for (child, parent) in pins {
+        if !gate.exists_pin(parent, 0) {
+            gate.insert_pin(PinKind::Internal, parent, 1, 0);
+        }
+        gate.connect_pins(index, &PinKey::new(parent, 0), &PinKey::new(child, 0)).unwrap();
+    }
+}
+
+fn inputs(name: &str, value: bool) -> PinValues {
+    let mut out = PinValues::new();
+    out.set(name, 0, value);
+    out
+}
+
+// out[t+1] = out[t] xor t, the DFF feeds its own input through the xor
+#[test]
+fn toggle() {
+    let factory = GateFactory::new();
+    let mut gate = Gate::new("toggle");
+    gate.insert_pin(PinKind::Input, "t", 1, 0);
+    gate.insert_pin(PinKind::Output, "out", 1, 0);
+    part(&mut gate, &factory, "xor", &[("a", "t"), ("b", "q"), ("out", "d")]);
+    part(&mut gate, &factory, "dff", &[("in", "d"), ("out", "q"), ("out", "out")]);
+    gate.compile().unwrap();
+    assert!(gate.is_stateful());
+
+    let mut expected = false;
+    for t in [true, true, false, true, false, false, true].iter() {
+        // the output only changes on the tock
+        assert_eq!(gate.tick(inputs("t", *t)).get("out", 0), expected);
+        expected ^= *t;
+        assert_eq!(gate.tock().get("out", 0), expected);
+        assert_eq!(gate.value("d", 0), Some(expected ^ *t));
+    }
+    // eval does not clock
+    assert_eq!(gate.eval(inputs("t", true)).get("out", 0), expected);
+    assert_eq!(gate.eval(inputs("t", true)).get("out", 0), expected);
+}
+
+#[test]
+fn combinational_loop() {
+    let factory = GateFactory::new();
+    let mut gate = Gate::new("loop");
+    gate.insert_pin(PinKind::Output, "out", 1, 0);
+    part(&mut gate, &factory, "not", &[("in", "x"), ("out", "x"), ("out", "out")]);
+    match gate.compile() {
+        Err(e @ GateValidationError::CombinationalLoop) => assert_eq!(e.to_string(), "combinational loop"),
+        x => panic!("expected a combinational loop, got {:?}", x)
+    }
+
+    // the same loop through a DFF is fine
+    let mut gate = Gate::new("blinker");
+    gate.insert_pin(PinKind::Output, "out", 1, 0);
+    part(&mut gate, &factory, "not", &[("in", "x"), ("out", "y")]);
+    part(&mut gate, &factory, "dff", &[("in", "y"), ("out", "x"), ("out", "out")]);
+    gate.compile().unwrap();
+    let out: Vec<bool> = (0..4).map(|_| gate.run(PinValues::new()).get("out", 0)).collect();
+    assert_eq!(out, vec![true, false, true, false]);
+}
+
+// parts without any input from the parent still run in a combinational chip
+#[test]
+fn constant_parts() {
+    let factory = GateFactory::new();
+    let mut gate = Gate::new("constants");
+    gate.insert_pin(PinKind::Input, "in", 1, 0);
+    gate.insert_pin(PinKind::Output, "one", 1, 0);
+    gate.insert_pin(PinKind::Output, "out", 1, 0);
+    let index = gate.add_gate(factory.build("not"));
+    gate.connect_constant(index, &PinKey::new("in", 0), false).unwrap();
+    gate.connect_pins(index, &PinKey::new("one", 0), &PinKey::new("out", 0)).unwrap();
+    part(&mut gate, &factory, "not", &[("in", "in"), ("out", "out")]);
+    gate.compile().unwrap();
+    assert!(!gate.is_stateful());
+    for value in [false, true].iter() {
+        let out = gate.eval(inputs("in", *value));
+        assert!(out.get("one", 0));
+        assert_eq!(out.get("out", 0), !*value);
+    }
+}
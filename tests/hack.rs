@@ -0,0 +1,137 @@
+use sunho_computer::assembler::assemble;
+use std::fs;
+use sunho_computer::hack::{load_program, HackEmulator, Lockstep, ROM_SIZE};
+
+// R2 = R0 * R1
+const MULT: &str = "
+    @R2
+    M=0
+(LOOP)
+    @R1
+    D=M
+    @END
+    D;JLE
+    @R0
+    D=M
+    @R2
+    M=D+M
+    @R1
+    M=M-1
+    @LOOP
+    0;JMP
+(END)
+    @END
+    0;JMP
+";
+
+// fills the screen while a key is held down
+const FILL: &str = "
+(LOOP)
+    @KBD
+    D=M
+    @LOOP
+    D;JEQ
+    @SCREEN
+    D=A
+    @R0
+    M=D
+(FILL)
+    @R0
+    A=M
+    M=-1
+    @R0
+    MD=M+1
+    @24576
+    D=D-A
+    @FILL
+    D;JLT
+    @LOOP
+    0;JMP
+";
+
+#[test]
+fn mult() {
+    let program = assemble("Mult.asm", MULT).unwrap();
+    for (a, b) in [(0, 5), (3, 7), (123, 45), (-2i16 as u16, 6)].iter() {
+        let mut emulator = HackEmulator::new(&program);
+        emulator.ram_mut()[0] = *a;
+        emulator.ram_mut()[1] = *b;
+        emulator.run(2000);
+        assert_eq!(emulator.ram()[2], a.wrapping_mul(*b));
+        assert_eq!(emulator.pc(), 14);
+    }
+}
+
+#[test]
+fn keyboard_and_screen() {
+    let program = assemble("Fill.asm", FILL).unwrap();
+    let mut emulator = HackEmulator::new(&program);
+    emulator.run(1000);
+    assert!(emulator.screen().iter().all(|x| *x == 0));
+    emulator.set_key(65);
+    emulator.run(100_000);
+    assert!(emulator.screen().iter().all(|x| *x == 0xffff));
+    assert_eq!(emulator.ram()[24576], 65);
+}
+
+#[test]
+fn lockstep_program() {
+    let program = assemble("Mult.asm", MULT).unwrap();
+    let mut both = Lockstep::new(&program);
+    both.emulator.ram_mut()[0] = 6;
+    both.emulator.ram_mut()[1] = 7;
+    both.computer.find_mut("ram16k").unwrap().memory_mut().unwrap()[..2].copy_from_slice(&[6, 7]);
+    both.run(100).unwrap();
+    assert_eq!(both.emulator.ram()[2], 42);
+    both.reset().unwrap();
+    assert_eq!(both.emulator.pc(), 0);
+    both.run(20).unwrap();
+}
+
+#[test]
+fn lockstep_every_instruction() {
+    // random words exercise every computation, including the ones without a
+    // mnemonic, and memory mapped addresses
+    let mut seed: u32 = 12345;
+    let mut program = Vec::new();
+    for _ in 0..256 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        program.push((seed >> 8) as u16);
+    }
+    let mut both = Lockstep::new(&program);
+    both.set_key(42);
+    both.run(500).unwrap();
+}
+
+#[test]
+fn divergence() {
+    let program = assemble("Mult.asm", MULT).unwrap();
+    let mut both = Lockstep::new(&program);
+    both.emulator.ram_mut()[0] = 3;
+    both.emulator.ram_mut()[1] = 2;
+    let error = both.run(100).unwrap_err();
+    assert_eq!(error.cycle, 1);
+    assert_eq!(error.location, "RAM[0]");
+    assert_eq!((error.emulator, error.gates), (3, 0));
+
+    let mut both = Lockstep::new(&program);
+    both.run(10).unwrap();
+    both.computer.find_mut("ram16k").unwrap().memory_mut().unwrap()[1] = 1;
+    let error = both.run(100).unwrap_err();
+    assert_eq!(error.cycle, 11);
+    assert_eq!(error.location, "RAM[1]");
+    assert_eq!(error.to_string(), "cycle 11: RAM[1] is 0 in the emulator but 1 in the gates");
+}
+
+#[test]
+fn load_errors() {
+    let e = load_program("/nonexistent/Prog.hack").unwrap_err();
+    assert!(e.to_string().starts_with("/nonexistent/Prog.hack: "), "{}", e);
+    let path = std::env::temp_dir().join(format!("sunho-computer-hack-{}.hack", std::process::id()));
+    fs::write(&path, "0000000000000000\n".repeat(ROM_SIZE + 1)).unwrap();
+    let e = load_program(&path).unwrap_err();
+    assert_eq!(e.to_string(), format!("{}: program of 32769 words does not fit in the ROM of 32768 words", path.display()));
+    fs::write(&path, "0000000000000000\n".repeat(ROM_SIZE)).unwrap();
+    assert_eq!(load_program(&path).unwrap().len(), ROM_SIZE);
+    fs::remove_file(&path).unwrap();
+}
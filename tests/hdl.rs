@@ -0,0 +1,153 @@
+use std::fs;
+use sunho_computer::gates::{parse_hdl, GateFactory, HdlError, PinValues};
+
+fn eval(factory: &GateFactory, chip: &str, inputs: &[(&str, i64, u64)], output: (&str, i64)) -> u64 {
+    let mut values = PinValues::new();
+    for (name, size, value) in inputs {
+        values.set_number(name, *size, *value);
+    }
+    factory.build(chip).eval(values).get_number(output.0, output.1)
+}
+
+fn load_error(source: &str) -> String {
+    GateFactory::new().load_hdl("Bad.hdl", source).unwrap_err().to_string()
+}
+
+#[test]
+fn sub_bus() {
+    let mut factory = GateFactory::new();
+    let name = factory.load_hdl("SwapBytes.hdl", "CHIP SwapBytes {
+        IN in[16];
+        OUT out[16], high[8];
+        PARTS:
+        Or16(a[0..7]=in[8..15], a[8..15]=in[0..7], b=false, out=out, out[0..7]=high);
+    }").unwrap();
+    assert_eq!(name, "swapbytes");
+    assert_eq!(eval(&factory, "swapbytes", &[("in", 16, 0x1234)], ("out", 16)), 0x3412);
+    assert_eq!(eval(&factory, "swapbytes", &[("in", 16, 0xabcd)], ("high", 8)), 0xab);
+
+    let chip = parse_hdl("SwapBytes.hdl", "CHIP S { IN in[16]; OUT out; PARTS: Not(in=in[3], out=out); }").unwrap();
+    assert_eq!(chip.parts[0].connections[0].outer.range, Some((3, 3)));
+    assert_eq!(chip.parts[0].connections[0].inner.range, None);
+}
+
+#[test]
+fn constants() {
+    let mut factory = GateFactory::new();
+    factory.load_hdl("Low.hdl", "CHIP Low {
+        IN in[16];
+        OUT out[16], one, zero;
+        PARTS:
+        And16(a=in, b[0..3]=true, b[4..15]=false, out=out);
+        Or(a=false, b=true, out=one);
+        And(a=true, b=false, out=zero);
+    }").unwrap();
+    for value in [0, 0xffff, 0x1234, 0x0f0f].iter() {
+        assert_eq!(eval(&factory, "low", &[("in", 16, *value)], ("out", 16)), value & 0xf);
+    }
+    assert_eq!(eval(&factory, "low", &[], ("one", 1)), 1);
+    assert_eq!(eval(&factory, "low", &[], ("zero", 1)), 0);
+}
+
+#[test]
+fn builtin() {
+    let mut factory = GateFactory::new();
+    assert_eq!(factory.load_hdl("MyNand.hdl", "CHIP MyNand { IN a, b; OUT out; BUILTIN Nand; }").unwrap(), "mynand");
+    for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)].iter() {
+        assert_eq!(eval(&factory, "mynand", &[("a", 1, *a), ("b", 1, *b)], ("out", 1)), 1 - (a & b));
+    }
+    // a builtin of the same name keeps the native chip
+    factory.load_hdl("DFF.hdl", "CHIP DFF { IN in; OUT out; BUILTIN DFF; CLOCKED in; }").unwrap();
+    assert!(factory.build("dff").is_stateful());
+
+    assert_eq!(load_error("CHIP MyNand { IN a, b; OUT out; BUILTIN Nope; }"),
+        "Bad.hdl:1:1: no builtin chip named Nope");
+    assert_eq!(load_error("CHIP MyNand { IN a; OUT out; BUILTIN Nand; }"),
+        "Bad.hdl:1:1: pins of MyNand do not match the builtin chip Nand");
+}
+
+#[test]
+fn load_dir() {
+    let directory = std::env::temp_dir().join(format!("sunho-computer-hdl-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    // files are read in name order but each chip uses one of a later file
+    fs::write(directory.join("A.hdl"), "CHIP Nand3 {
+        IN a, b, c;
+        OUT out;
+        PARTS:
+        And3(a=a, b=b, c=c, out=and);
+        Not(in=and, out=out);
+    }").unwrap();
+    fs::write(directory.join("B.hdl"), "CHIP And3 {
+        IN a, b, c;
+        OUT out;
+        PARTS:
+        MyAnd(a=a, b=b, out=ab);
+        MyAnd(a=ab, b=c, out=out);
+    }").unwrap();
+    fs::write(directory.join("C.hdl"), "CHIP MyAnd { IN a, b; OUT out; PARTS: And(a=a, b=b, out=out); }").unwrap();
+    fs::write(directory.join("notes.txt"), "not hdl").unwrap();
+    let mut factory = GateFactory::new();
+    assert_eq!(factory.load_hdl_dir(&directory).unwrap(), vec!["myand", "and3", "nand3"]);
+    for i in 0..8 {
+        let out = eval(&factory, "nand3", &[("a", 1, i & 1), ("b", 1, (i >> 1) & 1), ("c", 1, i >> 2)], ("out", 1));
+        assert_eq!(out, (i != 7) as u64, "{}", i);
+    }
+
+    fs::write(directory.join("C.hdl"), "CHIP MyAnd { IN a, b; OUT out; PARTS: Nand3(a=a, b=b, c=true, out=out); }").unwrap();
+    let error = GateFactory::new().load_hdl_dir(&directory).unwrap_err();
+    // found at the part of nand3 closing the loop and3 -> myand -> nand3 -> and3
+    assert_eq!(error.message, "chip And3 is defined recursively");
+    assert_eq!(error.file, directory.join("A.hdl").display().to_string());
+    assert_eq!((error.line, error.column), (5, 9));
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("CHIP Bad {\n    IN a;\n    OUT out;\n    PARTS:\n    Nope(in=a, out=out);\n}",
+            5, 5, "chip Nope is not defined"),
+        ("CHIP Bad {\n    IN a;\n    OUT out;\n    PARTS:\n    Not(input=a, out=out);\n}",
+            5, 9, "chip Not has no pin named input"),
+        ("CHIP Bad {\n    IN a[8];\n    OUT out[16];\n    PARTS:\n    Not16(in=a, out=out);\n}",
+            5, 14, "width mismatch: in is 16 bits but a is 8 bits"),
+        ("CHIP Bad {\n    IN a, b;\n    OUT out;\n    PARTS:\n    Not(in=a, out=out);\n    Not(in=b, out=out);\n}",
+            6, 19, "output pin out[0] is driven twice"),
+        ("CHIP Bad {\n    IN a[16];\n    OUT out[16];\n    PARTS:\n    Not16(in=a[8..16], out=out);\n}",
+            5, 14, "bit range 8..16 is out of bounds of a[16]"),
+        ("CHIP Bad {\n    IN a;\n    OUT out;\n    PARTS:\n    Not(in=x, out=out);\n}",
+            5, 12, "internal pin x is never driven"),
+        ("CHIP Bad {\n    IN a;\n    OUT out;\n    PARTS:\n    Not(in=a, out=out)\n}",
+            6, 1, "expected ';' but found '}'"),
+    ];
+    for (source, line, column, message) in cases.iter() {
+        let expected = HdlError { file: "Bad.hdl".to_string(), line: *line, column: *column, message: message.to_string() };
+        assert_eq!(GateFactory::new().load_hdl("Bad.hdl", source), Err(expected), "{}", source);
+    }
+    assert_eq!(load_error("CHIP Bad {\n    IN a;\n    OUT out;\n    PARTS:\n    Nope(in=a, out=out);\n}"),
+        "Bad.hdl:5:5: chip Nope is not defined");
+}
+
+#[test]
+fn loops() {
+    let source = "CHIP Loop {\n    IN a;\n    OUT out;\n    PARTS:\n    And(a=a, b=y, out=out);\n    Not(in=y, out=x);\n    Not(in=x, out=y);\n}";
+    let error = GateFactory::new().load_hdl("Loop.hdl", source).unwrap_err();
+    assert_eq!(error.to_string(), "Loop.hdl:6:5: combinational loop in chip loop");
+    // a loop through a register is not combinational
+    let mut factory = GateFactory::new();
+    factory.load_hdl("Toggle.hdl", "CHIP Toggle {
+        IN load;
+        OUT out;
+        PARTS:
+        Not(in=state, out=next);
+        Bit(in=next, load=load, out=state, out=out);
+    }").unwrap();
+    let mut toggle = factory.build("toggle");
+    let mut values = PinValues::new();
+    values.set_number("load", 1, 1);
+    for i in 0..4 {
+        toggle.tick(values.clone());
+        assert_eq!(toggle.tock().get_number("out", 1), (i + 1) % 2);
+    }
+}
@@ -0,0 +1,282 @@
+use sunho_computer::assembler::assemble;
+use sunho_computer::gates::{GateFactory, PinValues};
+use sunho_computer::jack::{compile, compile_path, to_vm};
+use sunho_computer::vm::{parse_vm, translate};
+
+fn vm(class: &str, source: &str) -> String {
+    to_vm(&compile(class, source).unwrap())
+}
+
+#[test]
+fn seven() {
+    let source = "
+/** Computes 1 + (2 * 3) */
+class Main {
+   function void main() {
+      do Output.printInt(1 + (2 * 3));
+      return;
+   }
+}
+";
+    let expected = "function Main.main 0
+push constant 1
+push constant 2
+push constant 3
+call Math.multiply 2
+add
+call Output.printInt 1
+pop temp 0
+push constant 0
+return
+";
+    assert_eq!(vm("Main", source), expected);
+}
+
+#[test]
+fn statements() {
+    let source = "
+class Main {
+    static boolean flag;
+    function int loop(int n) {
+        var int i;
+        while (i < n) {
+            if (flag) { let i = i + 1; } else { let flag = ~flag; }
+            if (i = 3) { return -i; }
+        }
+        return i;
+    }
+}
+";
+    let expected = "function Main.loop 1
+label WHILE_EXP0
+push local 0
+push argument 0
+lt
+not
+if-goto WHILE_END0
+push static 0
+if-goto IF_TRUE0
+goto IF_FALSE0
+label IF_TRUE0
+push local 0
+push constant 1
+add
+pop local 0
+goto IF_END0
+label IF_FALSE0
+push static 0
+not
+pop static 0
+label IF_END0
+push local 0
+push constant 3
+eq
+if-goto IF_TRUE1
+goto IF_FALSE1
+label IF_TRUE1
+push local 0
+neg
+return
+label IF_FALSE1
+goto WHILE_EXP0
+label WHILE_END0
+push local 0
+return
+";
+    assert_eq!(vm("Main", source), expected);
+}
+
+#[test]
+fn objects_arrays_and_strings() {
+    let source = "
+class Point {
+    field int x, y;
+    static int count;
+    constructor Point new(int ax, int ay) {
+        let x = ax;
+        let y = ay;
+        let count = count + 1;
+        return this;
+    }
+    method int getX() { return x; }
+    method void copy(Point other, Array a) {
+        let x = other.getX();
+        let a[x] = a[y];
+        do print();
+        do Output.printString(\"hi\");
+        return;
+    }
+    method void print() { return; }
+}
+";
+    let expected = "function Point.new 0
+push constant 2
+call Memory.alloc 1
+pop pointer 0
+push argument 0
+pop this 0
+push argument 1
+pop this 1
+push static 0
+push constant 1
+add
+pop static 0
+push pointer 0
+return
+function Point.getX 0
+push argument 0
+pop pointer 0
+push this 0
+return
+function Point.copy 0
+push argument 0
+pop pointer 0
+push argument 1
+call Point.getX 1
+pop this 0
+push argument 2
+push this 0
+add
+push argument 2
+push this 1
+add
+pop pointer 1
+push that 0
+pop temp 0
+pop pointer 1
+push temp 0
+pop that 0
+push pointer 0
+call Point.print 1
+pop temp 0
+push constant 2
+call String.new 1
+push constant 104
+call String.appendChar 2
+push constant 105
+call String.appendChar 2
+call Output.printString 1
+pop temp 0
+push constant 0
+return
+function Point.print 0
+push argument 0
+pop pointer 0
+push constant 0
+return
+";
+    assert_eq!(vm("Point", source), expected);
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("Main", "class Main {\n  function void main() {\n    let x = 1;\n    return;\n  }\n}\n", 3, 9, "undefined variable 'x'"),
+        ("Main", "class Main {\n  function void main() {\n    var int x;\n    let x = 1\n  }\n}\n", 5, 3, "expected ';', found '}'"),
+        ("Main", "class Main {\n  method void f() { return; }\n  function void main() { do f(); return; }\n}\n", 3, 29, "method 'f' cannot be called from a function"),
+        ("Main", "class Main {\n  field int x;\n  function int main() { return x; }\n}\n", 3, 32, "field 'x' cannot be used in a function"),
+        ("Main", "class Main {\n  function void main() { do g(); return; }\n}\n", 2, 29, "undefined subroutine 'g' in class Main"),
+        ("Main", "class Main {\n  function void main() { var int a, a; return; }\n}\n", 2, 37, "'a' is already declared"),
+        ("Main", "class Main {\n  function int main() { return; }\n}\n", 2, 25, "missing return value"),
+        ("Main", "class Main {\n  function int main() { return 40000; }\n}\n", 2, 32, "integer constant 40000 is too large"),
+        ("Main", "class Main {\n  function void main() {\n    do Output.printString(\"abc);\n", 3, 27, "unterminated string"),
+        ("Main", "class Main {\n  function void main() { var int a; do a.f(); return; }\n}\n", 2, 40, "'a' of type int has no methods"),
+        ("Main", "class Other {\n}\n", 1, 7, "class Other must be declared in Other.jack"),
+    ];
+    for (class, source, line, column, message) in cases.iter() {
+        let e = compile(class, source).unwrap_err();
+        assert_eq!((e.line, e.column, e.message.as_str()), (*line, *column, *message), "{}", source);
+        assert_eq!(e.to_string(), format!("{}:{}:{}: {}", class, line, column, message));
+    }
+    // files that can not be read are reported by path
+    for path in ["/nonexistent/Main.jack", "/nonexistent"].iter() {
+        let e = compile_path(path).unwrap_err();
+        assert_eq!((e.class.as_str(), e.line), (*path, 0));
+        assert!(e.to_string().starts_with(&format!("{}: ", path)), "{}", e);
+    }
+}
+
+const SYS: &str = "
+class Sys {
+    function void init() {
+        var Array ram;
+        do Memory.init();
+        let ram = 0;
+        let ram[8000] = Main.main();
+        while (true) {}
+        return;
+    }
+}
+";
+
+// a bump allocator instead of the OS
+const MEMORY: &str = "
+class Memory {
+    static int free;
+    function void init() { let free = 2048; return; }
+    function int alloc(int size) {
+        var int block;
+        let block = free;
+        let free = free + size;
+        return block;
+    }
+}
+";
+
+const COUNTER: &str = "
+class Counter {
+    field int count, step;
+    constructor Counter new(int s) { let count = 0; let step = s; return this; }
+    method void advance() { let count = count + step; return; }
+    method int get() { return count; }
+}
+";
+
+const MAIN: &str = "
+class Main {
+    function int main() {
+        var Counter c;
+        var Array a;
+        var int i, sum;
+        let c = Counter.new(3);
+        let a = Memory.alloc(5);
+        let i = 0;
+        while (i < 5) {
+            do c.advance();
+            let a[i] = c.get();
+            let i = i + 1;
+        }
+        let sum = 0;
+        let i = 0;
+        while (~(i = 5)) {
+            if ((a[i] > 6) & (a[i] < 15)) {
+                let sum = sum + a[i];
+            } else {
+                let sum = sum - 1;
+            }
+            let i = i + 1;
+        }
+        return sum;
+    }
+}
+";
+
+#[test]
+fn runs_on_the_computer() {
+    let mut files = Vec::new();
+    for (class, source) in [("Sys", SYS), ("Memory", MEMORY), ("Counter", COUNTER), ("Main", MAIN)].iter() {
+        files.push(parse_vm(&format!("{}.vm", class), &vm(class, source)).unwrap());
+    }
+    let words = assemble("Main.asm", &translate(&files).unwrap()).unwrap();
+    let mut gate = GateFactory::with_builtin_memory().build("computer");
+    gate.find_mut("rom32k").unwrap().memory_mut().unwrap()[..words.len()].copy_from_slice(&words);
+    for _ in 0..8000 {
+        let mut inputs = PinValues::new();
+        inputs.set("reset", 0, false);
+        gate.run(inputs);
+    }
+    let ram = gate.find("ram16k").unwrap().memory().unwrap();
+    assert_eq!(&ram[2048..2050], &[15, 3]);
+    assert_eq!(&ram[2050..2055], &[3, 6, 9, 12, 15]);
+    assert_eq!(ram[8000], 18);
+}
@@ -0,0 +1,123 @@
+use sunho_computer::gates::{parse_hdl, ChipDefinition, GateFactory, PinValues};
+
+const XOR: &str = "CHIP Xor2 {
+    IN a, b;
+    OUT out;
+    PARTS:
+    Not(in=a, out=nota);
+    Not(in=b, out=notb);
+    And(a=a, b=notb, out=w1);
+    And(a=nota, b=b, out=w2);
+    Or(a=w1, b=w2, out=out);
+}";
+
+#[test]
+fn round_trip() {
+    let chip = parse_hdl("Xor2.hdl", XOR).unwrap();
+    let json = chip.to_json();
+    let loaded = ChipDefinition::from_json("Xor2.json", &json).unwrap();
+    assert_eq!(loaded.to_json(), json);
+    assert_eq!(loaded.name, "Xor2");
+    assert_eq!(loaded.parts.len(), 5);
+    assert_eq!(loaded.parts[2].connections[1].outer.name, "notb");
+
+    let mut factory = GateFactory::new();
+    assert_eq!(factory.load_json("Xor2.json", &json).unwrap(), "xor2");
+    let mut gate = factory.build("xor2");
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+        let mut inputs = PinValues::new();
+        inputs.set("a", 0, *a);
+        inputs.set("b", 0, *b);
+        assert_eq!(gate.eval(inputs).get("out", 0), a != b);
+    }
+}
+
+#[test]
+fn schema() {
+    let json = r#"{
+        "name": "Low",
+        "inputs": [{"name": "in", "width": 16}],
+        "outputs": [{"name": "out", "width": 4}, {"name": "zero"}],
+        "parts": [
+            {"chip": "Or", "connections": [
+                {"inner": {"name": "a"}, "outer": {"name": "in", "range": [0, 0]}},
+                {"inner": {"name": "b"}, "outer": {"name": "false"}},
+                {"inner": {"name": "out"}, "outer": {"name": "out", "range": [0, 0]}}
+            ]},
+            {"chip": "Not16", "connections": [
+                {"inner": {"name": "in", "range": [0, 2]}, "outer": {"name": "in", "range": [1, 3]}},
+                {"inner": {"name": "out", "range": [0, 0]}, "outer": {"name": "inv"}}
+            ]},
+            {"chip": "Not", "connections": [
+                {"inner": {"name": "in"}, "outer": {"name": "inv"}},
+                {"inner": {"name": "out"}, "outer": {"name": "out", "range": [1, 1]}}
+            ]},
+            {"chip": "Or8Way", "connections": [
+                {"inner": {"name": "in", "range": [0, 3]}, "outer": {"name": "in", "range": [0, 3]}},
+                {"inner": {"name": "out"}, "outer": {"name": "notzero"}}
+            ]},
+            {"chip": "Not", "connections": [
+                {"inner": {"name": "in"}, "outer": {"name": "notzero"}},
+                {"inner": {"name": "out"}, "outer": {"name": "zero"}}
+            ]}
+        ]
+    }"#;
+    let mut factory = GateFactory::new();
+    factory.load_json("Low.json", json).unwrap();
+    let mut gate = factory.build("low");
+    let mut inputs = PinValues::new();
+    inputs.set_number("in", 16, 0b0011);
+    let out = gate.eval(inputs);
+    assert!(out.get("out", 0));
+    assert!(out.get("out", 1));
+    assert!(!out.get("zero", 0));
+
+    let chip = ChipDefinition::from_json("Low.json", json).unwrap();
+    assert_eq!(chip.inputs[0].size, 16);
+    assert_eq!(chip.outputs[1].size, 1);
+    assert_eq!(chip.parts[1].connections[0].outer.range, Some((1, 3)));
+
+    // builtin chips keep the builtin field instead of parts
+    let chip = parse_hdl("Nand2.hdl", "CHIP Nand2 { IN a, b; OUT out; BUILTIN Nand; }").unwrap();
+    let json = chip.to_json();
+    assert!(json.contains("\"builtin\": \"Nand\""));
+    assert!(!json.contains("parts"));
+    assert_eq!(GateFactory::new().load_json("Nand2.json", &json).unwrap(), "nand2");
+}
+
+#[test]
+fn values() {
+    let mut values = PinValues::new();
+    values.set_number("a", 4, 0b0101);
+    values.set("b", 0, true);
+    values.set("c", 2, false);
+    let json = values.to_json();
+    assert_eq!(json, r#"{"a":[true,false,true,false],"b":[true],"c":[null,null,false]}"#);
+    let loaded = PinValues::from_json("values.json", &json).unwrap();
+    assert_eq!(loaded.to_json(), json);
+    assert_eq!(loaded.get_number("a", 4), 0b0101);
+    assert!(!loaded.contains("c", 0));
+    assert!(!loaded.get("c", 2));
+}
+
+#[test]
+fn errors() {
+    let e = ChipDefinition::from_json("bad.json", "{\n  \"name\": }").unwrap_err();
+    assert_eq!((e.file.as_str(), e.line), ("bad.json", 2));
+    assert!(e.to_string().starts_with("bad.json:2:"));
+
+    let e = ChipDefinition::from_json("bad.json", r#"{"name": "X", "parts": [{"chip": "Not", "connections": [{"inner": {"name": "in"}}]}]}"#).unwrap_err();
+    assert_eq!(e.to_string(), "bad.json: parts[0].connections[0]: missing field outer");
+
+    let e = ChipDefinition::from_json("bad.json", r#"{"name": "X", "inputs": [{"name": "a", "width": -1}]}"#).unwrap_err();
+    assert_eq!(e.message, "inputs[0].width: expected a non negative integer");
+
+    let e = PinValues::from_json("values.json", r#"{"a": [1]}"#).unwrap_err();
+    assert_eq!(e.message, "a[0]: expected true, false or null");
+
+    // errors of the chip itself come from the hdl checks
+    let json = r#"{"name": "X", "inputs": [{"name": "a"}], "outputs": [{"name": "out"}],
+        "parts": [{"chip": "Missing", "connections": []}]}"#;
+    let e = GateFactory::new().load_json("X.json", json).unwrap_err();
+    assert_eq!(e.to_string(), "X.json: chip Missing is not defined");
+}
@@ -0,0 +1,141 @@
+use sunho_computer::assembler::assemble;
+use sunho_computer::gates::{GateFactory, PinValues};
+use sunho_computer::hack::HackEmulator;
+use sunho_computer::jack::{compile, to_vm};
+use sunho_computer::keyboard::{key_code, KeyEvent, KeyScript};
+use sunho_computer::vm::{parse_vm, VmEmulator};
+
+// copies the keyboard to the first word of the screen forever
+const ECHO: &str = "
+(LOOP)
+    @KBD
+    D=M
+    @SCREEN
+    M=D
+    @LOOP
+    0;JMP
+";
+
+fn event(cycle: u64, key: u16, pressed: bool) -> KeyEvent {
+    KeyEvent { cycle, key, pressed }
+}
+
+#[test]
+fn keys() {
+    assert_eq!(key_code("A"), Some(65));
+    assert_eq!(key_code("a"), Some(97));
+    assert_eq!(key_code("Newline"), Some(128));
+    assert_eq!(key_code("space"), Some(32));
+    assert_eq!(key_code("f12"), Some(152));
+    assert_eq!(key_code("140"), Some(140));
+    assert_eq!(key_code("f13"), None);
+    assert_eq!(key_code("ctrl"), None);
+}
+
+#[test]
+fn parse() {
+    let source = "
+# cycle action key
+10 press A
+20 release A   # comment
+30 press left
+5 type 2 \"a#\\n\"
+";
+    let script = KeyScript::parse("keys.txt", source).unwrap();
+    assert_eq!(script.events(), &[
+        event(5, 97, true),
+        event(7, 97, false),
+        event(9, 35, true),
+        event(10, 65, true),
+        event(11, 35, false),
+        event(13, 128, true),
+        event(15, 128, false),
+        event(20, 65, false),
+        event(30, 130, true)
+    ]);
+
+    let errors = [
+        ("x press A", "keys.txt:1: invalid cycle 'x'"),
+        ("1 press", "keys.txt:1: 'press' expects a key"),
+        ("\n1 press ctrl", "keys.txt:2: unknown key 'ctrl'"),
+        ("1 hold A", "keys.txt:1: unknown action 'hold'"),
+        ("1 type \"a\"", "keys.txt:1: 'type' expects a number of cycles and a text"),
+        ("1 type 5 abc", "keys.txt:1: invalid text, expected \"...\"")
+    ];
+    for (source, message) in errors.iter() {
+        assert_eq!(KeyScript::parse("keys.txt", source).unwrap_err().to_string(), *message);
+    }
+    let error = KeyScript::load("/nonexistent/keys.txt").err().unwrap();
+    assert!(error.to_string().starts_with("/nonexistent/keys.txt: "), "{}", error);
+}
+
+#[test]
+fn held_keys() {
+    let mut script = KeyScript::new(vec![
+        event(0, 65, true),
+        event(2, 66, true),
+        event(4, 66, false),
+        event(6, 65, false)
+    ]);
+    let keys: Vec<Option<u16>> = (0..8).map(|x| script.advance(x)).collect();
+    assert_eq!(keys, vec![Some(65), None, Some(66), None, Some(65), None, Some(0), None]);
+    assert!(script.is_done());
+}
+
+#[test]
+fn emulator() {
+    let program = assemble("Echo.asm", ECHO).unwrap();
+    let mut script = KeyScript::parse("keys.txt", "10 press A\n50 release A\n").unwrap();
+    let mut emulator = HackEmulator::new(&program);
+    let mut seen = Vec::new();
+    for _ in 0..100 {
+        script.drive(emulator.cycles(), &mut emulator);
+        emulator.step();
+        seen.push(emulator.screen()[0]);
+    }
+    assert_eq!(seen[5], 0);
+    assert_eq!(seen[30], 65);
+    assert_eq!(seen[99], 0);
+}
+
+#[test]
+fn gates() {
+    let program = assemble("Echo.asm", ECHO).unwrap();
+    let mut computer = GateFactory::with_builtin_memory().build("computer");
+    computer.find_mut("rom32k").unwrap().memory_mut().unwrap()[..program.len()].copy_from_slice(&program);
+    let mut script = KeyScript::parse("keys.txt", "0 press 131\n20 release 131\n").unwrap();
+    let mut seen = Vec::new();
+    for cycle in 0..30 {
+        script.drive(cycle, &mut computer);
+        let mut inputs = PinValues::new();
+        inputs.set("reset", 0, false);
+        computer.run(inputs);
+        seen.push(computer.find("screen").unwrap().memory().unwrap()[0]);
+    }
+    assert_eq!(seen[10], 131);
+    assert_eq!(seen[29], 0);
+}
+
+#[test]
+fn jack_program() {
+    let source = "
+class Main {
+    function void main() {
+        var int x;
+        let x = Keyboard.readInt(\"x? \");
+        do Memory.poke(8000, x * 2);
+        return;
+    }
+}
+";
+    let main = parse_vm("Main.vm", &to_vm(&compile("Main", source).unwrap())).unwrap();
+    let mut emulator = VmEmulator::new(&[main]).unwrap();
+    emulator.boot().unwrap();
+    let mut script = KeyScript::parse("keys.txt", "1000 type 50 \"-12\\b3\\n\"").unwrap();
+    while !emulator.is_halted() && emulator.steps() < 100_000 {
+        script.drive(emulator.steps(), &mut emulator);
+        emulator.step().unwrap();
+    }
+    assert!(script.is_done());
+    assert_eq!(emulator.ram()[8000] as i16, -26);
+}
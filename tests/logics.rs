@@ -0,0 +1,64 @@
+use sunho_computer::gates::{GateFactory, PinValues};
+
+// Runs `name` for every assignment of the single bit `inputs` and checks
+// each output against `expected`.
+fn check_truth_table(name: &str, inputs: &[&str], outputs: &[&str], expected: fn(&[bool]) -> Vec<bool>) {
+    let factory = GateFactory::new();
+    let mut gate = factory.build(name);
+    for row in 0..(1 << inputs.len()) {
+        let bits: Vec<bool> = (0..inputs.len()).map(|i| (row >> i) & 1 == 1).collect();
+        let mut values = PinValues::new();
+        for (input, bit) in inputs.iter().zip(&bits) {
+            values.set(input, 0, *bit);
+        }
+        let res = gate.run(values);
+        for (output, bit) in outputs.iter().zip(expected(&bits)) {
+            assert_eq!(res.get(output, 0), bit, "{} {}={:?}", name, output, bits);
+        }
+    }
+}
+
+#[test]
+fn nand() {
+    check_truth_table("nand", &["a", "b"], &["out"], |x| vec![!(x[0] && x[1])]);
+}
+
+#[test]
+fn not() {
+    check_truth_table("not", &["in"], &["out"], |x| vec![!x[0]]);
+}
+
+#[test]
+fn and() {
+    check_truth_table("and", &["a", "b"], &["out"], |x| vec![x[0] && x[1]]);
+}
+
+#[test]
+fn or() {
+    check_truth_table("or", &["a", "b"], &["out"], |x| vec![x[0] || x[1]]);
+}
+
+#[test]
+fn xor() {
+    check_truth_table("xor", &["a", "b"], &["out"], |x| vec![x[0] != x[1]]);
+}
+
+#[test]
+fn nor() {
+    check_truth_table("nor", &["a", "b"], &["out"], |x| vec![!(x[0] || x[1])]);
+}
+
+#[test]
+fn xnor() {
+    check_truth_table("xnor", &["a", "b"], &["out"], |x| vec![x[0] == x[1]]);
+}
+
+#[test]
+fn mux() {
+    check_truth_table("mux", &["a", "b", "sel"], &["out"], |x| vec![if x[2] { x[1] } else { x[0] }]);
+}
+
+#[test]
+fn dmux() {
+    check_truth_table("dmux", &["in", "sel"], &["a", "b"], |x| vec![x[0] && !x[1], x[0] && x[1]]);
+}
@@ -0,0 +1,166 @@
+use sunho_computer::gates::{Gate, GateFactory, PinValues};
+
+fn inputs(values: &[(&str, i64, u64)]) -> PinValues {
+    let mut out = PinValues::new();
+    for (input, size, value) in values {
+        out.set_number(input, *size, *value);
+    }
+    out
+}
+
+// Runs one clock cycle and returns out before the tick and after the tock.
+fn cycle(gate: &mut Gate, values: &[(&str, i64, u64)]) -> (u64, u64) {
+    let before = gate.tick(inputs(values)).get_number("out", 16);
+    let after = gate.tock().get_number("out", 16);
+    (before, after)
+}
+
+#[test]
+fn dff() {
+    let mut gate = GateFactory::new().build("dff");
+    assert!(gate.is_stateful());
+    let out = gate.tick(inputs(&[("in", 1, 1)]));
+    assert_eq!(out.get_number("out", 1), 0);
+    let out = gate.tock();
+    assert_eq!(out.get_number("out", 1), 1);
+    let out = gate.eval(inputs(&[("in", 1, 0)]));
+    assert_eq!(out.get_number("out", 1), 1);
+}
+
+#[test]
+fn bit() {
+    let mut gate = GateFactory::new().build("bit");
+    let steps = [
+        // in, load, out after the cycle
+        (1, 0, 0),
+        (1, 1, 1),
+        (0, 0, 1),
+        (0, 1, 0),
+        (1, 0, 0),
+        (1, 1, 1),
+        (1, 1, 1),
+    ];
+    for (i, (input, load, expected)) in steps.iter().enumerate() {
+        let out = gate.run(inputs(&[("in", 1, *input), ("load", 1, *load)]));
+        assert_eq!(out.get_number("out", 1), *expected, "step {}", i);
+    }
+}
+
+#[test]
+fn register() {
+    let mut gate = GateFactory::new().build("register");
+    assert_eq!(cycle(&mut gate, &[("in", 16, 0x1234), ("load", 1, 0)]), (0, 0));
+    assert_eq!(cycle(&mut gate, &[("in", 16, 0x1234), ("load", 1, 1)]), (0, 0x1234));
+    assert_eq!(cycle(&mut gate, &[("in", 16, 0xffff), ("load", 1, 0)]), (0x1234, 0x1234));
+    assert_eq!(cycle(&mut gate, &[("in", 16, 0xffff), ("load", 1, 1)]), (0x1234, 0xffff));
+    assert_eq!(cycle(&mut gate, &[("in", 16, 0x8001), ("load", 1, 1)]), (0xffff, 0x8001));
+}
+
+fn check_ram(factory: &GateFactory, name: &str, address_size: i64, addresses: &[u64]) {
+    let mut gate = factory.build(name);
+    for (i, address) in addresses.iter().enumerate() {
+        let value = 0x1111 * (i as u64 + 1);
+        cycle(&mut gate, &[("in", 16, value), ("load", 1, 1), ("address", address_size, *address)]);
+    }
+    for (i, address) in addresses.iter().enumerate() {
+        let value = 0x1111 * (i as u64 + 1);
+        let (before, after) = cycle(&mut gate, &[("in", 16, 0xdead), ("load", 1, 0), ("address", address_size, *address)]);
+        assert_eq!(before, value, "{} address {}", name, address);
+        assert_eq!(after, value, "{} address {}", name, address);
+    }
+    // a write only shows up after the clock cycle
+    let address = addresses[0];
+    let (before, after) = cycle(&mut gate, &[("in", 16, 0xbeef), ("load", 1, 1), ("address", address_size, address)]);
+    assert_eq!(before, 0x1111);
+    assert_eq!(after, 0xbeef);
+    // every other address is untouched
+    for (i, address) in addresses.iter().enumerate().skip(1) {
+        let out = gate.eval(inputs(&[("in", 16, 0), ("load", 1, 0), ("address", address_size, *address)]));
+        assert_eq!(out.get_number("out", 16), 0x1111 * (i as u64 + 1));
+    }
+}
+
+#[test]
+fn ram8() {
+    check_ram(&GateFactory::new(), "ram8", 3, &[0, 1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn ram64() {
+    check_ram(&GateFactory::new(), "ram64", 6, &[0, 7, 8, 13, 42, 63]);
+}
+
+// Factory with the builtin memories where `bank` is the native RAM16K with
+// the upper bits of its address false, so that only the address decoding of
+// the chips built from it runs at gate level
+fn native_banks(bank: &str, address_size: i64) -> GateFactory {
+    let mut factory = GateFactory::with_builtin_memory();
+    factory.load_hdl("Bank.hdl", &format!("CHIP {} {{
+        IN in[16], load, address[{}];
+        OUT out[16];
+        PARTS:
+        RAM16K(in=in, load=load, address[0..{}]=address, out=out);
+    }}", bank, address_size, address_size - 1)).unwrap();
+    factory
+}
+
+// the same address in each of the eight ram64 of ram512, picked by
+// address[6..8], so a wrong bank overwrites another word
+#[test]
+fn ram512() {
+    let addresses: Vec<u64> = (0..8).map(|x| x * 64 + 5).collect();
+    check_ram(&native_banks("RAM64", 6), "ram512", 9, &addresses);
+}
+
+#[test]
+fn ram4k() {
+    let mut factory = native_banks("RAM512", 9);
+    factory.register_function(GateFactory::new().get("ram4k").unwrap().clone());
+    let addresses: Vec<u64> = (0..8).map(|x| x * 512 + 37).collect();
+    check_ram(&factory, "ram4k", 12, &addresses);
+}
+
+// ram16k at gate level from four native ram4k
+#[test]
+fn ram16k() {
+    let mut factory = GateFactory::with_builtin_memory();
+    factory.register_function(GateFactory::new().get("ram16k").unwrap().clone());
+    let addresses: Vec<u64> = (0..4).map(|x| x * 4096 + 100).collect();
+    check_ram(&factory, "ram16k", 14, &addresses);
+    assert!(factory.build("ram16k").find("ram4k").unwrap().memory().is_some());
+}
+
+#[test]
+fn pc() {
+    let mut gate = GateFactory::new().build("pc");
+    let steps = [
+        // in, load, inc, reset, out after the cycle
+        (0, 0, 0, 0, 0),
+        (0, 0, 1, 0, 1),
+        (0, 0, 1, 0, 2),
+        (0x1000, 0, 0, 0, 2),
+        (0x1000, 1, 0, 0, 0x1000),
+        (0x2000, 1, 1, 0, 0x2000),
+        (0, 0, 1, 0, 0x2001),
+        (0x3000, 1, 1, 1, 0),
+        (0, 0, 1, 0, 1),
+        (0xffff, 1, 0, 0, 0xffff),
+        (0, 0, 1, 0, 0),
+        (0, 0, 0, 1, 0),
+    ];
+    for (i, (input, load, inc, reset, expected)) in steps.iter().enumerate() {
+        let (_, after) = cycle(&mut gate, &[("in", 16, *input), ("load", 1, *load), ("inc", 1, *inc), ("reset", 1, *reset)]);
+        assert_eq!(after, *expected, "step {}", i);
+    }
+}
+
+#[test]
+fn factory_knows_every_memory() {
+    let factory = GateFactory::new();
+    for name in ["bit", "register", "ram8", "ram64", "ram512", "ram4k", "ram16k", "pc"].iter() {
+        assert!(factory.contains(name), "{}", name);
+    }
+    let signature = &factory.get("ram16k").unwrap().signature;
+    assert_eq!(signature.inputs, vec![("in".to_string(), 16), ("load".to_string(), 1), ("address".to_string(), 14)]);
+}
+
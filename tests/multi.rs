@@ -0,0 +1,99 @@
+use sunho_computer::gates::{GateFactory, PinValues};
+
+const WORDS: [u64; 6] = [0x0000, 0xffff, 0xaaaa, 0x5555, 0x3cc3, 0x1234];
+
+fn run(name: &str, inputs: &[(&str, i64, u64)]) -> PinValues {
+    let factory = GateFactory::new();
+    let mut gate = factory.build(name);
+    let mut values = PinValues::new();
+    for (input, size, value) in inputs {
+        values.set_number(input, *size, *value);
+    }
+    gate.run(values)
+}
+
+#[test]
+fn not16() {
+    for a in WORDS.iter() {
+        let res = run("not16", &[("in", 16, *a)]);
+        assert_eq!(res.get_number("out", 16), !a & 0xffff);
+    }
+}
+
+#[test]
+fn and16_or16() {
+    for a in WORDS.iter() {
+        for b in WORDS.iter() {
+            let inputs = [("a", 16, *a), ("b", 16, *b)];
+            assert_eq!(run("and16", &inputs).get_number("out", 16), a & b);
+            assert_eq!(run("or16", &inputs).get_number("out", 16), a | b);
+        }
+    }
+}
+
+#[test]
+fn mux16() {
+    for sel in 0..2 {
+        let res = run("mux16", &[("a", 16, 0x1234), ("b", 16, 0x9876), ("sel", 1, sel)]);
+        assert_eq!(res.get_number("out", 16), if sel == 0 { 0x1234 } else { 0x9876 });
+    }
+}
+
+#[test]
+fn or8way() {
+    for x in 0..256 {
+        let res = run("or8way", &[("in", 8, x)]);
+        assert_eq!(res.get("out", 0), x != 0, "in={:08b}", x);
+    }
+}
+
+#[test]
+fn mux4way16() {
+    let words = [0x1111, 0x2222, 0x4444, 0x8888];
+    for sel in 0..4 {
+        let res = run("mux4way16", &[
+            ("a", 16, words[0]), ("b", 16, words[1]), ("c", 16, words[2]), ("d", 16, words[3]),
+            ("sel", 2, sel)
+        ]);
+        assert_eq!(res.get_number("out", 16), words[sel as usize]);
+    }
+}
+
+#[test]
+fn mux8way16() {
+    let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+    for sel in 0..8 {
+        let mut inputs: Vec<(&str, i64, u64)> = names.iter().enumerate()
+            .map(|(i, x)| (*x, 16, 0x100 * (i as u64 + 1) + i as u64))
+            .collect();
+        inputs.push(("sel", 3, sel));
+        let res = run("mux8way16", &inputs);
+        assert_eq!(res.get_number("out", 16), 0x100 * (sel + 1) + sel);
+    }
+}
+
+#[test]
+fn dmux4way() {
+    let names = ["a", "b", "c", "d"];
+    for input in 0..2 {
+        for sel in 0..4 {
+            let res = run("dmux4way", &[("in", 1, input), ("sel", 2, sel)]);
+            for (i, x) in names.iter().enumerate() {
+                assert_eq!(res.get(x, 0), input == 1 && i as u64 == sel, "in={} sel={} {}", input, sel, x);
+            }
+        }
+    }
+}
+
+#[test]
+fn dmux8way() {
+    let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+    for input in 0..2 {
+        for sel in 0..8 {
+            let res = run("dmux8way", &[("in", 1, input), ("sel", 3, sel)]);
+            for (i, x) in names.iter().enumerate() {
+                assert_eq!(res.get(x, 0), input == 1 && i as u64 == sel, "in={} sel={} {}", input, sel, x);
+            }
+        }
+    }
+}
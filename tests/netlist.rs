@@ -0,0 +1,143 @@
+mod common;
+
+use common::random;
+use sunho_computer::gates::{CellKind, Driver, Gate, GateFactory, Netlist, PinKind, PinValues};
+
+// Simple evaluator of a netlist of nands and dffs
+struct Evaluator {
+    netlist: Netlist,
+    order: Vec<usize>,
+    values: Vec<bool>,
+    state: Vec<bool>
+}
+
+impl Evaluator {
+    fn new(netlist: Netlist) -> Evaluator {
+        let order = netlist.order().unwrap();
+        let values = vec![false; netlist.net_count()];
+        let state = vec![false; netlist.cells.len()];
+        Evaluator { netlist, order, values, state }
+    }
+
+    fn eval(&mut self, inputs: &PinValues) -> PinValues {
+        self.values[1] = true;
+        for port in &self.netlist.inputs {
+            for (i, net) in port.nets.iter().enumerate() {
+                self.values[*net] = inputs.contains(&port.name, i as i64) && inputs.get(&port.name, i as i64);
+            }
+        }
+        for i in &self.order {
+            let cell = &self.netlist.cells[*i];
+            self.values[cell.outputs[0]] = match cell.kind {
+                CellKind::Nand => !(self.values[cell.inputs[0]] && self.values[cell.inputs[1]]),
+                CellKind::Dff => self.state[*i],
+                _ => panic!("unexpected builtin")
+            };
+        }
+        let mut out = PinValues::new();
+        for port in &self.netlist.outputs {
+            for (i, net) in port.nets.iter().enumerate() {
+                out.set(&port.name, i as i64, self.values[*net]);
+            }
+        }
+        out
+    }
+
+    fn run(&mut self, inputs: &PinValues) -> PinValues {
+        self.eval(inputs);
+        for (i, cell) in self.netlist.cells.iter().enumerate() {
+            if cell.kind == CellKind::Dff {
+                self.state[i] = self.values[cell.inputs[0]];
+            }
+        }
+        self.eval(inputs)
+    }
+}
+
+fn random_inputs(gate: &Gate, seed: &mut u32) -> PinValues {
+    let mut out = PinValues::new();
+    for pin in gate.pins().filter(|x| x.kind == PinKind::Input) {
+        out.set(&pin.name, pin.index, (random(seed) >> 8) & 1 == 1);
+    }
+    out
+}
+
+#[test]
+fn and() {
+    let gate = GateFactory::new().build("and");
+    let netlist = Netlist::flatten(&gate);
+    assert_eq!(netlist.net_count(), 6);
+    assert_eq!(netlist.inputs[0].nets, vec![2]);
+    assert_eq!(netlist.inputs[1].nets, vec![3]);
+    assert_eq!(netlist.cells.len(), 2);
+    assert_eq!(netlist.cells[0].path, "and.nand");
+    assert_eq!(netlist.cells[0].inputs, vec![2, 3]);
+    assert_eq!(netlist.cells[0].outputs, vec![4]);
+    assert_eq!(netlist.cells[1].path, "and.not.nand");
+    assert_eq!(netlist.cells[1].inputs, vec![4, 4]);
+    assert_eq!(netlist.output("out").unwrap().nets, vec![5]);
+    assert_eq!(netlist.driver(5), Driver::Cell(1, 0));
+    assert_eq!(netlist.driver(3), Driver::Input(1, 0));
+    assert_eq!(netlist.net_name(4), "and:nandab");
+    assert_eq!(netlist.net_name(5), "and:out");
+    assert_eq!(netlist.order().unwrap(), vec![0, 1]);
+
+    // a primitive is a single cell
+    let netlist = Netlist::flatten(&GateFactory::new().build("nand"));
+    assert_eq!(netlist.cells.len(), 1);
+    assert_eq!(netlist.output("out").unwrap().nets, netlist.cells[0].outputs);
+}
+
+#[test]
+fn combinational() {
+    let factory = GateFactory::new();
+    let mut seed = 1;
+    for name in ["not", "xor", "mux", "dmux8way", "mux8way16", "or8way", "add16", "inc16", "alu"].iter() {
+        let mut gate = factory.build(name);
+        let mut evaluator = Evaluator::new(Netlist::flatten(&gate));
+        assert!(!evaluator.netlist.is_stateful());
+        for _ in 0..200 {
+            let inputs = random_inputs(&gate, &mut seed);
+            assert_eq!(evaluator.eval(&inputs).to_string(), gate.eval(inputs).to_string(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn sequential() {
+    let factory = GateFactory::new();
+    let mut seed = 7;
+    for name in ["bit", "register", "pc", "ram8"].iter() {
+        let mut gate = factory.build(name);
+        let netlist = Netlist::flatten(&gate);
+        assert!(netlist.is_stateful());
+        let mut evaluator = Evaluator::new(netlist);
+        for _ in 0..300 {
+            let inputs = random_inputs(&gate, &mut seed);
+            assert_eq!(evaluator.run(&inputs).to_string(), gate.run(inputs).to_string(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn computer() {
+    let gate = GateFactory::with_builtin_memory().build("computer");
+    let netlist = Netlist::flatten(&gate);
+    assert_eq!(netlist.count("rom32k"), 1);
+    assert_eq!(netlist.count("ram16k"), 1);
+    // a, d and pc registers
+    assert_eq!(netlist.count("dff"), 16 * 3);
+    assert!(netlist.count("nand") > 1000);
+    let screen = netlist.cells.iter().find(|x| x.path == "computer.memory.screen").unwrap();
+    match &screen.kind {
+        CellKind::Builtin { chip, stateful, dependencies } => {
+            assert_eq!(chip, "screen");
+            assert!(stateful);
+            // out only depends on the address
+            assert_eq!(dependencies[0], (17..30).collect::<Vec<_>>());
+        },
+        x => panic!("{:?}", x)
+    }
+    // the memory feeds the cpu and back without a combinational loop
+    assert_eq!(netlist.order().unwrap().len(), netlist.cells.len());
+}
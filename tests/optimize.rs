@@ -0,0 +1,96 @@
+mod common;
+
+use common::random;
+use sunho_computer::gates::{optimize, symbolic_outputs, variable_order, Bdd, GateFactory, Netlist, ParallelSimulator, LANES};
+
+#[test]
+fn report() {
+    let netlist = Netlist::flatten(&GateFactory::new().build("mux16"));
+    let (optimized, report) = optimize(&netlist).unwrap();
+    // the 16 inverters of sel are one
+    assert_eq!(report.to_string(), "nand 64 -> 49, dff 0 -> 0 in 2 passes");
+    assert_eq!(optimized.count("nand"), 49);
+    assert_eq!(optimized.inputs, netlist.inputs);
+}
+
+#[test]
+fn constants() {
+    let mut factory = GateFactory::new();
+    factory.load_hdl("Folded.hdl", "CHIP Folded {
+        IN in;
+        OUT out, zero, same;
+        PARTS:
+        And(a=true, b=in, out=out);
+        DFF(in=false, out=zero);
+        Not(in=in, out=notin);
+        Not(in=notin, out=same);
+    }").unwrap();
+    let netlist = Netlist::flatten(&factory.build("folded"));
+    let (optimized, report) = optimize(&netlist).unwrap();
+    assert_eq!((report.nands_before, report.dffs_before), (4, 1));
+    assert!(optimized.cells.is_empty());
+    let input = optimized.input("in").unwrap().nets[0];
+    assert_eq!(optimized.output("out").unwrap().nets, vec![input]);
+    assert_eq!(optimized.output("same").unwrap().nets, vec![input]);
+    assert_eq!(optimized.output("zero").unwrap().nets, vec![0]);
+    assert_eq!(optimized.net_name(input), "folded:in");
+}
+
+#[test]
+fn combinational() {
+    let factory = GateFactory::new();
+    for name in ["inc16", "add16", "mux8way16", "alu"].iter() {
+        let gate = factory.build(name);
+        let netlist = Netlist::flatten(&gate);
+        let (optimized, report) = optimize(&netlist).unwrap();
+        assert!(report.nands_after < report.nands_before, "{}", name);
+
+        let order = variable_order(&gate);
+        let variable = |name: &str, index: i64| order.iter().position(|(x, i)| x == name && *i == index).map(|x| x as u32);
+        let mut bdd = Bdd::new();
+        let before = symbolic_outputs(&mut bdd, &netlist, &variable).unwrap();
+        let after = symbolic_outputs(&mut bdd, &optimized, &variable).unwrap();
+        assert_eq!(before, after, "{}", name);
+    }
+}
+
+#[test]
+fn sequential() {
+    let factory = GateFactory::new();
+    let mut seed = 5;
+    for name in ["pc", "ram8", "cpu"].iter() {
+        let netlist = Netlist::flatten(&factory.build(name));
+        let (optimized, _) = optimize(&netlist).unwrap();
+        let mut before = ParallelSimulator::new(netlist.clone()).unwrap();
+        let mut after = ParallelSimulator::new(optimized).unwrap();
+        for _ in 0..200 {
+            for port in &netlist.inputs {
+                for index in 0..port.nets.len() as i64 {
+                    let lanes = random(&mut seed) << 32 | random(&mut seed);
+                    before.set_lanes(&port.name, index, lanes);
+                    after.set_lanes(&port.name, index, lanes);
+                }
+            }
+            for simulator in [&mut before, &mut after].iter_mut() {
+                simulator.evaluate();
+                simulator.latch();
+                simulator.commit();
+            }
+            for port in &netlist.outputs {
+                assert_eq!(before.numbers(&port.name), after.numbers(&port.name), "{} {}", name, port.name);
+                assert_eq!(before.numbers(&port.name).len(), LANES);
+            }
+        }
+    }
+}
+
+#[test]
+fn builtins() {
+    let netlist = Netlist::flatten(&GateFactory::with_builtin_memory().build("computer"));
+    let (optimized, report) = optimize(&netlist).unwrap();
+    assert!(report.nands_after < report.nands_before);
+    for chip in ["rom32k", "ram16k", "screen", "keyboard"].iter() {
+        assert_eq!(optimized.count(chip), 1, "{}", chip);
+    }
+    assert_eq!(optimized.order().unwrap().len(), optimized.cells.len());
+}
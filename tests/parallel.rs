@@ -0,0 +1,93 @@
+mod common;
+
+use common::random;
+use sunho_computer::gates::{GateFactory, GateValidationError, ParallelSimulator, PinKind, PinValues, LANES};
+
+#[test]
+fn inc16_exhaustive() {
+    let mut simulator = ParallelSimulator::from_gate(&GateFactory::new().build("inc16")).unwrap();
+    for start in (0..1u64 << 16).step_by(LANES) {
+        let numbers: Vec<u64> = (start..start + LANES as u64).collect();
+        simulator.set_numbers("in", &numbers);
+        simulator.evaluate();
+        let out = simulator.numbers("out");
+        for (lane, number) in numbers.iter().enumerate() {
+            assert_eq!(out[lane], (number + 1) & 0xffff);
+        }
+    }
+}
+
+#[test]
+fn alu_lanes() {
+    let mut gate = GateFactory::new().build("alu");
+    let mut simulator = ParallelSimulator::from_gate(&gate).unwrap();
+    let inputs: Vec<(String, i64)> = gate.pins()
+        .filter(|x| x.kind == PinKind::Input && x.index == 0)
+        .map(|x| (x.name.clone(), x.size))
+        .collect();
+    let mut seed = 3;
+    for _ in 0..20 {
+        let mut vectors = vec![PinValues::new(); LANES];
+        for (name, size) in &inputs {
+            let numbers: Vec<u64> = (0..LANES).map(|_| random(&mut seed) & ((1 << size) - 1)).collect();
+            for (lane, number) in numbers.iter().enumerate() {
+                vectors[lane].set_number(name, *size, *number);
+            }
+            simulator.set_numbers(name, &numbers);
+        }
+        simulator.evaluate();
+        for (lane, vector) in vectors.into_iter().enumerate() {
+            assert_eq!(simulator.outputs(lane).to_string(), gate.eval(vector).to_string());
+        }
+    }
+}
+
+#[test]
+fn same_as_gate() {
+    let factory = GateFactory::new();
+    let mut seed = 11;
+    for name in ["xor", "mux4way16", "bit", "register", "pc", "ram8"].iter() {
+        let mut gate = factory.build(name);
+        let mut simulator = ParallelSimulator::from_gate(&gate).unwrap();
+        assert_eq!(simulator.is_stateful(), gate.is_stateful());
+        for _ in 0..100 {
+            let mut inputs = PinValues::new();
+            for pin in gate.pins().filter(|x| x.kind == PinKind::Input) {
+                inputs.set(&pin.name, pin.index, random(&mut seed) & 1 == 1);
+            }
+            assert_eq!(simulator.tick(inputs.clone()).to_string(), gate.tick(inputs).to_string(), "{}", name);
+            assert_eq!(simulator.tock().to_string(), gate.tock().to_string(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn register_lanes() {
+    let mut simulator = ParallelSimulator::from_gate(&GateFactory::new().build("register")).unwrap();
+    // lane i loads i on even lanes only
+    let numbers: Vec<u64> = (0..LANES as u64).collect();
+    simulator.set_numbers("in", &numbers);
+    simulator.set_lanes("load", 0, 0x5555_5555_5555_5555);
+    simulator.evaluate();
+    simulator.latch();
+    assert_eq!(simulator.numbers("out"), vec![0; LANES]);
+    simulator.commit();
+    let out = simulator.numbers("out");
+    for (lane, number) in out.iter().enumerate() {
+        assert_eq!(*number, if lane % 2 == 0 { lane as u64 } else { 0 });
+    }
+    assert_eq!(simulator.lanes("load", 0), Some(0x5555_5555_5555_5555));
+
+    simulator.reset();
+    simulator.evaluate();
+    assert_eq!(simulator.numbers("out"), vec![0; LANES]);
+}
+
+#[test]
+fn builtins() {
+    let gate = GateFactory::with_builtin_memory().build("computer");
+    match ParallelSimulator::from_gate(&gate) {
+        Err(GateValidationError::UnsupportedPrimitive(_)) => {},
+        x => panic!("{:?}", x.map(|_| ()))
+    }
+}
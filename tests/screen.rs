@@ -0,0 +1,130 @@
+use std::fs;
+
+use sunho_computer::assembler::assemble;
+use sunho_computer::gates::{GateFactory, PinValues};
+use sunho_computer::hack::HackEmulator;
+use sunho_computer::screen::{Frame, FrameDumper, ScreenSource, HEIGHT, WIDTH};
+
+// draws a 16 pixel line at the top left and a pixel at (17, 1)
+const DRAW: &str = "
+    @SCREEN
+    M=-1
+    @2
+    D=A
+    @16417
+    M=D
+(END)
+    @END
+    0;JMP
+";
+
+fn frame() -> Frame {
+    let mut words = vec![0; 8192];
+    words[0] = 0x8001;
+    words[32 * 255 + 31] = 0x8000;
+    Frame::new(&words)
+}
+
+#[test]
+fn pixels() {
+    let frame = frame();
+    assert!(frame.pixel(0, 0));
+    assert!(!frame.pixel(1, 0));
+    assert!(frame.pixel(15, 0));
+    assert!(frame.pixel(WIDTH - 1, HEIGHT - 1));
+    assert_eq!(frame.count_black(), 3);
+}
+
+#[test]
+fn pbm() {
+    let data = frame().to_pbm();
+    let header = b"P4\n512 256\n";
+    assert_eq!(&data[..header.len()], header);
+    assert_eq!(data.len(), header.len() + 512 * 256 / 8);
+    assert_eq!(&data[header.len()..header.len() + 3], &[0x80, 0x01, 0x00]);
+    assert_eq!(data[data.len() - 1], 0x01);
+    assert_eq!(Frame::from_pbm(&data), Some(frame()));
+
+    let mut commented = b"P4 # screen\n512\n256\n".to_vec();
+    commented.extend_from_slice(&data[header.len()..]);
+    assert_eq!(Frame::from_pbm(&commented), Some(frame()));
+    assert_eq!(Frame::from_pbm(b"P4\n10 10\n"), None);
+}
+
+fn be32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+#[test]
+fn png() {
+    let data = frame().to_png();
+    assert_eq!(&data[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+    let mut chunks = Vec::new();
+    let mut i = 8;
+    while i < data.len() {
+        let size = be32(&data[i..]) as usize;
+        chunks.push((data[i + 4..i + 8].to_vec(), data[i + 8..i + 8 + size].to_vec()));
+        i += 12 + size;
+    }
+    assert_eq!(i, data.len());
+    let kinds: Vec<&[u8]> = chunks.iter().map(|x| x.0.as_slice()).collect();
+    assert_eq!(kinds, vec![&b"IHDR"[..], b"IDAT", b"IEND"]);
+    assert_eq!(chunks[0].1, vec![0, 0, 2, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0]);
+
+    // a single stored deflate block after the zlib header
+    let idat = &chunks[1].1;
+    assert_eq!(&idat[..3], &[0x78, 0x01, 0x01]);
+    let size = u16::from_le_bytes([idat[3], idat[4]]) as usize;
+    assert_eq!(size, 256 * 65);
+    let raw = &idat[7..7 + size];
+    // filter byte, then the first row where black is 0
+    assert_eq!(&raw[..4], &[0, 0x7f, 0xfe, 0xff]);
+    assert_eq!(raw[size - 1], 0xfe);
+}
+
+#[test]
+fn gates_and_emulator() {
+    let program = assemble("Draw.asm", DRAW).unwrap();
+    let mut emulator = HackEmulator::new(&program);
+    emulator.run(10);
+
+    let mut computer = GateFactory::with_builtin_memory().build("computer");
+    computer.find_mut("rom32k").unwrap().memory_mut().unwrap()[..program.len()].copy_from_slice(&program);
+    for _ in 0..10 {
+        let mut inputs = PinValues::new();
+        inputs.set("reset", 0, false);
+        computer.run(inputs);
+    }
+
+    let expected = Frame::capture(&emulator).unwrap();
+    assert_eq!(Frame::capture(&computer), Some(expected.clone()));
+    assert_eq!(expected.count_black(), 17);
+    assert!(expected.pixel(17, 1));
+
+    // the memory chip alone
+    let memory = computer.find("memory").unwrap();
+    assert_eq!(memory.screen_words().map(Frame::new), Some(expected));
+    assert_eq!(GateFactory::new().build("and").screen_words(), None);
+}
+
+#[test]
+fn dumper() {
+    let directory = std::env::temp_dir().join(format!("sunho-computer-frames-{}", std::process::id()));
+    let program = assemble("Draw.asm", DRAW).unwrap();
+    let mut emulator = HackEmulator::new(&program);
+    let mut dumper = FrameDumper::new(&directory, "pbm", 2).unwrap();
+    let mut saved = Vec::new();
+    for _ in 0..6 {
+        emulator.step();
+        if let Some(path) = dumper.cycle(emulator.cycles(), &emulator).unwrap() {
+            saved.push(path);
+        }
+    }
+    assert_eq!(saved, vec![dumper.path(2), dumper.path(4), dumper.path(6)]);
+    assert!(saved[0].ends_with("frame_00000002.pbm"));
+    let first = Frame::from_pbm(&fs::read(&saved[0]).unwrap()).unwrap();
+    let last = Frame::from_pbm(&fs::read(&saved[2]).unwrap()).unwrap();
+    assert_eq!(first.count_black(), 16);
+    assert_eq!(last.count_black(), 17);
+    fs::remove_dir_all(&directory).unwrap();
+}
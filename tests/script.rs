@@ -0,0 +1,205 @@
+use std::fs;
+use sunho_computer::gates::{parse_script, run_script_file, GateFactory, ScriptError, ScriptRunner};
+
+// Runs a script whose chips all come from the factory
+fn run(source: &str) -> Result<Vec<String>, ScriptError> {
+    let script = parse_script("Test.tst", source)?;
+    let mut factory = GateFactory::new();
+    ScriptRunner::new(&mut factory, ".").run(&script)
+}
+
+fn runtime_message(result: Result<Vec<String>, ScriptError>) -> (usize, String) {
+    match result {
+        Err(ScriptError::Runtime { line, message, .. }) => (line, message),
+        x => panic!("expected a runtime error, got {:?}", x)
+    }
+}
+
+#[test]
+fn combinational() {
+    let lines = run("
+        load And.hdl,
+        output-list a%B3.1.3 b%B3.1.3 out%B3.1.3;
+        set a 0, set b 0, eval, output;
+        set a 0, set b 1, eval, output;
+        set a 1, set b 0, eval, output;
+        set a 1, set b 1, eval, output;
+    ").unwrap();
+    assert_eq!(lines, vec![
+        "|   a   |   b   |  out  |",
+        "|   0   |   0   |   0   |",
+        "|   0   |   1   |   0   |",
+        "|   1   |   0   |   0   |",
+        "|   1   |   1   |   1   |",
+    ]);
+}
+
+#[test]
+fn sequential() {
+    let lines = run("
+        load Bit.hdl,
+        output-list time%S1.4.1 in%B2.1.2 load%B2.1.2 out%B2.1.2;
+        set in 1, set load 1, tick, output, tock, output;
+        set in 0, set load 0, tick, output, tock, output;
+    ").unwrap();
+    assert_eq!(lines, vec![
+        "| time | in  |load | out |",
+        "| 0+   |  1  |  1  |  0  |",
+        "| 1    |  1  |  1  |  1  |",
+        "| 1+   |  0  |  0  |  1  |",
+        "| 2    |  0  |  0  |  1  |",
+    ]);
+}
+
+#[test]
+fn formats() {
+    let lines = run("
+        load Add16.hdl,
+        output-list a%D1.6.1 b%X1.6.1 out%B1.16.1 out[0..3]%B1.2.1 out[0..3]%B1.6.1 out%D1.6.1;
+        set a -2, set b %X00FF, eval, output;
+        set a %B111, set b %D-32768, eval, output;
+    ").unwrap();
+    assert_eq!(lines[1], "|     -2 | 0000FF | 0000000011111101 | 01 | 001101 |    253 |");
+    assert_eq!(lines[2], "|      7 | 008000 | 1000000000000111 | 11 | 000111 | -32761 |");
+}
+
+#[test]
+fn loops() {
+    let lines = run("
+        load PC.hdl,
+        output-list time%S1.4.1 out%D1.6.1;
+        set inc 1,
+        repeat 3 {
+            tick, tock;
+        }
+        output;
+        while out < 7 {
+            tick, tock;
+        }
+        output;
+        while out[0..1] <> 0 {
+            tick, tock;
+        }
+        output;
+    ").unwrap();
+    assert_eq!(&lines[1..], &["| 3    |      3 |", "| 7    |      7 |", "| 8    |      8 |"]);
+}
+
+#[test]
+fn echo() {
+    let script = parse_script("Test.tst", "load Not.hdl, echo \"testing not\", set in 1, eval;").unwrap();
+    let mut factory = GateFactory::new();
+    let mut runner = ScriptRunner::new(&mut factory, ".");
+    assert_eq!(runner.run(&script).unwrap(), Vec::<String>::new());
+    assert_eq!(runner.echoes(), &["testing not".to_string()]);
+}
+
+#[test]
+fn comparison() {
+    let directory = std::env::temp_dir().join(format!("sunho-computer-script-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("Or.tst"), "
+        load Or.hdl,
+        output-file Or.out,
+        compare-to Or.cmp,
+        output-list a%B1.1.1 b%B1.1.1 out%B1.1.1;
+        set a 0, set b 0, eval, output;
+        set a 1, set b 0, eval, output;
+        set a 0, set b 1, eval, output;
+    ").unwrap();
+    fs::write(directory.join("Or.cmp"), "| a | b |out|\n| 0 | 0 | 0 |\n| 1 | 0 | 0 |\n| 0 | 1 | 1 |\n").unwrap();
+    let error = run_script_file(&mut GateFactory::new(), directory.join("Or.tst")).unwrap_err();
+    let cmp = directory.join("Or.cmp").display().to_string();
+    assert_eq!(error, ScriptError::Comparison {
+        file: cmp.clone(),
+        line: 3,
+        expected: "| 1 | 0 | 0 |".to_string(),
+        actual: "| 1 | 0 | 1 |".to_string()
+    });
+    assert!(error.to_string().starts_with(&format!("{}:3: comparison failure", cmp)));
+    // the output file has every line up to the mismatch
+    let out = fs::read_to_string(directory.join("Or.out")).unwrap();
+    assert_eq!(out, "| a | b |out|\n| 0 | 0 | 0 |\n| 1 | 0 | 1 |\n");
+
+    fs::write(directory.join("Or.cmp"), "| a | b |out|\n| 0 | 0 | 0 |\n| 1 | 0 | 1 |\n| 0 | 1 | 1 |\n").unwrap();
+    assert_eq!(run_script_file(&mut GateFactory::new(), directory.join("Or.tst")).unwrap().len(), 4);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn syntax_errors() {
+    let cases = [
+        ("load And.hdl,\nset a 1 eval;", 2, 9, "expected ',' or ';'"),
+        ("load And.hdl,\n  frob;", 2, 3, "unknown command frob"),
+        ("load And.hdl,\noutput-list out%Q1.1.1;", 2, 13, "invalid output format out%Q1.1.1"),
+        ("echo \"unterminated,\neval;", 1, 6, "unterminated string"),
+        ("repeat {\n  eval;\n}", 1, 8, "expected repeat count"),
+        ("load And.hdl,\nset a %X1G;", 2, 7, "invalid value %X1G"),
+        ("while out => 3 {}", 1, 11, "expected comparison operator"),
+        ("while out < 3 {\n  tick, tock;\n", 2, 13, "expected '}' but found end of script"),
+    ];
+    for (source, line, column, message) in cases.iter() {
+        let expected = ScriptError::Syntax {
+            file: "Test.tst".to_string(), line: *line, column: *column, message: message.to_string()
+        };
+        assert_eq!(parse_script("Test.tst", source), Err(expected), "{}", source);
+    }
+    let error = parse_script("Test.tst", "load And.hdl,\n  frob;").unwrap_err();
+    assert_eq!(error.to_string(), "Test.tst:2:3: unknown command frob");
+    // negative and reversed bit ranges
+    for pin in ["a[-1]", "a[3..1]", "a[-2..1]", "a[1"].iter() {
+        match parse_script("Test.tst", &format!("set {} 0;", pin)) {
+            Err(ScriptError::Syntax { message, .. }) => assert_eq!(message, format!("invalid pin {}", pin)),
+            x => panic!("{}: {:?}", pin, x)
+        }
+    }
+}
+
+#[test]
+fn runtime_errors() {
+    assert_eq!(runtime_message(run("load And16.hdl,\n\noutput-list out[20];")),
+        (3, "bit range 20..20 is out of bounds of out[16]".to_string()));
+    assert_eq!(runtime_message(run("load And16.hdl,\noutput-list a out[8..16];")),
+        (2, "bit range 8..16 is out of bounds of out[16]".to_string()));
+    assert_eq!(runtime_message(run("load And16.hdl,\nset a[16] 1;")),
+        (2, "bit range 16..16 is out of bounds of a[16]".to_string()));
+    assert_eq!(runtime_message(run("load And.hdl,\nset a 2;")),
+        (2, "value 2 does not fit in 1 bits".to_string()));
+    assert_eq!(runtime_message(run("load And.hdl,\nset out 1;")),
+        (2, "out is not an input pin".to_string()));
+    assert_eq!(runtime_message(run("load And.hdl,\noutput-list c;")),
+        (2, "chip and has no pin named c".to_string()));
+    assert_eq!(runtime_message(run("eval;")), (1, "no chip is loaded".to_string()));
+    assert_eq!(runtime_message(run("load Missing.hdl;")), (1, "can not find Missing.hdl".to_string()));
+    // a registered chip that no longer builds after one of its parts changed
+    let mut factory = GateFactory::new();
+    factory.load_hdl("Helper.hdl", "CHIP Helper { IN in; OUT out; PARTS: Not(in=in, out=out); }").unwrap();
+    factory.load_hdl("Top.hdl", "CHIP Top { IN in; OUT out; PARTS: Helper(in=in, out=out); }").unwrap();
+    factory.load_hdl("Helper.hdl", "CHIP Helper { IN a; OUT out; PARTS: Not(in=a, out=out); }").unwrap();
+    let script = parse_script("Test.tst", "echo \"top\";\nload Top.hdl;").unwrap();
+    assert_eq!(runtime_message(ScriptRunner::new(&mut factory, ".").run(&script)),
+        (2, "Top.hdl:1:35: failed to connect in of helper: pin does not exist".to_string()));
+    let error = run_script_file(&mut GateFactory::new(), "Missing.tst").unwrap_err();
+    assert!(error.to_string().starts_with("Missing.tst: "), "{}", error);
+}
+
+#[test]
+fn directory() {
+    let directory = std::env::temp_dir().join(format!("sunho-computer-script-dir-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("Top.tst"), "
+        load Top.hdl,
+        output-list in%B1.1.1 out%B1.1.1;
+        set in 0, eval, output;
+        set in 1, eval, output;
+    ").unwrap();
+    fs::write(directory.join("Top.hdl"), "CHIP Top { IN in; OUT out; PARTS: Helper(in=in, out=out); }").unwrap();
+    fs::write(directory.join("Helper.hdl"), "CHIP Helper { IN in; OUT out; PARTS: Not(in=in, out=out); }").unwrap();
+    assert_eq!(run_script_file(&mut GateFactory::new(), directory.join("Top.tst")).unwrap(),
+        vec!["|in |out|", "| 0 | 1 |", "| 1 | 0 |"]);
+    // a chip next to the script takes the place of the builtin one
+    fs::write(directory.join("Not.hdl"), "CHIP Not { IN in; OUT out; PARTS: Nand(a=in, b=false, out=out); }").unwrap();
+    assert_eq!(run_script_file(&mut GateFactory::new(), directory.join("Top.tst")).unwrap(),
+        vec!["|in |out|", "| 0 | 1 |", "| 1 | 1 |"]);
+    fs::remove_dir_all(&directory).unwrap();
+}
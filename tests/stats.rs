@@ -0,0 +1,58 @@
+use sunho_computer::gates::{ChipStats, GateFactory};
+
+#[test]
+fn and() {
+    let stats = ChipStats::new(&GateFactory::new().build("and")).unwrap();
+    assert_eq!(stats.to_string(), "chip and
+primitives 2
+  nand 2
+parts 3
+  nand 2
+  not 1
+depth 2
+critical path
+  and:a
+  and:nandab
+  and:out
+");
+}
+
+#[test]
+fn combinational() {
+    let factory = GateFactory::new();
+    for (name, nands, depth) in [("nand", 1, 1), ("xor", 4, 3), ("mux16", 64, 3), ("add16", 231, 62), ("alu", 741, 82)].iter() {
+        let stats = ChipStats::new(&factory.build(name)).unwrap();
+        assert_eq!(stats.primitives.get("nand"), Some(nands), "{}", name);
+        assert_eq!(stats.primitive_count(), *nands, "{}", name);
+        assert_eq!(stats.depth, *depth, "{}", name);
+        // a nand level between every two nets
+        assert_eq!(stats.critical_path.len(), depth + 1, "{}", name);
+    }
+
+    let stats = ChipStats::new(&factory.build("alu")).unwrap();
+    assert_eq!(stats.parts["add16"], 1);
+    assert_eq!(stats.parts["fulladder"], 15);
+    assert_eq!(stats.parts["mux16"], 6);
+    assert_eq!(stats.parts["nand"], 741);
+    assert_eq!(stats.critical_path.first().unwrap(), "alu:zx");
+    assert_eq!(stats.critical_path.last().unwrap(), "alu:zr");
+    assert!(stats.critical_path.contains(&"alu.add16:carry[14]".to_string()));
+}
+
+#[test]
+fn sequential() {
+    let factory = GateFactory::with_builtin_memory();
+    let stats = ChipStats::new(&factory.build("bit")).unwrap();
+    assert_eq!(stats.primitives.get("dff"), Some(&1));
+    // through the mux into the dff
+    assert_eq!(stats.depth, 3);
+    assert_eq!(stats.critical_path.last().unwrap(), "bit:muxout");
+
+    let stats = ChipStats::new(&factory.build("computer")).unwrap();
+    assert_eq!(stats.primitives["dff"], 48);
+    assert_eq!(stats.primitives["rom32k"], 1);
+    assert_eq!(stats.primitives["ram16k"], 1);
+    assert_eq!(stats.parts["cpu"], 1);
+    assert_eq!(stats.parts["register"], 3);
+    assert_eq!(stats.depth, 104);
+}
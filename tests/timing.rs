@@ -0,0 +1,122 @@
+mod common;
+
+use common::random;
+use sunho_computer::gates::{ChipStats, Delays, GateFactory, GateValidationError, ParallelSimulator, TimingSimulator};
+
+#[test]
+fn hazard() {
+    let mut simulator = TimingSimulator::from_gate(&GateFactory::new().build("mux"), Delays::default()).unwrap();
+    simulator.set("a", 0, true);
+    simulator.set("b", 0, true);
+    simulator.set("sel", 0, true);
+    let report = simulator.settle();
+    assert_eq!(report.settling_time, 2);
+    assert!(report.glitches.is_empty());
+
+    // the and of b falls before the and of a rises
+    simulator.set("sel", 0, false);
+    let report = simulator.settle();
+    assert_eq!(report.start, 2);
+    assert_eq!(report.settling_time, 3);
+    assert_eq!(report.glitches.len(), 1);
+    assert!(report.glitches[0].is_static());
+    assert_eq!(report.glitches[0].to_string(), "out[0]: 0 at 2, 1 at 3 (static)");
+    assert_eq!(simulator.get("out", 0), Some(true));
+
+    // no hazard once the inverter is as fast as the wire
+    let mut simulator = TimingSimulator::from_gate(&GateFactory::new().build("mux"), Delays::default()).unwrap();
+    let not = simulator.netlist().cells.iter().position(|x| x.path == "mux.not.nand").unwrap();
+    simulator.set_delay(not, 0);
+    assert_eq!(simulator.delay(not), 0);
+    for (name, value) in [("a", true), ("b", true), ("sel", true)].iter() {
+        simulator.set(name, 0, *value);
+    }
+    simulator.settle();
+    simulator.set("sel", 0, false);
+    assert!(simulator.settle().glitches.is_empty());
+}
+
+#[test]
+fn ripple() {
+    let factory = GateFactory::new();
+    let mut simulator = TimingSimulator::from_gate(&factory.build("add16"), Delays { nand: 2, dff: 1, setup: 0 }).unwrap();
+    simulator.set_number("a", 0xffff);
+    simulator.settle();
+    simulator.set_number("b", 1);
+    let report = simulator.settle();
+    assert_eq!(simulator.number("out"), 0);
+    // the carry goes through every bit
+    assert_eq!(report.settling_time, 122);
+    assert!(report.settling_time <= simulator.max_delay());
+
+    // the longest path in nand levels with unit delays
+    for name in ["xor", "add16", "alu"].iter() {
+        let gate = factory.build(name);
+        let simulator = TimingSimulator::from_gate(&gate, Delays::default()).unwrap();
+        assert_eq!(simulator.max_delay() as usize, ChipStats::new(&gate).unwrap().depth, "{}", name);
+        assert_eq!(simulator.min_clock_period(), None);
+    }
+}
+
+#[test]
+fn random_inputs() {
+    let factory = GateFactory::new();
+    let mut seed = 7;
+    for name in ["alu", "mux8way16"].iter() {
+        let gate = factory.build(name);
+        let mut simulator = TimingSimulator::from_gate(&gate, Delays::default()).unwrap();
+        let mut parallel = ParallelSimulator::from_gate(&gate).unwrap();
+        let max_delay = simulator.max_delay();
+        for _ in 0..200 {
+            for port in &simulator.netlist().inputs.clone() {
+                let number = random(&mut seed) & ((1 << port.nets.len()) - 1);
+                simulator.set_number(&port.name, number);
+                parallel.set_numbers(&port.name, &[number]);
+            }
+            let report = simulator.settle();
+            parallel.evaluate();
+            assert!(report.settling_time <= max_delay, "{}", name);
+            assert_eq!(simulator.outputs().to_string(), parallel.outputs(0).to_string(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn sequential() {
+    let factory = GateFactory::new();
+    let mut seed = 13;
+    for name in ["bit", "pc", "ram8"].iter() {
+        let gate = factory.build(name);
+        let mut simulator = TimingSimulator::from_gate(&gate, Delays::default()).unwrap();
+        let mut parallel = ParallelSimulator::from_gate(&gate).unwrap();
+        let period = simulator.min_clock_period().unwrap();
+        for _ in 0..100 {
+            for port in &simulator.netlist().inputs.clone() {
+                let number = random(&mut seed) & ((1 << port.nets.len()) - 1);
+                simulator.set_number(&port.name, number);
+                parallel.set_numbers(&port.name, &[number]);
+            }
+            assert!(simulator.settle().quiet_time <= period, "{}", name);
+            let report = simulator.clock();
+            assert!(report.quiet_time <= period, "{}", name);
+            parallel.evaluate();
+            parallel.latch();
+            parallel.commit();
+            assert_eq!(simulator.outputs().to_string(), parallel.outputs(0).to_string(), "{}", name);
+        }
+    }
+
+    let simulator = TimingSimulator::from_gate(&factory.build("pc"), Delays { nand: 1, dff: 3, setup: 2 }).unwrap();
+    // from a dff through the increment and the muxes back to a dff
+    assert_eq!(simulator.min_clock_period(), Some(3 + 70 + 2));
+    assert_eq!(simulator.max_delay(), 3);
+}
+
+#[test]
+fn builtin() {
+    let gate = GateFactory::with_builtin_memory().build("ram16k");
+    match TimingSimulator::from_gate(&gate, Delays::default()) {
+        Err(GateValidationError::UnsupportedPrimitive(chip)) => assert_eq!(chip, "ram16k"),
+        _ => panic!("builtin memory is not simulated")
+    }
+}
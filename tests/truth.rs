@@ -0,0 +1,79 @@
+use sunho_computer::gates::{check_equivalence, GateFactory, TruthTable};
+
+#[test]
+fn and() {
+    let mut gate = GateFactory::new().build("and");
+    let table = TruthTable::new(&mut gate).unwrap();
+    assert_eq!(table.len(), 4);
+    assert_eq!(table.to_string(), "| a | b |out|
+| 0 | 0 | 0 |
+| 0 | 1 | 0 |
+| 1 | 0 | 0 |
+| 1 | 1 | 1 |
+");
+    assert!(table.row(3).get("out", 0));
+    assert!(table.assignment(2).get("a", 0));
+    assert!(!table.assignment(2).get("b", 0));
+}
+
+#[test]
+fn buses() {
+    let mut gate = GateFactory::new().build("mux4way16");
+    assert!(TruthTable::new(&mut gate).is_err());
+
+    let mut gate = GateFactory::new().build("dmux4way");
+    let table = TruthTable::new(&mut gate).unwrap();
+    assert_eq!(table.inputs(), &[("in".to_string(), 1), ("sel".to_string(), 2)]);
+    // in=1 sel=10
+    assert_eq!(table.row_numbers(0b110), vec![0, 0, 1, 0]);
+    assert!(table.to_string().starts_with("|in |sel | a | b | c | d |\n| 0 | 00 | 0 | 0 | 0 | 0 |\n"));
+}
+
+#[test]
+fn equivalence() {
+    let mut factory = GateFactory::new();
+    factory.load_hdl("MyXor.hdl", "CHIP MyXor {
+        IN b, a;
+        OUT out;
+        PARTS:
+        Nand(a=a, b=b, out=n);
+        Nand(a=a, b=n, out=x);
+        Nand(a=n, b=b, out=y);
+        Nand(a=x, b=y, out=out);
+    }").unwrap();
+    factory.load_hdl("BadXor.hdl", "CHIP BadXor {
+        IN a, b;
+        OUT out;
+        PARTS:
+        Or(a=a, b=b, out=out);
+    }").unwrap();
+    let mut xor = factory.build("xor");
+    assert!(check_equivalence(&mut xor, &mut factory.build("myxor")).unwrap().is_none());
+
+    let counterexample = check_equivalence(&mut xor, &mut factory.build("badxor")).unwrap().unwrap();
+    assert!(counterexample.inputs.get("a", 0) && counterexample.inputs.get("b", 0));
+    assert_eq!(counterexample.to_string(), "a=1 b=1: out=0 in xor but out=1 in badxor");
+
+    // inc16 against add16 with b=1
+    factory.load_hdl("MyInc16.hdl", "CHIP MyInc16 {
+        IN in[16];
+        OUT out[16];
+        PARTS:
+        Add16(a=in, b[0]=true, out=out);
+    }").unwrap();
+    let mut inc16 = factory.build("inc16");
+    assert!(check_equivalence(&mut inc16, &mut factory.build("myinc16")).unwrap().is_none());
+}
+
+#[test]
+fn errors() {
+    let factory = GateFactory::new();
+    let e = TruthTable::new(&mut factory.build("bit")).unwrap_err();
+    assert_eq!(e.to_string(), "bit: is not combinational");
+    let e = TruthTable::new(&mut factory.build("add16")).unwrap_err();
+    assert_eq!(e.to_string(), "add16: has 32 input bits, at most 24 are supported");
+    let e = check_equivalence(&mut factory.build("and"), &mut factory.build("not")).unwrap_err();
+    assert_eq!(e.to_string(), "and: inputs differ from those of not");
+    let e = check_equivalence(&mut factory.build("dmux"), &mut factory.build("mux")).unwrap_err();
+    assert_eq!(e.to_string(), "dmux: inputs differ from those of mux");
+}
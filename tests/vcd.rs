@@ -0,0 +1,74 @@
+use sunho_computer::gates::{GateFactory, PinValues, VcdRecorder};
+
+#[test]
+fn bit() {
+    let mut gate = GateFactory::new().build("bit");
+    let mut recorder = VcdRecorder::new(&gate, &["dff.out", "in", "load", "out"]).unwrap();
+    for (value, load) in [(true, true), (false, false), (false, true)].iter() {
+        let mut inputs = PinValues::new();
+        inputs.set("in", 0, *value);
+        inputs.set("load", 0, *load);
+        recorder.cycle(&mut gate, inputs);
+    }
+    let expected = "$version sunho-computer $end
+$timescale 1ns $end
+$scope module bit $end
+$var wire 1 ! in $end
+$var wire 1 \" load $end
+$var wire 1 # out $end
+$scope module dff $end
+$var wire 1 $ out $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+1!
+1\"
+0#
+0$
+#1
+1#
+1$
+#2
+0!
+0\"
+#4
+1\"
+#5
+0#
+0$
+#6
+";
+    assert_eq!(recorder.contents(), expected);
+}
+
+#[test]
+fn buses_and_scopes() {
+    let mut gate = GateFactory::new().build("cpu");
+    let mut recorder = VcdRecorder::new(&gate, &["pc.*", "areg", "register_1.out", "areg"]).unwrap();
+    let names = recorder.signal_names();
+    assert_eq!(&names[..4], &["cpu.areg", "cpu.register_1.out", "cpu.pc.in", "cpu.pc.load"]);
+    assert_eq!(names.len(), 12);
+
+    // @12345
+    let mut inputs = PinValues::new();
+    inputs.set_number("inM", 16, 0);
+    inputs.set_number("instruction", 16, 12345);
+    inputs.set_number("reset", 1, 0);
+    recorder.cycle(&mut gate, inputs);
+    let contents = recorder.contents();
+    assert!(contents.contains("$var wire 16 ! areg [15:0] $end\n$scope module register_1 $end\n"));
+    assert!(contents.contains("#1\nb0011000000111001 !\n"), "{}", contents);
+
+    let all = VcdRecorder::new(&gate, &["**"]).unwrap();
+    assert!(all.signal_names().contains(&"cpu.alu.add16.fulladder_14.sum".to_string()));
+}
+
+#[test]
+fn errors() {
+    let gate = GateFactory::new().build("cpu");
+    let error = VcdRecorder::new(&gate, &["register.out"]).err().unwrap();
+    assert_eq!(error.to_string(), "register.out: cpu has no part register");
+    let error = VcdRecorder::new(&gate, &["pc.foo"]).err().unwrap();
+    assert_eq!(error.to_string(), "pc.foo: cpu.pc has no pin foo");
+}
@@ -0,0 +1,346 @@
+use sunho_computer::assembler::assemble;
+use sunho_computer::gates::{Gate, GateFactory, PinValues};
+use sunho_computer::vm::{parse_vm, translate, VmEmulator, VmError, VmFile};
+
+// Runs the translated program on the gate level computer
+fn run(files: &[VmFile], setup: &[(usize, u16)], cycles: usize) -> Gate {
+    let asm = translate(files).unwrap();
+    let words = assemble("test.asm", &asm).unwrap();
+    let mut gate = GateFactory::with_builtin_memory().build("computer");
+    let rom = gate.find_mut("rom32k").unwrap().memory_mut().unwrap();
+    rom[..words.len()].copy_from_slice(&words);
+    let ram = gate.find_mut("ram16k").unwrap().memory_mut().unwrap();
+    for (address, value) in setup {
+        ram[*address] = *value;
+    }
+    for _ in 0..cycles {
+        let mut inputs = PinValues::new();
+        inputs.set("reset", 0, false);
+        gate.run(inputs);
+    }
+    gate
+}
+
+fn ram(gate: &Gate) -> &[u16] {
+    gate.find("ram16k").unwrap().memory().unwrap()
+}
+
+fn file(name: &str, source: &str) -> VmFile {
+    parse_vm(name, source).unwrap()
+}
+
+#[test]
+fn simple_add() {
+    let gate = run(&[file("SimpleAdd.vm", "push constant 7\npush constant 8\nadd\n")], &[(0, 256)], 60);
+    assert_eq!(ram(&gate)[0], 257);
+    assert_eq!(ram(&gate)[256], 15);
+}
+
+#[test]
+fn stack_test() {
+    let source = "
+push constant 17
+push constant 17
+eq
+push constant 17
+push constant 16
+eq
+push constant 16
+push constant 17
+eq
+push constant 892
+push constant 891
+lt
+push constant 891
+push constant 892
+lt
+push constant 891
+push constant 891
+lt
+push constant 32767
+push constant 32766
+gt
+push constant 32766
+push constant 32767
+gt
+push constant 32766
+push constant 32766
+gt
+push constant 57
+push constant 31
+push constant 53
+add
+push constant 112
+sub
+neg
+and
+push constant 82
+or
+not
+";
+    let gate = run(&[file("StackTest.vm", source)], &[(0, 256)], 1000);
+    let expected: [i16; 10] = [-1, 0, 0, 0, -1, 0, -1, 0, 0, -91];
+    assert_eq!(ram(&gate)[0], 266);
+    for (i, value) in expected.iter().enumerate() {
+        assert_eq!(ram(&gate)[256 + i] as i16, *value, "RAM[{}]", 256 + i);
+    }
+}
+
+#[test]
+fn basic_test() {
+    let source = "
+push constant 10
+pop local 0
+push constant 21
+push constant 22
+pop argument 2
+pop argument 1
+push constant 36
+pop this 6
+push constant 42
+push constant 45
+pop that 5
+pop that 2
+push constant 510
+pop temp 6
+push local 0
+push that 5
+add
+push argument 1
+sub
+push this 6
+push this 6
+add
+sub
+push temp 6
+add
+";
+    let gate = run(&[file("BasicTest.vm", source)], &[(0, 256), (1, 300), (2, 400), (3, 3000), (4, 3010)], 600);
+    for (address, value) in [(256, 472), (300, 10), (401, 21), (402, 22), (3006, 36), (3012, 42), (3015, 45), (11, 510)].iter() {
+        assert_eq!(ram(&gate)[*address], *value, "RAM[{}]", address);
+    }
+}
+
+#[test]
+fn pointer_and_static() {
+    let source = "
+push constant 3030
+pop pointer 0
+push constant 3040
+pop pointer 1
+push constant 32
+pop this 2
+push constant 46
+pop that 6
+push pointer 0
+push pointer 1
+add
+push this 2
+sub
+push that 6
+add
+push constant 111
+pop static 3
+push static 3
+";
+    let gate = run(&[file("PointerTest.vm", source)], &[(0, 256)], 600);
+    for (address, value) in [(256, 6084), (257, 111), (3, 3030), (4, 3040), (3032, 32), (3046, 46), (16, 111)].iter() {
+        assert_eq!(ram(&gate)[*address], *value, "RAM[{}]", address);
+    }
+}
+
+#[test]
+fn basic_loop() {
+    let source = "
+push constant 0
+pop local 0
+label LOOP_START
+push argument 0
+push local 0
+add
+pop local 0
+push argument 0
+push constant 1
+sub
+pop argument 0
+push argument 0
+if-goto LOOP_START
+push local 0
+";
+    let gate = run(&[file("BasicLoop.vm", source)], &[(0, 256), (1, 300), (2, 400), (400, 3)], 600);
+    assert_eq!(ram(&gate)[0], 257);
+    assert_eq!(ram(&gate)[256], 6);
+}
+
+#[test]
+fn simple_function() {
+    let source = "
+function SimpleFunction.test 2
+push local 0
+push local 1
+add
+not
+push argument 0
+add
+push argument 1
+sub
+return
+";
+    let setup = [(0, 317), (1, 317), (2, 310), (3, 3000), (4, 4000), (310, 1234), (311, 37), (312, 1000), (313, 305), (314, 300), (315, 3010), (316, 4010)];
+    let gate = run(&[file("SimpleFunction.vm", source)], &setup, 300);
+    for (address, value) in [(0, 311), (1, 305), (2, 300), (3, 3010), (4, 4010), (310, 1196)].iter() {
+        assert_eq!(ram(&gate)[*address], *value, "RAM[{}]", address);
+    }
+}
+
+const SYS_FIBONACCI: &str = "
+function Sys.init 0
+push constant 4
+call Main.fibonacci 1
+label WHILE
+goto WHILE
+";
+
+const MAIN_FIBONACCI: &str = "
+function Main.fibonacci 0
+push argument 0
+push constant 2
+lt
+if-goto IF_TRUE
+goto IF_FALSE
+label IF_TRUE
+push argument 0
+return
+label IF_FALSE
+push argument 0
+push constant 2
+sub
+call Main.fibonacci 1
+push argument 0
+push constant 1
+sub
+call Main.fibonacci 1
+add
+return
+";
+
+#[test]
+fn fibonacci_element() {
+    let files = [file("Main.vm", MAIN_FIBONACCI), file("Sys.vm", SYS_FIBONACCI)];
+    let gate = run(&files, &[], 6000);
+    assert_eq!(ram(&gate)[0], 262);
+    assert_eq!(ram(&gate)[261], 3);
+}
+
+#[test]
+fn statics_test() {
+    let class = |name: &str| format!("
+function {0}.set 0
+push argument 0
+pop static 0
+push argument 1
+pop static 1
+push constant 0
+return
+function {0}.get 0
+push static 0
+push static 1
+sub
+return
+", name);
+    let sys = "
+function Sys.init 0
+push constant 6
+push constant 8
+call Class1.set 2
+pop temp 0
+push constant 23
+push constant 15
+call Class2.set 2
+pop temp 0
+call Class1.get 0
+call Class2.get 0
+label WHILE
+goto WHILE
+";
+    let files = [file("Class1.vm", &class("Class1")), file("Class2.vm", &class("Class2")), file("Sys.vm", sys)];
+    let gate = run(&files, &[], 2500);
+    assert_eq!(ram(&gate)[0], 263);
+    assert_eq!(ram(&gate)[261] as i16, -2);
+    assert_eq!(ram(&gate)[262], 8);
+}
+
+// Pushes a word, constants only go up to 32767
+fn push(value: i16) -> String {
+    match value {
+        -32768 => "push constant 32767\nneg\npush constant 1\nsub\n".to_string(),
+        x if x < 0 => format!("push constant {}\nneg\n", -x),
+        x => format!("push constant {}\n", x)
+    }
+}
+
+// gt and lt compare signed words even when x - y overflows, the same as the
+// vm emulator
+#[test]
+fn signed_comparisons() {
+    let pairs: [(i16, i16); 8] = [
+        (32767, -1), (-1, 32767), (-32768, 1), (1, -32768),
+        (-32768, 32767), (32767, -32768), (-5, -3), (3, 3),
+    ];
+    let mut source = String::new();
+    let mut expected = Vec::new();
+    for (x, y) in pairs.iter() {
+        source += &format!("{}{}gt\n{}{}lt\n", push(*x), push(*y), push(*x), push(*y));
+        expected.push(if x > y { -1 } else { 0 });
+        expected.push(if x < y { -1 } else { 0 });
+    }
+    let files = [file("Compare.vm", &source)];
+    let gate = run(&files, &[(0, 256)], 1500);
+    let mut emulator = VmEmulator::new(&files).unwrap();
+    emulator.ram_mut()[0] = 256;
+    emulator.run(1000).unwrap();
+    assert!(emulator.is_halted());
+    assert_eq!(ram(&gate)[0], 256 + expected.len() as u16);
+    for (i, value) in expected.iter().enumerate() {
+        let (x, y) = pairs[i / 2];
+        let op = if i % 2 == 0 { "gt" } else { "lt" };
+        assert_eq!(ram(&gate)[256 + i] as i16, *value, "{} {} {}", x, op, y);
+        assert_eq!(emulator.ram()[256 + i] as i16, *value, "{} {} {} in the emulator", x, op, y);
+    }
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("push constant\n", 1, "'push' expects 2 arguments"),
+        ("\npop constant 1\n", 2, "cannot pop to constant"),
+        ("push heap 1\n", 1, "unknown segment 'heap'"),
+        ("push temp 8\n", 1, "index 8 is out of range for temp"),
+        ("push pointer 2\n", 1, "index 2 is out of range for pointer"),
+        ("function Main.main x\n", 1, "invalid number 'x'"),
+        ("label 1abc\n", 1, "invalid name '1abc'"),
+        ("// comment\nmul\n", 2, "unknown command 'mul'"),
+    ];
+    for (source, line, message) in cases.iter() {
+        let e = parse_vm("Bad.vm", source).unwrap_err();
+        assert_eq!((e.line, e.message.as_str()), (*line, *message), "{}", source);
+    }
+}
+
+#[test]
+fn undefined_labels() {
+    let main = file("Main.vm", "function Main.main 0\nlabel LOOP\ngoto LOOP\nfunction Main.other 0\nif-goto LOOP\nreturn\n");
+    let e = translate(&[main]).unwrap_err();
+    assert_eq!((e.file.as_str(), e.line, e.message.as_str()), ("Main", 5, "undefined label 'LOOP'"));
+    let main = file("Main.vm", "function Main.main 0\ngoto END\nlabel END\nreturn\n");
+    assert!(translate(&[main]).is_ok());
+}
+
+#[test]
+fn error_positions() {
+    let e = VmError { file: String::new(), line: 0, message: "Sys.init is not defined".to_string() };
+    assert_eq!(e.to_string(), "Sys.init is not defined");
+    let e = VmEmulator::load_path("/nonexistent/Main.vm").err().unwrap();
+    assert!(e.to_string().starts_with("/nonexistent/Main.vm: "), "{}", e);
+    assert_eq!(parse_vm("Bad.vm", "\nmul\n").unwrap_err().to_string(), "Bad.vm:2: unknown command 'mul'");
+}
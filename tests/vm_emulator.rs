@@ -0,0 +1,209 @@
+use sunho_computer::jack::{compile, to_vm};
+use sunho_computer::vm::{parse_vm, VmEmulator, VmFile};
+
+fn jack(class: &str, source: &str) -> VmFile {
+    parse_vm(&format!("{}.vm", class), &to_vm(&compile(class, source).unwrap())).unwrap()
+}
+
+fn boot(files: &[VmFile]) -> VmEmulator {
+    let mut emu = VmEmulator::new(files).unwrap();
+    emu.boot().unwrap();
+    emu
+}
+
+fn press(emu: &mut VmEmulator, key: u16) {
+    emu.set_key(key);
+    emu.run(1000).unwrap();
+    emu.set_key(0);
+    emu.run(1000).unwrap();
+}
+
+#[test]
+fn stack_arithmetic() {
+    let file = parse_vm("Test.vm", "push constant 7\npush constant 8\nadd\npush constant 3\nneg\nlt\n").unwrap();
+    let mut emu = VmEmulator::new(&[file]).unwrap();
+    emu.ram_mut()[0] = 256;
+    emu.run(100).unwrap();
+    assert!(emu.is_halted());
+    assert_eq!(emu.ram()[0], 257);
+    assert_eq!(emu.ram()[256], 0);
+}
+
+#[test]
+fn vm_functions() {
+    let source = "
+function Sys.init 0
+push constant 10
+call Main.fib 1
+pop static 0
+label END
+goto END
+function Main.fib 0
+push argument 0
+push constant 2
+lt
+if-goto BASE
+push argument 0
+push constant 1
+sub
+call Main.fib 1
+push argument 0
+push constant 2
+sub
+call Main.fib 1
+add
+return
+label BASE
+push argument 0
+return
+";
+    let mut emu = boot(&[parse_vm("Sys.vm", source).unwrap()]);
+    emu.run(10000).unwrap();
+    assert_eq!(emu.ram()[16], 55);
+    assert_eq!(emu.call_stack(), vec!["Sys.init"]);
+}
+
+#[test]
+fn native_os() {
+    let source = "
+class Main {
+    function void main() {
+        var Array a;
+        var String s;
+        let a = Array.new(3);
+        let a[2] = Math.sqrt(1000);
+        do Memory.poke(8000, a[2]);
+        do Memory.poke(8001, Math.multiply(-7, 6));
+        do Memory.poke(8002, -100 / 7);
+        do Memory.poke(8003, Math.max(Math.abs(-5), 4));
+        let s = \"-123\";
+        do Memory.poke(8004, s.intValue() + 1);
+        do s.eraseLastChar();
+        do s.appendChar(57);
+        do Memory.poke(8005, s.charAt(3));
+        do Memory.poke(8006, s.length());
+        do s.setInt(4567);
+        do Memory.poke(8007, s.intValue());
+        do Memory.poke(8008, a);
+        do a.dispose();
+        let a = Array.new(2);
+        do Memory.poke(8009, a);
+        return;
+    }
+}
+";
+    let mut emu = boot(&[jack("Main", source)]);
+    emu.run(100000).unwrap();
+    assert!(emu.is_halted());
+    assert_eq!(emu.exit_code(), None);
+    let ram = &emu.ram()[8000..8010];
+    assert_eq!(ram[0], 31);
+    assert_eq!(ram[1] as i16, -42);
+    assert_eq!(ram[2] as i16, -14);
+    assert_eq!(ram[3], 5);
+    assert_eq!(ram[4] as i16, -122);
+    assert_eq!(ram[5], 57);
+    assert_eq!(ram[6], 4);
+    assert_eq!(ram[7], 4567);
+    assert_eq!(ram[8], 2048);
+    assert_eq!(ram[9], 2048);
+}
+
+#[test]
+fn screen_and_output() {
+    let source = "
+class Main {
+    function void main() {
+        do Output.printString(\"Hi\");
+        do Output.println();
+        do Output.printInt(-7);
+        do Screen.drawRectangle(0, 100, 31, 101);
+        do Screen.drawLine(0, 200, 3, 200);
+        do Screen.setColor(false);
+        do Screen.drawPixel(1, 200);
+        return;
+    }
+}
+";
+    let mut emu = boot(&[jack("Main", source)]);
+    emu.run(100000).unwrap();
+    let screen = emu.screen();
+    // 'H' is 51 in its first row, 'i' is 12
+    assert_eq!(screen[0], 51 | 12 << 8);
+    // '-' and '7' on the second text row
+    assert_eq!(screen[(11 + 5) * 32] & 0xff, 63);
+    assert_eq!(screen[11 * 32] >> 8, 63);
+    assert_eq!(screen[100 * 32], 0xffff);
+    assert_eq!(screen[101 * 32 + 1], 0xffff);
+    assert_eq!(screen[200 * 32], 0b1101);
+}
+
+#[test]
+fn vm_class_replaces_native() {
+    let main = "
+class Main {
+    function void main() {
+        do Memory.poke(8000, 3 * 4);
+        do Memory.poke(8001, Math.sqrt(16));
+        return;
+    }
+}
+";
+    let math = "
+function Math.init 0
+push constant 0
+return
+function Math.multiply 0
+push constant 42
+return
+";
+    let mut emu = boot(&[jack("Main", main), parse_vm("Math.vm", math).unwrap()]);
+    let error = emu.run(100000).unwrap_err();
+    assert_eq!(emu.ram()[8000], 42);
+    assert_eq!(error.file, "Main");
+    assert!(error.message.starts_with("undefined function 'Math.sqrt'"), "{}", error.message);
+    assert!(error.message.ends_with("(in Sys.init > Main.main)"), "{}", error.message);
+}
+
+#[test]
+fn sys_error() {
+    let source = "
+class Main {
+    function void main() {
+        do Memory.poke(8000, 1);
+        do Memory.poke(8000, 5 / 0);
+        do Memory.poke(8000, 2);
+        return;
+    }
+}
+";
+    let mut emu = boot(&[jack("Main", source)]);
+    emu.run(100000).unwrap();
+    assert!(emu.is_halted());
+    assert_eq!(emu.exit_code(), Some(3));
+    assert_eq!(emu.ram()[8000], 1);
+    // 'E' is printed at the top left
+    assert_eq!(emu.screen()[0] & 0xff, 63);
+}
+
+#[test]
+fn keyboard() {
+    let source = "
+class Main {
+    function void main() {
+        do Memory.poke(8000, Keyboard.readInt(\"n? \"));
+        return;
+    }
+}
+";
+    let mut emu = boot(&[jack("Main", source)]);
+    emu.run(10000).unwrap();
+    assert!(!emu.is_halted());
+    assert_eq!(emu.call_stack().last(), Some(&"Keyboard.readLine"));
+    for key in [52, 57, 129, 50, 128] {
+        press(&mut emu, key);
+    }
+    emu.run(10000).unwrap();
+    assert!(emu.is_halted());
+    assert_eq!(emu.ram()[8000], 42);
+}